@@ -21,9 +21,11 @@ use m64prs_sys::{api::FullCoreApi, Command, CoreParam, MsgLevel};
 
 use self::save::{SavestateWaitManager, SavestateWaiter};
 
+pub mod cheat;
 pub mod config;
 pub mod emu_state;
 pub mod key_forward;
+pub mod memory;
 pub mod plugin;
 pub mod rom;
 pub mod save;
@@ -0,0 +1,206 @@
+use std::{ffi::CString, str::FromStr};
+
+use crate::error::CheatError;
+
+use super::{core_fn, Core};
+
+/// A GameShark code, made of a code type and address, and a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CheatCode {
+    /// The code type in the top byte, followed by the address.
+    pub address: u32,
+    pub value: u16,
+}
+
+impl CheatCode {
+    /// Type of patch codes, which repeat the following code.
+    const PATCH_TYPE: u8 = 0x50;
+    /// Code types understood by Mupen64Plus. `CC`, `DE`, `EE` and `FF` are hardware setup
+    /// codes, which are accepted but have no effect.
+    const SUPPORTED_TYPES: [u8; 17] = [
+        0x50, 0x80, 0x81, 0x88, 0x89, 0xA0, 0xA1, 0xCC, 0xD0, 0xD1, 0xD2, 0xD3, 0xDE, 0xEE, 0xF0,
+        0xF1, 0xFF,
+    ];
+
+    /// Returns the code type, i.e. the top byte of the address.
+    pub fn code_type(&self) -> u8 {
+        (self.address >> 24) as u8
+    }
+
+    /// Parses a list of codes, one per line. Blank lines are skipped.
+    pub fn parse_list(text: &str) -> Result<Vec<CheatCode>, CheatError> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let codes = lines
+            .iter()
+            .map(|line| line.parse())
+            .collect::<Result<Vec<CheatCode>, _>>()?;
+        Self::validate_list(&codes)
+            .map_err(|index| CheatError::DanglingPatch(lines[index].into()))?;
+        Ok(codes)
+    }
+
+    /// Checks that the list doesn't end with a patch code, as those must be followed by the
+    /// code they repeat. Returns the index of the dangling patch code otherwise.
+    fn validate_list(codes: &[CheatCode]) -> Result<(), usize> {
+        match codes.last() {
+            Some(code) if code.code_type() == Self::PATCH_TYPE => Err(codes.len() - 1),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for CheatCode {
+    type Err = CheatError;
+
+    /// Parses a code of the form `AAAAAAAA VVVV`, where both parts are in hex.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CheatError::InvalidCode(s.to_owned());
+        let mut parts = s.split_whitespace();
+        let (Some(address), Some(value), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let is_hex =
+            |part: &str, len| part.len() == len && part.bytes().all(|b| b.is_ascii_hexdigit());
+        if !is_hex(address, 8) || !is_hex(value, 4) {
+            return Err(invalid());
+        }
+        let code = CheatCode {
+            address: u32::from_str_radix(address, 16).map_err(|_| invalid())?,
+            value: u16::from_str_radix(value, 16).map_err(|_| invalid())?,
+        };
+        match Self::SUPPORTED_TYPES.contains(&code.code_type()) {
+            true => Ok(code),
+            false => Err(CheatError::UnsupportedType(code.code_type())),
+        }
+    }
+}
+
+/// Functions for managing cheats.
+///
+/// Cheats are identified by name, and are cleared by the core when the ROM is closed.
+impl Core {
+    /// Adds a cheat, or replaces the codes of an existing cheat with the same name. The
+    /// cheat starts enabled.
+    pub fn add_cheat(&self, name: &str, codes: &[CheatCode]) -> Result<(), CheatError> {
+        let c_name = cheat_name(name)?;
+        if codes.is_empty() {
+            return Err(CheatError::NoCodes);
+        }
+        CheatCode::validate_list(codes)
+            .map_err(|index| CheatError::DanglingPatch(format!("{:08X}", codes[index].address)))?;
+
+        let mut ffi_codes: Vec<m64prs_sys::CheatCode> = codes
+            .iter()
+            .map(|code| m64prs_sys::CheatCode {
+                address: code.address,
+                value: code.value as _,
+            })
+            .collect();
+        // SAFETY: the core copies the name and codes before returning.
+        core_fn(unsafe {
+            (self.api.cheat.add_cheat)(
+                c_name.as_ptr(),
+                ffi_codes.as_mut_ptr(),
+                ffi_codes.len() as _,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Enables or disables a cheat added with [`Core::add_cheat`].
+    pub fn set_cheat_enabled(&self, name: &str, enabled: bool) -> Result<(), CheatError> {
+        let c_name = cheat_name(name)?;
+        // SAFETY: the name is only read during the call.
+        core_fn(unsafe { (self.api.cheat.cheat_enabled)(c_name.as_ptr(), enabled as _) })?;
+        Ok(())
+    }
+
+    /// Removes a cheat.
+    ///
+    /// Mupen64Plus can't forget a cheat until the ROM is closed, so this disables it. Adding
+    /// a cheat with the same name replaces it.
+    pub fn remove_cheat(&self, name: &str) -> Result<(), CheatError> {
+        self.set_cheat_enabled(name, false)
+    }
+}
+
+fn cheat_name(name: &str) -> Result<CString, CheatError> {
+    match name.is_empty() {
+        true => Err(CheatError::InvalidName(name.to_owned())),
+        false => CString::new(name).map_err(|_| CheatError::InvalidName(name.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::CheatError;
+
+    use super::CheatCode;
+
+    #[test]
+    fn parse_code() {
+        assert_eq!(
+            "8033B21E 0008".parse::<CheatCode>().unwrap(),
+            CheatCode {
+                address: 0x8033_B21E,
+                value: 0x0008,
+            }
+        );
+        assert_eq!(
+            "  d033afa1\t0020 ".parse::<CheatCode>().unwrap(),
+            CheatCode {
+                address: 0xD033_AFA1,
+                value: 0x0020,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_rejects_malformed() {
+        for code in [
+            "",
+            "8033B21E",
+            "8033B21E 0008 0000",
+            "8033B21 0008",
+            "8033B21E 008",
+            "+033B21E 0008",
+            "8033B21G 0008",
+        ] {
+            assert!(
+                matches!(code.parse::<CheatCode>(), Err(CheatError::InvalidCode(_))),
+                "{code:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unsupported_type() {
+        assert!(matches!(
+            "4033B21E 0008".parse::<CheatCode>(),
+            Err(CheatError::UnsupportedType(0x40))
+        ));
+    }
+
+    #[test]
+    fn parse_list_rejects_dangling_patch() {
+        let err = CheatCode::parse_list("\n8033B21E 0008\n\n50000402 0000\n").unwrap_err();
+        assert!(matches!(err, CheatError::DanglingPatch(line) if line == "50000402 0000"));
+        assert_eq!(
+            CheatCode::parse_list("50000402 0000\n8033B21E 0008").unwrap(),
+            [
+                CheatCode {
+                    address: 0x5000_0402,
+                    value: 0x0000,
+                },
+                CheatCode {
+                    address: 0x8033_B21E,
+                    value: 0x0008,
+                },
+            ]
+        );
+    }
+}
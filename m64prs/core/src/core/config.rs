@@ -33,6 +33,22 @@ impl Core {
         }
     }
 
+    /// Returns the directory where Mupen64Plus stores user data (e.g. save files).
+    pub fn cfg_user_data_path(&self) -> Option<PathBuf> {
+        let path_ptr = unsafe { (self.api.config.user_data_path)() };
+        if path_ptr.is_null() {
+            None
+        } else {
+            // SAFETY: Mupen should return a valid pointer.
+            Some(
+                unsafe { CStr::from_ptr(path_ptr) }
+                    .to_string_lossy()
+                    .to_string()
+                    .into(),
+            )
+        }
+    }
+
     /// Runs the provided callback once per available config section.
     pub fn cfg_for_each_section<F: FnMut(&CStr)>(
         &mut self,
@@ -2,6 +2,7 @@ use std::{
     ffi::{c_int, c_void},
     future::Future,
     pin::Pin,
+    ptr::null_mut,
     sync::mpsc,
     task::{Context, Poll},
 };
@@ -14,8 +15,40 @@ use crate::error::M64PError;
 
 use super::Core;
 
+/// Handle for controlling emulation from core callbacks.
+///
+/// Unlike [`Core`], this can be held by the callbacks themselves, so that they can
+/// pause or reset the emulator on an exact frame.
+#[derive(Debug, Clone, Copy)]
+pub struct EmuControlHandle {
+    do_command: unsafe extern "C" fn(Command, c_int, *mut c_void) -> m64prs_sys::Error,
+}
+
+impl EmuControlHandle {
+    /// Requests that the core pause execution of the current ROM. If called from a core
+    /// callback, the emulator pauses before running the next frame.
+    pub fn request_pause(&self) -> Result<(), M64PError> {
+        // SAFETY: pausing doesn't borrow any data, and can be requested from any thread.
+        super::core_fn(unsafe { (self.do_command)(Command::Pause, 0, null_mut()) })
+    }
+
+    /// Requests that the core reset the current ROM. If called from a core callback, the
+    /// reset happens once the callback returns.
+    pub fn request_reset(&self, hard: bool) -> Result<(), M64PError> {
+        // SAFETY: resetting doesn't borrow any data, and can be requested from any thread.
+        super::core_fn(unsafe { (self.do_command)(Command::Reset, hard as c_int, null_mut()) })
+    }
+}
+
 // Asynchronous core commands
 impl Core {
+    /// Creates a handle for pausing or resetting the emulator from core callbacks.
+    pub fn control_handle(&self) -> EmuControlHandle {
+        EmuControlHandle {
+            do_command: self.api.base.do_command,
+        }
+    }
+
     /// Requests that the core stop execution of the current ROM.
     /// To await the core stopping, use [`Core::emu_state_change`]
     pub fn request_stop(&self) -> Result<(), M64PError> {
@@ -38,6 +71,20 @@ impl Core {
         self.do_command_i(Command::Reset, hard as c_int)
     }
 
+    /// Enables or disables the speed limiter. With it disabled, the emulator runs as fast
+    /// as it can.
+    pub fn set_speed_limiter(&self, enabled: bool) -> Result<(), M64PError> {
+        let mut value = enabled as c_int;
+        // SAFETY: the core only reads the value during the call.
+        unsafe {
+            self.do_command_ip(
+                Command::CoreStateSet,
+                CoreParam::SpeedLimiter as c_int,
+                &mut value as *mut _ as *mut c_void,
+            )
+        }
+    }
+
     /// Queries the emulator's current state.
     pub fn emu_state(&self) -> EmuState {
         unsafe {
@@ -0,0 +1,332 @@
+use std::{
+    ffi::{c_int, c_uchar, c_uint, c_ulonglong, c_ushort, c_void},
+    mem, ptr, slice,
+};
+
+use m64prs_sys::{common::M64PError, debug::DbgMemPtrType, CoreCaps};
+
+use super::{core_fn, Core};
+
+/// Size of RDRAM with the expansion pak.
+const RDRAM_SIZE: usize = 0x80_0000;
+/// Size of RDRAM without the expansion pak.
+const RDRAM_SIZE_NO_EXPANSION: usize = 0x40_0000;
+
+/// Base of the KSEG0 and KSEG1 segments, which map directly to physical memory.
+const KSEG_BASE: u32 = 0x8000_0000;
+/// Mask selecting the segment of a virtual address.
+const SEGMENT_MASK: u32 = 0xC000_0000;
+/// Mask converting a KSEG0/KSEG1 address to a physical address.
+const PHYSICAL_MASK: u32 = 0x1FFF_FFFF;
+
+/// Handle for reading and writing emulated memory from core callbacks.
+///
+/// This can be sent to other threads, but memory should only be accessed while the core
+/// is running and the emulator thread is stopped in a callback, e.g. a
+/// [`FrameHandler`][super::tas_callbacks::FrameHandler]. Accessing it at any other time
+/// races with the emulator.
+///
+/// Addresses are virtual addresses as seen by the N64's CPU, and values are read and
+/// written in the N64's (big-endian) byte order. Addresses in KSEG0 and KSEG1 that map to
+/// RDRAM are accessed directly. Other addresses go through the core's debugger functions,
+/// which are only available if the core was built with the debugger.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryHandle {
+    get_pointer: unsafe extern "C" fn(DbgMemPtrType) -> *mut c_void,
+    virtual_to_physical: unsafe extern "C" fn(c_uint) -> c_uint,
+    read_64: unsafe extern "C" fn(c_uint) -> c_ulonglong,
+    read_32: unsafe extern "C" fn(c_uint) -> c_uint,
+    read_16: unsafe extern "C" fn(c_uint) -> c_ushort,
+    read_8: unsafe extern "C" fn(c_uint) -> c_uchar,
+    write_64: unsafe extern "C" fn(c_uint, c_ulonglong),
+    write_32: unsafe extern "C" fn(c_uint, c_uint),
+    write_16: unsafe extern "C" fn(c_uint, c_ushort),
+    write_8: unsafe extern "C" fn(c_uint, c_uchar),
+    rdram_size: usize,
+    /// True if the core was built with the debugger. Without it, the debugger functions
+    /// above are stubs that do nothing.
+    debugger: bool,
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A value that can be read from or written to emulated memory.
+pub trait MemoryValue: Copy + sealed::Sealed {
+    /// Size of the value in bytes. Addresses must be aligned to this.
+    const SIZE: u32;
+
+    #[doc(hidden)]
+    fn from_raw(raw: u64) -> Self;
+    #[doc(hidden)]
+    fn into_raw(self) -> u64;
+}
+
+macro_rules! impl_memory_value {
+    (int $($ty:ty;)*) => {$(
+        impl sealed::Sealed for $ty {}
+        impl MemoryValue for $ty {
+            const SIZE: u32 = mem::size_of::<$ty>() as u32;
+
+            fn from_raw(raw: u64) -> Self {
+                raw as $ty
+            }
+            fn into_raw(self) -> u64 {
+                self as u64
+            }
+        }
+    )*};
+    (float $($ty:ty;)*) => {$(
+        impl sealed::Sealed for $ty {}
+        impl MemoryValue for $ty {
+            const SIZE: u32 = mem::size_of::<$ty>() as u32;
+
+            fn from_raw(raw: u64) -> Self {
+                <$ty>::from_bits(raw as _)
+            }
+            fn into_raw(self) -> u64 {
+                self.to_bits() as u64
+            }
+        }
+    )*};
+}
+
+impl_memory_value! { int
+    u8; i8; u16; i16; u32; i32; u64; i64;
+}
+impl_memory_value! { float
+    f32; f64;
+}
+
+impl MemoryHandle {
+    /// Returns the size of RDRAM in bytes.
+    pub fn rdram_size(&self) -> usize {
+        self.rdram_size
+    }
+
+    /// Returns the contents of RDRAM, or `None` if no ROM is running.
+    ///
+    /// RDRAM is stored as native-endian 32-bit words, so byte addresses are
+    /// swizzled on little-endian hosts.
+    ///
+    /// # Safety
+    /// This must only be called from a core callback running on the emulator thread.
+    /// The returned slice must not be held past the end of the callback.
+    pub unsafe fn rdram(&self) -> Option<&[u8]> {
+        let ptr = (self.get_pointer)(DbgMemPtrType::Rdram) as *const u8;
+        if ptr.is_null() {
+            return None;
+        }
+        Some(slice::from_raw_parts(ptr, self.rdram_size))
+    }
+
+    /// Reads a value from emulated memory.
+    ///
+    /// # Errors
+    /// Fails with [`M64PError::NotInit`] if no ROM is running, [`M64PError::InputInvalid`]
+    /// if `address` isn't aligned to the value's size, or [`M64PError::Unsupported`] if
+    /// `address` isn't in RDRAM and the core was built without the debugger.
+    pub fn read<T: MemoryValue>(&self, address: u32) -> Result<T, M64PError> {
+        let rdram = self.check_access(address, T::SIZE)?;
+        if let Some(offset) = self.rdram_offset(address, T::SIZE as usize) {
+            let raw = (0..T::SIZE as usize).fold(0u64, |raw, i| {
+                // SAFETY: rdram_offset checked that the range lies within RDRAM.
+                (raw << 8) | u64::from(unsafe { ptr::read(rdram.add(swizzle(offset + i))) })
+            });
+            return Ok(T::from_raw(raw));
+        }
+        self.check_debugger()?;
+        // SAFETY: the core decodes the address itself, and ignores unmapped addresses.
+        let raw = unsafe {
+            match T::SIZE {
+                1 => u64::from((self.read_8)(address)),
+                2 => u64::from((self.read_16)(address)),
+                4 => u64::from((self.read_32)(address)),
+                _ => (self.read_64)(address),
+            }
+        };
+        Ok(T::from_raw(raw))
+    }
+
+    /// Writes a value to emulated memory.
+    ///
+    /// # Errors
+    /// See [`MemoryHandle::read`].
+    pub fn write<T: MemoryValue>(&self, address: u32, value: T) -> Result<(), M64PError> {
+        let rdram = self.check_access(address, T::SIZE)?;
+        let raw = value.into_raw();
+        // Writes go through the core when possible, so that it can invalidate recompiled code.
+        if !self.debugger {
+            let offset = self
+                .rdram_offset(address, T::SIZE as usize)
+                .ok_or(M64PError::Unsupported)?;
+            for i in 0..T::SIZE as usize {
+                let byte = (raw >> (8 * (T::SIZE as usize - 1 - i))) as u8;
+                // SAFETY: rdram_offset checked that the range lies within RDRAM.
+                unsafe { ptr::write(rdram.cast_mut().add(swizzle(offset + i)), byte) };
+            }
+            return Ok(());
+        }
+        // SAFETY: the core decodes the address itself, and ignores unmapped addresses.
+        unsafe {
+            match T::SIZE {
+                1 => (self.write_8)(address, raw as c_uchar),
+                2 => (self.write_16)(address, raw as c_ushort),
+                4 => (self.write_32)(address, raw as c_uint),
+                _ => (self.write_64)(address, raw),
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies emulated memory starting at `address` into `buf`, one byte per address.
+    ///
+    /// # Errors
+    /// See [`MemoryHandle::read`].
+    pub fn read_range(&self, address: u32, buf: &mut [u8]) -> Result<(), M64PError> {
+        let rdram = self.check_access(address, 1)?;
+        match self.rdram_offset(address, buf.len()) {
+            Some(offset) => {
+                for (i, byte) in buf.iter_mut().enumerate() {
+                    // SAFETY: rdram_offset checked that the range lies within RDRAM.
+                    *byte = unsafe { ptr::read(rdram.add(swizzle(offset + i))) };
+                }
+            }
+            None => {
+                for (byte_address, byte) in (address..).zip(buf.iter_mut()) {
+                    *byte = self.read(byte_address)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies `data` into emulated memory starting at `address`.
+    ///
+    /// # Errors
+    /// See [`MemoryHandle::read`].
+    pub fn write_range(&self, address: u32, data: &[u8]) -> Result<(), M64PError> {
+        self.check_access(address, 1)?;
+        if !self.debugger && self.rdram_offset(address, data.len()).is_none() {
+            return Err(M64PError::Unsupported);
+        }
+        for (byte_address, &byte) in (address..).zip(data) {
+            self.write(byte_address, byte)?;
+        }
+        Ok(())
+    }
+
+    /// Translates a virtual address to a physical address, using the TLB if needed.
+    ///
+    /// # Errors
+    /// Fails with [`M64PError::NotInit`] if no ROM is running,
+    /// [`M64PError::InputInvalid`] if the address isn't mapped, or
+    /// [`M64PError::Unsupported`] if the address needs the TLB and the core was built
+    /// without the debugger.
+    pub fn virtual_to_physical(&self, address: u32) -> Result<u32, M64PError> {
+        self.check_access(address, 1)?;
+        if address & SEGMENT_MASK == KSEG_BASE {
+            return Ok(address & PHYSICAL_MASK);
+        }
+        self.check_debugger()?;
+        // SAFETY: the core only reads CPU state to translate the address.
+        match unsafe { (self.virtual_to_physical)(address) } {
+            // The core returns 0 for unmapped addresses.
+            0 => Err(M64PError::InputInvalid),
+            physical => Ok(physical),
+        }
+    }
+
+    /// Checks that a ROM is running and that `address` is aligned to `size`, returning a
+    /// pointer to RDRAM.
+    fn check_access(&self, address: u32, size: u32) -> Result<*const u8, M64PError> {
+        // SAFETY: this only returns a pointer, and doesn't access memory.
+        let rdram = unsafe { (self.get_pointer)(DbgMemPtrType::Rdram) } as *const u8;
+        if rdram.is_null() {
+            return Err(M64PError::NotInit);
+        }
+        if address.checked_rem(size) != Some(0) {
+            return Err(M64PError::InputInvalid);
+        }
+        Ok(rdram)
+    }
+
+    /// Checks that the core's debugger functions can be used.
+    fn check_debugger(&self) -> Result<(), M64PError> {
+        match self.debugger {
+            true => Ok(()),
+            false => Err(M64PError::Unsupported),
+        }
+    }
+
+    /// Returns the RDRAM offset of a range of `len` bytes starting at `address`, if the
+    /// whole range is directly mapped to RDRAM.
+    fn rdram_offset(&self, address: u32, len: usize) -> Option<usize> {
+        if address & SEGMENT_MASK != KSEG_BASE {
+            return None;
+        }
+        let offset = (address & PHYSICAL_MASK) as usize;
+        (offset.checked_add(len)? <= self.rdram_size).then_some(offset)
+    }
+}
+
+/// Converts an RDRAM byte offset to an index into the core's RDRAM, which is stored as
+/// native-endian 32-bit words.
+fn swizzle(offset: usize) -> usize {
+    if cfg!(target_endian = "little") {
+        offset ^ 3
+    } else {
+        offset
+    }
+}
+
+/// Functions dealing with emulated memory.
+impl Core {
+    /// Creates a handle for reading and writing emulated memory. The RDRAM size is read from
+    /// the core's configuration, so this should be called after a ROM is opened.
+    pub fn memory_handle(&self) -> MemoryHandle {
+        let disable_extra_mem = self
+            .cfg_open(c"Core")
+            .ok()
+            .and_then(|section| section.get_cast_or(false, c"DisableExtraMem").ok())
+            .unwrap_or(false);
+
+        MemoryHandle {
+            get_pointer: self.api.debug.mem_get_pointer,
+            virtual_to_physical: self.api.debug.virtual_to_physical,
+            read_64: self.api.debug.mem_read_64,
+            read_32: self.api.debug.mem_read_32,
+            read_16: self.api.debug.mem_read_16,
+            read_8: self.api.debug.mem_read_8,
+            write_64: self.api.debug.mem_write_64,
+            write_32: self.api.debug.mem_write_32,
+            write_16: self.api.debug.mem_write_16,
+            write_8: self.api.debug.mem_write_8,
+            rdram_size: match disable_extra_mem {
+                true => RDRAM_SIZE_NO_EXPANSION,
+                false => RDRAM_SIZE,
+            },
+            debugger: self.capabilities().contains(CoreCaps::DEBUGGER),
+        }
+    }
+
+    /// Returns the features the core was built with.
+    fn capabilities(&self) -> CoreCaps {
+        let mut capabilities: c_int = 0;
+        // SAFETY: the core only writes to the pointers passed, and null pointers are skipped.
+        let result = core_fn(unsafe {
+            (self.api.base.get_version)(
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut capabilities,
+            )
+        });
+        match result {
+            Ok(()) => CoreCaps::from_bits_retain(capabilities as u32),
+            Err(_) => CoreCaps::empty(),
+        }
+    }
+}
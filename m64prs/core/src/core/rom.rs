@@ -1,6 +1,7 @@
 use std::{
-    ffi::{c_int, c_void},
+    ffi::{c_int, c_void, CStr, CString},
     mem,
+    path::PathBuf,
 };
 
 use m64prs_sys::{common::M64PError, Command, RomHeader, RomSettings};
@@ -60,3 +61,103 @@ impl Core {
         }
     }
 }
+
+/// Paths to the save memory files used by the currently open ROM.
+#[derive(Debug, Clone)]
+pub struct SaveRamPaths {
+    pub eeprom: PathBuf,
+    pub sram: PathBuf,
+    pub flashram: PathBuf,
+    pub mempak: PathBuf,
+}
+
+impl SaveRamPaths {
+    /// File extensions used by Mupen64Plus for each type of save memory, in the
+    /// same order as [`SaveRamPaths::iter`].
+    pub const EXTENSIONS: [&'static str; 4] = ["eep", "sra", "fla", "mpk"];
+
+    /// Iterates over all save memory paths.
+    pub fn iter(&self) -> impl Iterator<Item = &PathBuf> {
+        [&self.eeprom, &self.sram, &self.flashram, &self.mempak].into_iter()
+    }
+}
+
+/// Functions for locating files associated with the current ROM.
+impl Core {
+    /// Determines where the core stores save memory for the currently open ROM.
+    ///
+    /// This follows the same rules Mupen64Plus uses internally, taking
+    /// `SaveSRAMPath` and `SaveFilenameFormat` from the `Core` config section into account.
+    pub fn save_ram_paths(&self) -> Result<SaveRamPaths, M64PError> {
+        let rom_header = self.rom_header()?;
+        let rom_settings = self.rom_settings()?;
+
+        let (save_dir, name_format) = {
+            let sect = self.cfg_open(c"Core")?;
+            let save_dir = sect
+                .get_cast_or(CString::default(), c"SaveSRAMPath")
+                .unwrap_or_default();
+            let name_format = sect.get_cast_or(1, c"SaveFilenameFormat").unwrap_or(1);
+            (save_dir, name_format)
+        };
+
+        let save_dir: PathBuf = if save_dir.is_empty() {
+            self.cfg_user_data_path()
+                .ok_or(M64PError::Internal)?
+                .join("save")
+        } else {
+            save_dir.to_string_lossy().into_owned().into()
+        };
+
+        // The header name is space-padded and not necessarily null-terminated.
+        let header_name = {
+            let len = rom_header
+                .Name
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(rom_header.Name.len());
+            String::from_utf8_lossy(&rom_header.Name[..len])
+                .trim_end()
+                .to_owned()
+        };
+
+        let file_name = if name_format == 0 {
+            header_name
+        } else {
+            // SAFETY: Mupen null-terminates both of these strings.
+            let (good_name, md5) = unsafe {
+                (
+                    CStr::from_ptr(rom_settings.goodname.as_ptr()).to_string_lossy(),
+                    CStr::from_ptr(rom_settings.MD5.as_ptr()).to_string_lossy(),
+                )
+            };
+            let md5: String = md5.chars().take(8).collect();
+
+            if !good_name.contains("(unknown rom)") {
+                let good_name: String = good_name.chars().take(32).collect();
+                format!("{}-{}", good_name, md5)
+            } else if !header_name.is_empty() {
+                format!("{}-{}", header_name, md5)
+            } else {
+                format!("unknown-{}", md5)
+            }
+        };
+        let file_name: String = file_name
+            .chars()
+            .map(|c| match c {
+                ':' | '<' | '>' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+                c => c,
+            })
+            .collect();
+
+        let [eeprom, sram, flashram, mempak] =
+            SaveRamPaths::EXTENSIONS.map(|ext| save_dir.join(format!("{}.{}", file_name, ext)));
+
+        Ok(SaveRamPaths {
+            eeprom,
+            sram,
+            flashram,
+            mempak,
+        })
+    }
+}
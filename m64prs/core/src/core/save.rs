@@ -1,8 +1,14 @@
 use std::{
+    env,
     ffi::{c_int, CString},
-    path::Path,
+    fs,
+    path::{Path, PathBuf},
     pin::Pin,
-    sync::mpsc,
+    process,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc,
+    },
     task::{Context, Poll},
 };
 
@@ -62,6 +68,28 @@ impl Core {
         .await
     }
 
+    /// Saves game state into memory.
+    ///
+    /// The core can only write savestates to disk, so the state passes through a temporary file.
+    pub async fn save_data(&self, format: SavestateFormat) -> Result<Vec<u8>, SavestateError> {
+        let path = temp_state_path();
+        let result = match self.save_file(&path, format).await {
+            Ok(()) => fs::read(&path).map_err(SavestateError::Io),
+            Err(err) => Err(err),
+        };
+        let _ = fs::remove_file(&path);
+        result
+    }
+
+    /// Loads game state from memory, as saved by [`Core::save_data`].
+    pub async fn load_data(&self, data: &[u8]) -> Result<(), SavestateError> {
+        let path = temp_state_path();
+        fs::write(&path, data).map_err(SavestateError::Io)?;
+        let result = self.load_file(&path).await;
+        let _ = fs::remove_file(&path);
+        result
+    }
+
     fn save_op_inner<F: FnOnce() -> Result<(), M64PError>>(
         &self,
         param: CoreParam,
@@ -88,6 +116,13 @@ impl Core {
     }
 }
 
+/// Returns a unique path for passing an in-memory savestate to or from the core.
+fn temp_state_path() -> PathBuf {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+    env::temp_dir().join(format!("m64prs-{}-{}.st", process::id(), id))
+}
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, IntoPrimitive, TryFromPrimitive)]
 pub enum SavestateFormat {
@@ -27,6 +27,10 @@ pub enum SavestateError {
     /// An error occurred while saving or loading the savestate.
     #[error("savestate save/load failed")]
     SaveLoad,
+
+    /// An error occurred while passing an in-memory savestate through a temporary file.
+    #[error("I/O error on temporary savestate file: {0}")]
+    Io(#[source] std::io::Error),
 }
 
 /// Error that may occur during plugin loading.
@@ -76,3 +80,32 @@ impl From<WrongConfigType> for ConfigGetError {
         Self::WrongConfigType(value)
     }
 }
+
+/// Error that may occur when parsing or adding a cheat.
+#[derive(Debug, Error)]
+pub enum CheatError {
+    /// A line isn't of the form `AAAAAAAA VVVV`, with a hex address and value.
+    #[error("invalid GameShark code {0:?}")]
+    InvalidCode(String),
+    /// A code's type isn't supported by Mupen64Plus.
+    #[error("unsupported GameShark code type {0:02X}")]
+    UnsupportedType(u8),
+    /// A patch code isn't followed by the code it repeats.
+    #[error("patch code {0:?} must be followed by the code it repeats")]
+    DanglingPatch(String),
+    /// The cheat has no codes.
+    #[error("cheat has no codes")]
+    NoCodes,
+    /// The cheat's name is empty or contains a null byte.
+    #[error("invalid cheat name {0:?}")]
+    InvalidName(String),
+    /// An error occurred within Mupen64Plus.
+    #[error("M64+ error: {0}")]
+    M64P(#[source] M64PError),
+}
+
+impl From<M64PError> for CheatError {
+    fn from(value: M64PError) -> Self {
+        Self::M64P(value)
+    }
+}
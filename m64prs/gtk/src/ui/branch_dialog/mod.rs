@@ -0,0 +1,184 @@
+mod inner {
+    use std::cell::RefCell;
+
+    use futures::channel::oneshot;
+    use glib::subclass::InitializingObject;
+    use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
+    use tr::tr;
+
+    use crate::ui::core::BranchInfo;
+
+    use super::BranchCommand;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(file = "mod.ui")]
+    pub struct BranchDialog {
+        #[template_child]
+        name_field: TemplateChild<gtk::Entry>,
+        #[template_child]
+        branch_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        comparison_label: TemplateChild<gtk::Label>,
+
+        command_tx: RefCell<Option<oneshot::Sender<Option<BranchCommand>>>>,
+    }
+
+    #[m64prs_gtk_utils::forward_wrapper(super::BranchDialog, vis = pub(in super::super))]
+    impl BranchDialog {
+        /// Shows the dialog if needed, then waits for the user to pick a command.
+        /// # Return value
+        /// `None` once the dialog is closed.
+        pub(super) async fn next_command(
+            &self,
+            transient_for: Option<&impl IsA<gtk::Window>>,
+        ) -> Option<BranchCommand> {
+            let (tx, rx) = oneshot::channel();
+            *self.command_tx.borrow_mut() = Some(tx);
+
+            if !self.obj().is_visible() {
+                self.obj().set_transient_for(transient_for);
+                self.obj().present();
+            }
+            rx.await.unwrap_or(None)
+        }
+
+        /// Replaces the listed branches. `frame_size` is the number of input samples per
+        /// frame, used to show each branch's frame.
+        pub(super) fn set_branches(&self, branches: &[BranchInfo], frame_size: u32) {
+            let selected = self.branch_list.selected_row().map(|row| row.index());
+            self.branch_list.remove_all();
+            for branch in branches {
+                let label = gtk::Label::new(Some(&tr!(
+                    "branch_dialog" => "{} (frame {}, VI {})",
+                    branch.name,
+                    branch.index / frame_size.max(1),
+                    branch.vi_count
+                )));
+                label.set_xalign(0.0);
+                self.branch_list.append(&label);
+            }
+            if let Some(row) = selected.and_then(|index| self.branch_list.row_at_index(index)) {
+                self.branch_list.select_row(Some(&row));
+            }
+        }
+
+        /// Shows the result of the last comparison.
+        pub(super) fn set_comparison(&self, text: &str) {
+            self.comparison_label.set_text(text);
+        }
+    }
+
+    impl BranchDialog {
+        fn send(&self, command: Option<BranchCommand>) {
+            if let Some(tx) = self.command_tx.take() {
+                let _ = tx.send(command);
+            }
+        }
+
+        fn send_selected(&self, f: fn(usize) -> BranchCommand) {
+            if let Some(row) = self.branch_list.selected_row() {
+                self.send(Some(f(row.index() as usize)));
+            }
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl BranchDialog {
+        #[template_callback]
+        fn create_clicked(&self, _: &gtk::Button) {
+            let mut name = self.name_field.text().trim().to_owned();
+            if name.is_empty() {
+                let count = self.branch_list.observe_children().n_items();
+                name = tr!("branch_dialog" => "Branch {}", count + 1);
+            }
+            self.name_field.set_text("");
+            self.send(Some(BranchCommand::Create(name)));
+        }
+
+        #[template_callback]
+        fn load_clicked(&self, _: &gtk::Button) {
+            self.send_selected(BranchCommand::Load);
+        }
+
+        #[template_callback]
+        fn compare_clicked(&self, _: &gtk::Button) {
+            self.send_selected(BranchCommand::Compare);
+        }
+
+        #[template_callback]
+        fn promote_clicked(&self, _: &gtk::Button) {
+            self.send_selected(BranchCommand::Promote);
+        }
+
+        #[template_callback]
+        fn remove_clicked(&self, _: &gtk::Button) {
+            self.send_selected(BranchCommand::Remove);
+        }
+
+        #[template_callback]
+        fn close_clicked(&self, _: &gtk::Button) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BranchDialog {
+        const NAME: &'static str = "M64PRS_BranchDialog";
+        type Type = super::BranchDialog;
+        type ParentType = gtk::Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for BranchDialog {
+        fn dispose(&self) {
+            self.dispose_template();
+        }
+    }
+    impl WidgetImpl for BranchDialog {}
+    impl WindowImpl for BranchDialog {
+        fn close_request(&self) -> glib::Propagation {
+            self.send(None);
+            glib::Propagation::Proceed
+        }
+    }
+}
+
+/// An action chosen in the [`BranchDialog`]. Branches are identified by their position
+/// in the list.
+#[derive(Debug, Clone)]
+pub enum BranchCommand {
+    Create(String),
+    Load(usize),
+    Compare(usize),
+    Promote(usize),
+    Remove(usize),
+}
+
+glib::wrapper! {
+    /// Window for managing the active movie's branches.
+    pub struct BranchDialog(ObjectSubclass<inner::BranchDialog>)
+    @extends
+        gtk::Window,
+        gtk::Widget,
+    @implements
+        gtk::Accessible,
+        gtk::Buildable,
+        gtk::ConstraintTarget,
+        gtk::Native,
+        gtk::Root,
+        gtk::ShortcutManager;
+}
+
+impl BranchDialog {
+    pub(super) fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,136 @@
+//! Cheats, the databases they are read from, and the files custom cheats are stored in.
+
+use std::{fs, io, path::PathBuf};
+
+use m64prs_sys::RomHeader;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::paths::{CONFIG_DIR, INSTALL_DIRS};
+
+/// Placeholder for a value chosen from a cheat's options.
+const OPTION_PLACEHOLDER: &str = "????";
+
+/// A named list of GameShark codes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cheat {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The codes, one per line, in the form accepted by
+    /// [`CheatCode::parse_list`][m64prs_core::cheat::CheatCode::parse_list].
+    pub codes: String,
+}
+
+/// Returns the key identifying a ROM in a cheat database.
+pub fn rom_key(header: &RomHeader) -> String {
+    format!(
+        "{:08X}-{:08X}-C:{:02X}",
+        header.CRC1,
+        header.CRC2,
+        header.Country_code & 0xFF
+    )
+}
+
+/// Reads the cheats for one ROM from a Mupen64Plus cheat database (`mupencheat.txt`).
+///
+/// Each ROM's section starts with a `crc <key>` line, followed by cheats starting with
+/// `cn <name>`. A cheat's lines are an optional `cd <description>`, then its codes. Codes
+/// with the value `????` are followed by a list of options such as `0001:"One",0002:"Two"`;
+/// these cheats are read as one cheat per option.
+pub fn parse_database(text: &str, rom_key: &str) -> Vec<Cheat> {
+    let mut cheats = Vec::new();
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.strip_prefix("crc ").map(str::trim) != Some(rom_key))
+        .skip(1)
+        .take_while(|line| !line.starts_with("crc "))
+        .peekable();
+
+    while let Some(line) = lines.next() {
+        let Some(name) = line.strip_prefix("cn ") else {
+            continue;
+        };
+        let mut cheat = Cheat {
+            name: name.trim().to_owned(),
+            description: String::new(),
+            codes: String::new(),
+        };
+        let mut options = Vec::new();
+        while let Some(line) = lines.next_if(|line| !line.starts_with("cn ")) {
+            if let Some(description) = line.strip_prefix("cd ") {
+                cheat.description = description.trim().to_owned();
+                continue;
+            }
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let (Some(address), Some(value)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            if value == OPTION_PLACEHOLDER {
+                options = parse_options(parts.next().unwrap_or_default());
+            }
+            cheat.codes.push_str(&format!("{} {}\n", address, value));
+        }
+
+        if options.is_empty() {
+            cheats.push(cheat);
+            continue;
+        }
+        for (value, option) in options {
+            cheats.push(Cheat {
+                name: format!("{} ({})", cheat.name, option),
+                description: cheat.description.clone(),
+                codes: cheat.codes.replace(OPTION_PLACEHOLDER, &value),
+            });
+        }
+    }
+    cheats
+}
+
+/// Parses a list of options, such as `0001:"One",0002:"Two"`.
+fn parse_options(text: &str) -> Vec<(String, String)> {
+    let mut options = Vec::new();
+    let mut rest = text.trim();
+    while let Some((value, after)) = rest.split_once(":\"") {
+        let Some((name, after)) = after.split_once('"') else {
+            break;
+        };
+        options.push((value.trim().to_owned(), name.to_owned()));
+        rest = after.trim_start_matches(',').trim();
+    }
+    options
+}
+
+/// Loads the cheats for a ROM from the cheat database in the data directory.
+pub fn load_database(rom_key: &str) -> io::Result<Vec<Cheat>> {
+    match fs::read(INSTALL_DIRS.data_dir.join("mupencheat.txt")) {
+        Ok(data) => Ok(parse_database(&String::from_utf8_lossy(&data), rom_key)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Path of the file storing the custom cheats for a ROM.
+fn custom_cheats_path(rom_crc: u32) -> PathBuf {
+    CONFIG_DIR
+        .join("cheats")
+        .join(format!("{:08X}.json", rom_crc))
+}
+
+/// Loads the custom cheats for a ROM.
+pub fn load_custom_cheats(rom_crc: u32) -> io::Result<Vec<Cheat>> {
+    match fs::read(custom_cheats_path(rom_crc)) {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Saves the custom cheats for a ROM.
+pub fn save_custom_cheats(rom_crc: u32, cheats: &[Cheat]) -> io::Result<()> {
+    let path = custom_cheats_path(rom_crc);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(cheats)?)
+}
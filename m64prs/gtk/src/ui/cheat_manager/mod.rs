@@ -0,0 +1,352 @@
+use database::Cheat;
+
+pub mod database;
+mod row;
+
+mod inner {
+    use std::cell::RefCell;
+
+    use futures::channel::mpsc;
+    use glib::subclass::InitializingObject;
+    use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
+    use m64prs_core::cheat::CheatCode;
+    use tr::tr;
+
+    use super::{database::Cheat, row::CheatRow, CheatCommand, CheatEntry};
+
+    #[derive(gtk::CompositeTemplate)]
+    #[template(file = "mod.ui")]
+    pub struct CheatManager {
+        #[template_child]
+        cheat_view: TemplateChild<gtk::ColumnView>,
+        #[template_child]
+        name_field: TemplateChild<gtk::Entry>,
+        #[template_child]
+        codes_field: TemplateChild<gtk::TextView>,
+
+        rows: gio::ListStore,
+        selection: gtk::SingleSelection,
+
+        /// The cheats shown, kept in sync with the rows.
+        entries: RefCell<Vec<CheatEntry>>,
+
+        command_tx: RefCell<Option<mpsc::UnboundedSender<CheatCommand>>>,
+        command_rx: RefCell<Option<mpsc::UnboundedReceiver<CheatCommand>>>,
+    }
+
+    #[m64prs_gtk_utils::forward_wrapper(super::CheatManager, vis = pub(in super::super))]
+    impl CheatManager {
+        /// Takes the stream of commands from the window. The stream ends when the window
+        /// is closed.
+        pub(super) fn take_commands(&self) -> Option<mpsc::UnboundedReceiver<CheatCommand>> {
+            self.command_rx.take()
+        }
+
+        /// Replaces the cheats shown.
+        pub(super) fn set_entries(&self, entries: Vec<CheatEntry>) {
+            let rows: Vec<CheatRow> = entries
+                .iter()
+                .map(|entry| CheatRow::new(&entry.cheat, entry.custom, entry.enabled))
+                .collect();
+            *self.entries.borrow_mut() = entries;
+            self.rows.splice(0, self.rows.n_items(), &rows);
+        }
+
+        /// Shows a cheat as enabled or disabled, without reporting a change. This is used
+        /// when a cheat couldn't be enabled.
+        pub(super) fn show_enabled(&self, name: &str, enabled: bool) {
+            let mut entries = self.entries.borrow_mut();
+            let Some(index) = entries.iter().position(|entry| entry.cheat.name == name) else {
+                return;
+            };
+            entries[index].enabled = enabled;
+            if let Some(row) = self.rows.item(index as u32).and_downcast::<CheatRow>() {
+                row.set_enabled(enabled);
+            }
+        }
+
+        /// Shows an error that occurred while applying a cheat.
+        pub(super) fn show_error(&self, message: &str, detail: &str) {
+            let dialog = gtk::AlertDialog::builder()
+                .modal(true)
+                .message(message)
+                .detail(detail)
+                .build();
+            dialog.show(Some(&*self.obj()));
+        }
+    }
+
+    impl CheatManager {
+        fn send(&self, command: CheatCommand) {
+            if let Some(tx) = &*self.command_tx.borrow() {
+                let _ = tx.unbounded_send(command);
+            }
+        }
+
+        /// Records a cheat being checked or unchecked, and reports it.
+        fn toggle(&self, row: &CheatRow, enabled: bool) {
+            row.set_enabled(enabled);
+            let cheat = {
+                let mut entries = self.entries.borrow_mut();
+                let Some(entry) = entries
+                    .iter_mut()
+                    .find(|entry| entry.cheat.name == row.name())
+                else {
+                    return;
+                };
+                entry.enabled = enabled;
+                entry.cheat.clone()
+            };
+            self.send(CheatCommand::SetEnabled(cheat, enabled));
+        }
+
+        /// Returns the index of the selected cheat.
+        fn selected_index(&self) -> Option<usize> {
+            match self.selection.selected() {
+                gtk::INVALID_LIST_POSITION => None,
+                index => Some(index as usize),
+            }
+        }
+
+        fn add_check_column(&self) {
+            let factory = gtk::SignalListItemFactory::new();
+            let this_ref = self.obj().downgrade();
+            factory.connect_setup(move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let check = gtk::CheckButton::new();
+                item.property_expression("item")
+                    .chain_property::<CheatRow>("enabled")
+                    .bind(&check, "active", gtk::Widget::NONE);
+
+                let this_ref = this_ref.clone();
+                let item_ref = item.downgrade();
+                check.connect_toggled(move |check| {
+                    let (Some(this), Some(row)) = (
+                        this_ref.upgrade(),
+                        item_ref
+                            .upgrade()
+                            .and_then(|item| item.item())
+                            .and_downcast::<CheatRow>(),
+                    ) else {
+                        return;
+                    };
+                    // Ignore changes made by the binding.
+                    if row.enabled() != check.is_active() {
+                        this.imp().toggle(&row, check.is_active());
+                    }
+                });
+                item.set_child(Some(&check));
+            });
+
+            let column = gtk::ColumnViewColumn::new(None, Some(factory));
+            self.cheat_view.append_column(&column);
+        }
+
+        fn add_column(&self, title: &str, property: &'static str, expand: bool) {
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let label = gtk::Label::new(None);
+                label.set_xalign(0.0);
+                label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                item.property_expression("item")
+                    .chain_property::<CheatRow>(property)
+                    .bind(&label, "label", gtk::Widget::NONE);
+                item.set_child(Some(&label));
+            });
+
+            let column = gtk::ColumnViewColumn::new(Some(title), Some(factory));
+            column.set_resizable(true);
+            column.set_expand(expand);
+            self.cheat_view.append_column(&column);
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl CheatManager {
+        #[template_callback]
+        fn save_clicked(&self, _: &gtk::Button) {
+            let name = self.name_field.text().trim().to_owned();
+            let buffer = self.codes_field.buffer();
+            let codes = buffer
+                .text(&buffer.start_iter(), &buffer.end_iter(), false)
+                .to_string();
+
+            let error = match CheatCode::parse_list(&codes) {
+                _ if name.is_empty() => Some(tr!("cheat_manager" => "The cheat needs a name.")),
+                Ok(parsed) if parsed.is_empty() => {
+                    Some(tr!("cheat_manager" => "The cheat needs at least one code."))
+                }
+                Ok(_) => None,
+                Err(err) => Some(err.to_string()),
+            };
+            if let Some(error) = error {
+                self.show_error(&tr!("cheat_manager" => "Failed to save cheat"), &error);
+                return;
+            }
+
+            let cheat = Cheat {
+                name,
+                description: String::new(),
+                codes,
+            };
+            let mut entries = self.entries.borrow().clone();
+            match entries
+                .iter_mut()
+                .find(|entry| entry.cheat.name == cheat.name)
+            {
+                Some(entry) if !entry.custom => {
+                    self.show_error(
+                        &tr!("cheat_manager" => "Failed to save cheat"),
+                        &tr!("cheat_manager" => "A cheat from the database already has this name."),
+                    );
+                    return;
+                }
+                Some(entry) => entry.cheat = cheat.clone(),
+                None => entries.push(CheatEntry {
+                    cheat: cheat.clone(),
+                    custom: true,
+                    enabled: false,
+                }),
+            }
+            self.set_entries(entries);
+            self.send(CheatCommand::Save(cheat));
+        }
+
+        #[template_callback]
+        fn remove_clicked(&self, _: &gtk::Button) {
+            let mut entries = self.entries.borrow().clone();
+            let Some(index) = self
+                .selected_index()
+                .filter(|&index| entries.get(index).is_some_and(|entry| entry.custom))
+            else {
+                self.obj().error_bell();
+                return;
+            };
+            let entry = entries.remove(index);
+            self.set_entries(entries);
+            self.send(CheatCommand::Remove(entry.cheat.name));
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CheatManager {
+        const NAME: &'static str = "M64PRS_CheatManager";
+        type Type = super::CheatManager;
+        type ParentType = gtk::Window;
+
+        fn new() -> Self {
+            let rows = gio::ListStore::new::<CheatRow>();
+            let selection = gtk::SingleSelection::new(Some(rows.clone()));
+            selection.set_autoselect(false);
+            selection.set_can_unselect(true);
+            let (command_tx, command_rx) = mpsc::unbounded();
+
+            Self {
+                cheat_view: Default::default(),
+                name_field: Default::default(),
+                codes_field: Default::default(),
+                rows,
+                selection,
+                entries: Default::default(),
+                command_tx: RefCell::new(Some(command_tx)),
+                command_rx: RefCell::new(Some(command_rx)),
+            }
+        }
+
+        fn class_init(class: &mut Self::Class) {
+            CheatRow::ensure_type();
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for CheatManager {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.cheat_view.set_model(Some(&self.selection));
+
+            self.add_check_column();
+            self.add_column(&tr!("cheat_manager" => "Name"), "name", false);
+            self.add_column(&tr!("cheat_manager" => "Description"), "description", true);
+
+            // Selecting a cheat loads it into the fields, so it can be copied or edited.
+            let this_ref = self.obj().downgrade();
+            self.selection.connect_selection_changed(move |_, _, _| {
+                let Some(this) = this_ref.upgrade() else {
+                    return;
+                };
+                let this = this.imp();
+                let Some(index) = this.selected_index() else {
+                    return;
+                };
+                let cheat = this
+                    .entries
+                    .borrow()
+                    .get(index)
+                    .map(|entry| entry.cheat.clone());
+                if let Some(cheat) = cheat {
+                    this.name_field.set_text(&cheat.name);
+                    this.codes_field.buffer().set_text(&cheat.codes);
+                }
+            });
+        }
+
+        fn dispose(&self) {
+            self.dispose_template();
+        }
+    }
+    impl WidgetImpl for CheatManager {}
+    impl WindowImpl for CheatManager {
+        fn close_request(&self) -> glib::Propagation {
+            // Ends the stream of commands.
+            self.command_tx.take();
+            glib::Propagation::Proceed
+        }
+    }
+}
+
+/// A cheat shown in the [`CheatManager`].
+#[derive(Debug, Clone)]
+pub struct CheatEntry {
+    pub cheat: Cheat,
+    /// True if the cheat was added by the user, rather than read from the database.
+    pub custom: bool,
+    pub enabled: bool,
+}
+
+/// A request from the [`CheatManager`] window.
+#[derive(Debug, Clone)]
+pub enum CheatCommand {
+    /// Enable or disable a cheat.
+    SetEnabled(Cheat, bool),
+    /// Add a custom cheat, or replace the custom cheat with the same name.
+    Save(Cheat),
+    /// Remove the custom cheat with the given name.
+    Remove(String),
+}
+
+glib::wrapper! {
+    /// Window for enabling cheats from the cheat database, and managing custom cheats.
+    pub struct CheatManager(ObjectSubclass<inner::CheatManager>)
+    @extends
+        gtk::Window,
+        gtk::Widget,
+    @implements
+        gtk::Accessible,
+        gtk::Buildable,
+        gtk::ConstraintTarget,
+        gtk::Native,
+        gtk::Root,
+        gtk::ShortcutManager;
+}
+
+impl CheatManager {
+    pub(super) fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,46 @@
+use super::database::Cheat;
+
+mod inner {
+    use std::cell::{Cell, RefCell};
+
+    use gtk::{prelude::*, subclass::prelude::*};
+
+    /// One cheat, as shown in the cheat manager.
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::CheatRow)]
+    pub struct CheatRow {
+        #[property(get, construct_only)]
+        name: RefCell<String>,
+        #[property(get, construct_only)]
+        description: RefCell<String>,
+        /// True if the cheat was added by the user, rather than read from the database.
+        #[property(get, construct_only)]
+        custom: Cell<bool>,
+        #[property(get, set)]
+        enabled: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CheatRow {
+        const NAME: &'static str = "M64PRS_CheatRow";
+        type Type = super::CheatRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for CheatRow {}
+}
+
+glib::wrapper! {
+    pub struct CheatRow(ObjectSubclass<inner::CheatRow>);
+}
+
+impl CheatRow {
+    pub fn new(cheat: &Cheat, custom: bool, enabled: bool) -> Self {
+        glib::Object::builder()
+            .property("name", &cheat.name)
+            .property("description", &cheat.description)
+            .property("custom", custom)
+            .property("enabled", enabled)
+            .build()
+    }
+}
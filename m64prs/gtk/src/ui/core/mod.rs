@@ -1,27 +1,44 @@
 use std::{
     borrow::Borrow,
-    cell::Cell,
+    cell::{Cell, RefCell},
+    collections::HashMap,
     error::Error,
     ffi::CStr,
-    fs,
+    fs, io,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
-use futures::{executor::block_on, lock::Mutex};
+use futures::{
+    channel::{mpsc, oneshot},
+    executor::block_on,
+    lock::Mutex,
+};
 use gdk::prelude::SurfaceExt;
 use glib::SendWeakRef;
 use gtk::prelude::NativeExt;
 use m64prs_core::{
+    cheat::CheatCode,
     config::ConfigSection,
-    error::{M64PError, PluginLoadError, SavestateError},
+    emu_state::EmuControlHandle,
+    error::{CheatError, M64PError, PluginLoadError, SavestateError},
+    memory::MemoryHandle,
     plugin::{PluginInfo, PluginSet, PluginType},
     save::SavestateFormat,
     tas_callbacks::{FrameHandler, InputHandler, SaveHandler},
     ConfigSectionMut, Core,
 };
-use m64prs_sys::{CoreParam, EmuState, RomHeader, RomSettings};
-use m64prs_vcr::{movie::M64File, VcrState};
+use m64prs_sys::{Buttons, CoreParam, EmuState, RomHeader, RomSettings};
+use m64prs_vcr::{
+    branch::BranchComparison,
+    compat::Environment,
+    greenzone::{GreenzoneSettings, SeekTarget},
+    movie::ResetKind,
+    EndBehavior, ExportedMovie, SessionStats, VcrState,
+};
 use num_enum::TryFromPrimitive;
 use threading::RunningCore;
 use vidext::{VideoExtensionParameters, VideoExtensionState};
@@ -31,11 +48,14 @@ use crate::utils::{
     paths::{CONFIG_DIR, INSTALL_DIRS},
 };
 
-use super::{main_window::MainWindow, settings_dialog};
+use super::{main_window::MainWindow, ram_watch::watch::Watch, settings_dialog};
 
 mod threading;
 mod vidext;
 
+/// Virtual address of the start of RDRAM, in KSEG0.
+const RDRAM_BASE: u32 = 0x8000_0000;
+
 #[derive(Debug)]
 pub enum CoreState {
     Uninit,
@@ -54,15 +74,110 @@ pub struct CoreRunningState {
     main_window_ref: SendWeakRef<MainWindow>,
     vcr_read_only: Cell<bool>,
     vcr_state: Arc<Mutex<Option<VcrState>>>,
+    /// Set whenever the game polls for input.
+    input_polled: Arc<AtomicBool>,
+    /// True while frame advancing until the next input poll.
+    advancing_to_poll: Cell<bool>,
+    /// Position to pause at while seeking.
+    seek_target: Arc<Mutex<Option<SeekTarget>>>,
+    memory: MemoryHandle,
+    /// Watches read by the frame handler.
+    watch_link: Arc<Mutex<Option<WatchLink>>>,
+    /// Pending requests for a copy of RDRAM, answered by the frame handler.
+    rdram_requests: Arc<Mutex<Vec<oneshot::Sender<Box<[u8]>>>>>,
+    /// Cheats added to the core, and whether each is enabled.
+    cheats: RefCell<HashMap<String, bool>>,
+    /// Set while any cheat is enabled.
+    cheats_active: Arc<AtomicBool>,
+}
+
+/// Watches read after every frame, and where to send their values.
+#[derive(Debug)]
+struct WatchLink {
+    watches: Vec<Watch>,
+    values: mpsc::UnboundedSender<Vec<Option<u64>>>,
+}
+
+impl WatchLink {
+    /// Writes the frozen values.
+    /// # Return value
+    /// True if any value was written.
+    fn apply_freezes(&self, memory: &MemoryHandle) -> bool {
+        self.watches
+            .iter()
+            .fold(false, |written, watch| watch.apply_freeze(memory) | written)
+    }
+
+    /// Reads every watch and sends their values.
+    /// # Return value
+    /// False if the receiver was dropped.
+    fn send_values(&self, memory: &MemoryHandle) -> bool {
+        let values = self
+            .watches
+            .iter()
+            .map(|watch| watch.read(memory))
+            .collect();
+        self.values.unbounded_send(values).is_ok()
+    }
+}
+
+/// Copies RDRAM, in the N64's byte order.
+fn copy_rdram(memory: &MemoryHandle) -> Option<Box<[u8]>> {
+    let mut rdram = vec![0u8; memory.rdram_size()];
+    memory.read_range(RDRAM_BASE, &mut rdram).ok()?;
+    Some(rdram.into_boxed_slice())
+}
+
+/// Summary of one of the active movie's branches, for display in the UI.
+#[derive(Debug, Clone)]
+pub struct BranchInfo {
+    pub name: String,
+    pub index: u32,
+    pub vi_count: u32,
+}
+
+/// Copy of the active movie's inputs, for the input editor.
+#[derive(Debug, Clone)]
+pub struct InputSnapshot {
+    /// Changes whenever the inputs change.
+    pub revision: u64,
+    /// Index of the next input sample.
+    pub index: u32,
+    /// Number of input samples in each frame.
+    pub frame_size: u32,
+    /// The inputs, if they changed since the revision passed in.
+    pub inputs: Option<Vec<Buttons>>,
+}
+
+/// Snapshot of the active movie's statistics, for display in the UI.
+#[derive(Debug, Clone, Copy)]
+pub struct VcrStats {
+    pub rerecord_count: u64,
+    pub index: u32,
+    pub length_samples: u32,
+    pub vi_count: u32,
+    pub lag_count: u32,
+    pub session: SessionStats,
 }
 
 struct CoreInputHandler {
     vcr_state: Arc<Mutex<Option<VcrState>>>,
+    input_polled: Arc<AtomicBool>,
+    watch_link: Arc<Mutex<Option<WatchLink>>>,
+    memory: MemoryHandle,
+    control: EmuControlHandle,
     main_window_ref: SendWeakRef<MainWindow>,
 }
 
 struct CoreFrameHandler {
     vcr_state: Arc<Mutex<Option<VcrState>>>,
+    seek_target: Arc<Mutex<Option<SeekTarget>>>,
+    watch_link: Arc<Mutex<Option<WatchLink>>>,
+    rdram_requests: Arc<Mutex<Vec<oneshot::Sender<Box<[u8]>>>>>,
+    cheats_active: Arc<AtomicBool>,
+    memory: MemoryHandle,
+    control: EmuControlHandle,
+    main_window_ref: SendWeakRef<MainWindow>,
 }
 
 struct CoreSaveHandler {
@@ -235,9 +350,19 @@ impl CoreReadyState {
         // Load the main window
         let main_window_ref = self.main_window_ref;
         let vcr_state = Arc::new(Mutex::new(None));
+        let input_polled = Arc::new(AtomicBool::new(false));
+        let seek_target = Arc::new(Mutex::new(None));
+        let watch_link = Arc::new(Mutex::new(None));
+        let rdram_requests = Arc::new(Mutex::new(Vec::new()));
+        let cheats_active = Arc::new(AtomicBool::new(false));
+        let memory = core.memory_handle();
 
         let input_handler = CoreInputHandler {
             vcr_state: Arc::clone(&vcr_state),
+            input_polled: Arc::clone(&input_polled),
+            watch_link: Arc::clone(&watch_link),
+            memory,
+            control: core.control_handle(),
             main_window_ref: main_window_ref.clone(),
         };
         core.set_input_handler(input_handler)
@@ -251,6 +376,13 @@ impl CoreReadyState {
 
         let frame_handler = CoreFrameHandler {
             vcr_state: Arc::clone(&vcr_state),
+            seek_target: Arc::clone(&seek_target),
+            watch_link: Arc::clone(&watch_link),
+            rdram_requests: Arc::clone(&rdram_requests),
+            cheats_active: Arc::clone(&cheats_active),
+            memory,
+            control: core.control_handle(),
+            main_window_ref: main_window_ref.clone(),
         };
         core.set_frame_handler(frame_handler)
             .expect("should be able to set frame handler");
@@ -262,6 +394,14 @@ impl CoreReadyState {
             main_window_ref,
             vcr_read_only: Cell::new(false),
             vcr_state,
+            input_polled,
+            advancing_to_poll: Cell::new(false),
+            seek_target,
+            memory,
+            watch_link,
+            rdram_requests,
+            cheats: RefCell::new(HashMap::new()),
+            cheats_active,
         })
     }
 
@@ -276,8 +416,30 @@ impl CoreReadyState {
 
 impl CoreRunningState {
     pub(super) async fn stop_rom(self) -> (CoreReadyState, Option<M64PError>) {
-        let _ = self.unset_vcr_state().await;
+        let vcr_state = self.unset_vcr_state().await;
         let (mut core, error) = gio::spawn_blocking(|| self.core.stop()).await.unwrap();
+        if let Some(mut vcr_state) = vcr_state {
+            // Like closing the movie, save it unless it's read-only. The journal is kept if
+            // saving fails, so that the changes can still be recovered.
+            let saved = match self.vcr_read_only.get() {
+                true => Ok(()),
+                false => {
+                    let (path, data) = vcr_state.export();
+                    gio::spawn_blocking(move || data.save(&path)).await.unwrap()
+                }
+            };
+            match saved {
+                Ok(()) => {
+                    if let Err(err) = vcr_state.remove_journal() {
+                        log::error!("failed to remove movie journal: {}", err);
+                    }
+                }
+                Err(err) => log::error!("failed to save movie: {}", err),
+            }
+            if let Err(err) = vcr_state.restore_save_ram() {
+                log::error!("failed to restore save memory: {}", err);
+            }
+        }
 
         let main_window_ref = self.main_window_ref;
 
@@ -301,6 +463,7 @@ impl CoreRunningState {
     }
 
     pub(super) fn toggle_pause(&self) -> Result<(), M64PError> {
+        self.advancing_to_poll.set(false);
         match self.core.emu_state() {
             EmuState::Running => self.core.request_pause(),
             EmuState::Paused => self.core.request_resume(),
@@ -309,10 +472,42 @@ impl CoreRunningState {
     }
 
     pub(super) fn frame_advance(&self) -> Result<(), M64PError> {
+        self.advancing_to_poll.set(false);
         self.core.request_advance_frame()
     }
 
-    pub(super) fn reset(&self, hard: bool) -> Result<(), M64PError> {
+    /// Frame advances until the game polls for input, skipping over lag frames.
+    pub(super) fn advance_to_poll(&self) -> Result<(), M64PError> {
+        self.input_polled.store(false, Ordering::Release);
+        self.advancing_to_poll.set(true);
+        self.core.request_advance_frame()
+    }
+
+    /// Continues an [`advance_to_poll`][Self::advance_to_poll] once the emulator pauses.
+    /// Does nothing if no such advance is in progress.
+    pub(super) fn continue_advance_to_poll(&self) -> Result<(), M64PError> {
+        if !self.advancing_to_poll.get() {
+            return Ok(());
+        }
+        if self.input_polled.load(Ordering::Acquire) {
+            self.advancing_to_poll.set(false);
+            return Ok(());
+        }
+        self.core.request_advance_frame()
+    }
+
+    /// Resets the ROM. While recording a movie, the reset is stored in the movie and performed
+    /// at the start of the next input frame.
+    pub(super) async fn reset(&self, hard: bool) -> Result<(), M64PError> {
+        if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
+            let reset = match hard {
+                true => ResetKind::Hard,
+                false => ResetKind::Soft,
+            };
+            if vcr_state.request_reset(reset) {
+                return Ok(());
+            }
+        }
         self.core.reset(hard)
     }
 
@@ -321,10 +516,9 @@ impl CoreRunningState {
     }
 
     pub(super) async fn load_slot(&self) -> Result<(), SavestateError> {
+        self.sync_vcr_read_only().await;
         self.core.load_slot().await?;
-        if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
-            vcr_state.set_read_only(self.vcr_read_only.get());
-        }
+        self.notify_vcr_stats().await;
         Ok(())
     }
 
@@ -339,13 +533,143 @@ impl CoreRunningState {
     }
 
     pub(super) async fn load_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SavestateError> {
+        self.sync_vcr_read_only().await;
         self.core.load_file(path.as_ref()).await?;
+        self.notify_vcr_stats().await;
+        Ok(())
+    }
+
+    /// Takes a greenzone capture of the current state. The frame handler starts the capture,
+    /// since it knows when one is due.
+    pub(super) async fn capture_greenzone(&self) {
+        let data = self.core.save_data(SavestateFormat::Mupen64Plus).await;
+        if let Err(err) = &data {
+            log::warn!("greenzone capture failed: {}", err);
+        }
         if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
-            vcr_state.set_read_only(self.vcr_read_only.get());
+            vcr_state.greenzone_mut().finish_capture(data.ok());
+        }
+    }
+
+    /// Seeks the movie to `target` and pauses there. If the target is behind the current
+    /// position, the nearest greenzone capture before it is loaded (or the movie is
+    /// restarted), and the movie is replayed from there with the speed limiter disabled.
+    pub(super) async fn seek(&self, target: SeekTarget) -> Result<(), Box<dyn Error>> {
+        enum SeekStart {
+            Current,
+            Capture(Arc<[u8]>),
+            Restart,
+        }
+
+        let start = {
+            let mut vcr_state = self.vcr_state.lock().await;
+            let Some(vcr_state) = vcr_state.as_mut() else {
+                return Ok(());
+            };
+            // Play the movie back instead of overwriting it.
+            vcr_state.set_read_only(true);
+            let index = vcr_state.index();
+            let behind = target.value() < vcr_state.seek_position(target);
+            match vcr_state.greenzone().nearest(target) {
+                Some((capture_index, data)) if behind || capture_index > index => {
+                    SeekStart::Capture(data)
+                }
+                None if behind => SeekStart::Restart,
+                _ => SeekStart::Current,
+            }
+        };
+
+        self.advancing_to_poll.set(false);
+        if self.core.emu_state() == EmuState::Running {
+            self.core.request_pause()?;
+        }
+        match start {
+            SeekStart::Current => (),
+            SeekStart::Capture(data) => self.core.load_data(&data).await?,
+            SeekStart::Restart => {
+                // Restarting the movie loads savestates, which needs the VCR state unlocked.
+                let vcr_state = self.vcr_state.lock().await.take();
+                if let Some(mut vcr_state) = vcr_state {
+                    let result = self.reset_vcr(&mut vcr_state, false).await;
+                    *self.vcr_state.lock().await = Some(vcr_state);
+                    result?;
+                }
+            }
+        }
+
+        let reached = match &*self.vcr_state.lock().await {
+            Some(vcr_state) => vcr_state.reached(target),
+            None => return Ok(()),
+        };
+        if reached {
+            self.finish_seek().await;
+        } else {
+            *self.seek_target.lock().await = Some(target);
+            self.core.set_speed_limiter(false)?;
+            self.core.request_resume()?;
         }
+        self.notify_vcr_stats().await;
         Ok(())
     }
 
+    /// Returns the sample index of the start of the previous input frame, if a movie is active.
+    pub(super) async fn vcr_previous_frame(&self) -> Option<u32> {
+        let vcr_state = self.vcr_state.lock().await;
+        vcr_state
+            .as_ref()
+            .map(|state| state.index().saturating_sub(state.frame_size()))
+    }
+
+    /// Stops a seek in progress and pauses the emulator.
+    /// # Return value
+    /// False if no seek was in progress.
+    pub(super) async fn cancel_seek(&self) -> Result<bool, M64PError> {
+        if self.seek_target.lock().await.take().is_none() {
+            return Ok(false);
+        }
+        self.core.request_pause()?;
+        self.finish_seek().await;
+        Ok(true)
+    }
+
+    /// Returns the current position and the target of the seek in progress, if any.
+    pub(super) async fn seek_progress(&self) -> Option<(u32, SeekTarget)> {
+        let target = (*self.seek_target.lock().await)?;
+        let vcr_state = self.vcr_state.lock().await;
+        vcr_state
+            .as_ref()
+            .map(|state| (state.seek_position(target), target))
+    }
+
+    /// Returns the number of input samples in each frame of the active movie.
+    pub(super) async fn vcr_frame_size(&self) -> Option<u32> {
+        let vcr_state = self.vcr_state.lock().await;
+        vcr_state.as_ref().map(VcrState::frame_size)
+    }
+
+    /// Restores the speed limiter and the movie's read-only mode once a seek has stopped.
+    pub(super) async fn finish_seek(&self) {
+        if let Err(err) = self.core.set_speed_limiter(true) {
+            log::warn!("failed to restore speed limiter: {}", err);
+        }
+        self.sync_vcr_read_only().await;
+        self.notify_vcr_stats().await;
+    }
+
+    /// Applies the frontend's read-only flag to the VCR state. This must happen before
+    /// a savestate is loaded, since the VCR state decides whether to count a rerecord
+    /// while the savestate is loading.
+    async fn sync_vcr_read_only(&self) {
+        if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
+            vcr_state.set_read_only(self.vcr_read_only.get());
+        }
+    }
+
+    async fn notify_vcr_stats(&self) {
+        let stats = self.vcr_stats().await;
+        self.notify_main_window(move |main_window| main_window.set_vcr_stats(stats));
+    }
+
     pub(super) fn forward_key_down(&self, key_code: u32, r#mod: gdk::ModifierType) {
         if let Some(window) = self.main_window_ref.upgrade() {
             let display = window.surface().unwrap().display();
@@ -381,7 +705,12 @@ impl CoreRunningState {
         new: bool,
     ) -> Result<(), Box<dyn Error>> {
         vcr_state.set_read_only(self.vcr_read_only.get());
-        vcr_state.reset(&self.core, new).await?;
+        vcr_state.set_greenzone_settings(self.greenzone_settings());
+        vcr_state.set_end_behavior(self.end_behavior());
+        self.reset_vcr(&mut vcr_state, new).await?;
+        if let Err(err) = vcr_state.start_journal() {
+            log::warn!("failed to create movie journal: {}", err);
+        }
         {
             let mut self_vcr_state = self.vcr_state.lock().await;
             *self_vcr_state = Some(vcr_state);
@@ -390,24 +719,222 @@ impl CoreRunningState {
         Ok(())
     }
 
+    /// Restarts the movie, restarting emulation if the movie's save memory needs to be loaded.
+    async fn reset_vcr(&self, vcr_state: &mut VcrState, new: bool) -> Result<(), Box<dyn Error>> {
+        vcr_state.reset(&self.core, new).await?;
+        if vcr_state.take_restart() {
+            self.restart(|core| vcr_state.load_save_ram(core)).await?;
+        }
+        Ok(())
+    }
+
+    /// Puts back the save memory replaced by a movie, restarting emulation so that the core
+    /// reads it. Call this after the movie is closed.
+    pub(super) async fn restore_save_ram(&self, vcr_state: &mut VcrState) -> io::Result<()> {
+        if !vcr_state.replaced_save_ram() {
+            return Ok(());
+        }
+        self.restart(|_| vcr_state.restore_save_ram()).await
+    }
+
+    /// Stops emulation, calls `stopped`, then starts emulation again. The core drops its
+    /// cheats when emulation stops, so they are forgotten as well.
+    async fn restart<F: FnOnce(&Core) -> R, R>(&self, stopped: F) -> R {
+        let result = self.core.restart(stopped).await;
+        self.cheats.borrow_mut().clear();
+        self.cheats_active.store(false, Ordering::Release);
+        result
+    }
+
+    /// Reads the greenzone settings from the config.
+    fn greenzone_settings(&self) -> GreenzoneSettings {
+        let defaults = GreenzoneSettings::default();
+        let Ok(sect) = self.core.cfg_open(settings_dialog::VCR_SECTION_KEY) else {
+            return defaults;
+        };
+        let interval: i32 = sect
+            .get_cast_or(60, settings_dialog::GREENZONE_INTERVAL_KEY)
+            .unwrap_or(60);
+        let max_mb: i32 = sect
+            .get_cast_or(0, settings_dialog::GREENZONE_MAX_MB_KEY)
+            .unwrap_or(0);
+        GreenzoneSettings {
+            interval: interval.max(0) as u32,
+            max_bytes: match max_mb {
+                ..=0 => defaults.max_bytes,
+                max_mb => max_mb as usize * 1024 * 1024,
+            },
+        }
+    }
+
+    /// Reads the end-of-movie behaviour from the config.
+    fn end_behavior(&self) -> EndBehavior {
+        let value: i32 = self
+            .core
+            .cfg_open(settings_dialog::VCR_SECTION_KEY)
+            .ok()
+            .and_then(|sect| sect.get_cast_or(0, settings_dialog::END_BEHAVIOR_KEY).ok())
+            .unwrap_or(0);
+        match value {
+            1 => EndBehavior::Pause,
+            2 => EndBehavior::PlayLive,
+            3 => EndBehavior::Record,
+            _ => EndBehavior::Stop,
+        }
+    }
+
     pub(super) async fn unset_vcr_state(&self) -> Option<VcrState> {
         let result = self.vcr_state.lock().await.take();
         self.notify_main_window(|main_window| main_window.set_vcr_active(false));
         result
     }
 
-    pub(super) async fn export_vcr(&self) -> Option<(PathBuf, M64File)> {
+    pub(super) async fn export_vcr(&self) -> Option<(PathBuf, ExportedMovie)> {
         println!("yeet!");
         let vcr_state = self.vcr_state.lock().await;
         println!("locked");
         vcr_state.as_ref().map(|state| state.export())
     }
 
+    /// Restarts the movie journal. This should be called after the movie is saved.
+    pub(super) async fn checkpoint_vcr_journal(&self) {
+        if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
+            if let Err(err) = vcr_state.checkpoint_journal() {
+                log::warn!("failed to checkpoint movie journal: {}", err);
+            }
+        }
+    }
+
     pub(super) fn set_read_only(&self, value: bool) {
         self.vcr_read_only.set(value);
         self.notify_main_window(move |main_window| main_window.set_vcr_read_only(value));
     }
 
+    /// Returns the current movie and session statistics, if a movie is active.
+    pub(super) async fn vcr_stats(&self) -> Option<VcrStats> {
+        let vcr_state = self.vcr_state.lock().await;
+        vcr_state.as_ref().map(|state| VcrStats {
+            rerecord_count: state.rerecord_count(),
+            index: state.index(),
+            length_samples: state.length_samples(),
+            vi_count: state.vi_count(),
+            lag_count: state.lag_count(),
+            session: state.session_stats(),
+        })
+    }
+
+    /// Returns the active movie's inputs and position, if a movie is active. The inputs
+    /// are only copied if they changed since `known_revision`.
+    pub(super) async fn vcr_inputs(&self, known_revision: Option<u64>) -> Option<InputSnapshot> {
+        let vcr_state = self.vcr_state.lock().await;
+        vcr_state.as_ref().map(|state| InputSnapshot {
+            revision: state.revision(),
+            index: state.index(),
+            frame_size: state.frame_size(),
+            inputs: (known_revision != Some(state.revision())).then(|| state.inputs().to_vec()),
+        })
+    }
+
+    /// Replaces `len` samples of the active movie's inputs starting at `start`.
+    pub(super) async fn splice_vcr_inputs(&self, start: u32, len: u32, samples: &[Buttons]) {
+        if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
+            vcr_state.splice_inputs(start, len, samples);
+        }
+        self.notify_vcr_stats().await;
+    }
+
+    /// Reverts the last change to the active movie's inputs. Returns false if there was
+    /// nothing to undo.
+    pub(super) async fn undo_vcr(&self) -> bool {
+        let undone = match &mut *self.vcr_state.lock().await {
+            Some(vcr_state) => vcr_state.undo(),
+            None => false,
+        };
+        self.notify_vcr_stats().await;
+        undone
+    }
+
+    /// Reapplies the last change undone with [`Self::undo_vcr`]. Returns false if there was
+    /// nothing to redo.
+    pub(super) async fn redo_vcr(&self) -> bool {
+        let redone = match &mut *self.vcr_state.lock().await {
+            Some(vcr_state) => vcr_state.redo(),
+            None => false,
+        };
+        self.notify_vcr_stats().await;
+        redone
+    }
+
+    /// Returns the active movie's branches, if a movie is active.
+    pub(super) async fn vcr_branches(&self) -> Option<Vec<BranchInfo>> {
+        let vcr_state = self.vcr_state.lock().await;
+        vcr_state.as_ref().map(|state| {
+            state
+                .branches()
+                .iter()
+                .map(|branch| BranchInfo {
+                    name: branch.name.clone(),
+                    index: branch.index,
+                    vi_count: branch.vi_count,
+                })
+                .collect()
+        })
+    }
+
+    /// Creates a branch of the active movie at the current position.
+    pub(super) async fn create_branch(&self, name: String) -> Result<(), SavestateError> {
+        let savestate = self.core.save_data(SavestateFormat::Mupen64Plus).await?;
+        if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
+            vcr_state.create_branch(name, savestate);
+        }
+        Ok(())
+    }
+
+    /// Loads a branch of the active movie, switching the movie to its inputs.
+    pub(super) async fn load_branch(&self, index: usize) -> Result<(), SavestateError> {
+        let savestate = match &*self.vcr_state.lock().await {
+            Some(vcr_state) => vcr_state
+                .branches()
+                .get(index)
+                .map(|branch| branch.savestate.clone()),
+            None => None,
+        };
+        if let Some(savestate) = savestate {
+            self.sync_vcr_read_only().await;
+            self.core.load_data(&savestate).await?;
+            self.notify_vcr_stats().await;
+        }
+        Ok(())
+    }
+
+    /// Compares a branch of the active movie against its current inputs.
+    pub(super) async fn compare_branch(&self, index: usize) -> Option<BranchComparison> {
+        let vcr_state = self.vcr_state.lock().await;
+        vcr_state.as_ref()?.compare_branch(index)
+    }
+
+    /// Swaps a branch of the active movie with the main timeline, then loads it.
+    pub(super) async fn promote_branch(&self, index: usize) -> Result<(), SavestateError> {
+        let current = self.core.save_data(SavestateFormat::Mupen64Plus).await?;
+        let savestate = match &mut *self.vcr_state.lock().await {
+            Some(vcr_state) => vcr_state.promote_branch(index, current),
+            None => None,
+        };
+        if let Some(savestate) = savestate {
+            self.sync_vcr_read_only().await;
+            self.core.load_data(&savestate).await?;
+            self.notify_vcr_stats().await;
+        }
+        Ok(())
+    }
+
+    /// Deletes a branch of the active movie.
+    pub(super) async fn remove_branch(&self, index: usize) {
+        if let Some(vcr_state) = &mut *self.vcr_state.lock().await {
+            vcr_state.remove_branch(index);
+        }
+    }
+
     pub(super) fn toggle_read_only(&self) {
         self.set_read_only(!self.vcr_read_only.get());
     }
@@ -426,6 +953,122 @@ impl CoreRunningState {
         self.core.plugin_info(ptype).unwrap().unwrap()
     }
 
+    /// Starts reading `watches` after every frame, replacing any watches read before.
+    /// # Return value
+    /// A stream of the watches' values, which ends when the ROM is closed or another set of
+    /// watches replaces this one.
+    pub(super) async fn watch_memory(
+        &self,
+        watches: Vec<Watch>,
+    ) -> mpsc::UnboundedReceiver<Vec<Option<u64>>> {
+        let (values, values_rx) = mpsc::unbounded();
+        *self.watch_link.lock().await = Some(WatchLink { watches, values });
+        self.refresh_watches().await;
+        values_rx
+    }
+
+    /// Changes the watches read after every frame, keeping the same stream of values.
+    pub(super) async fn set_watches(&self, watches: Vec<Watch>) {
+        if let Some(link) = &mut *self.watch_link.lock().await {
+            link.watches = watches;
+        }
+        self.refresh_watches().await;
+    }
+
+    /// Sends the watches' values immediately if the emulator is paused, since no frames
+    /// will run until it resumes.
+    async fn refresh_watches(&self) {
+        if self.core.emu_state() != EmuState::Paused {
+            return;
+        }
+        let mut watch_link = self.watch_link.lock().await;
+        // The emulator thread is parked until it resumes, so memory can be read here.
+        if watch_link
+            .as_ref()
+            .is_some_and(|link| !link.send_values(&self.memory))
+        {
+            *watch_link = None;
+        }
+    }
+
+    /// Copies RDRAM, in the N64's byte order. If the emulator is running, the copy is taken
+    /// at the end of the next frame.
+    /// # Return value
+    /// `None` if the ROM was closed before the copy could be taken.
+    pub(super) async fn snapshot_rdram(&self) -> Option<Box<[u8]>> {
+        let rdram_rx = {
+            let mut requests = self.rdram_requests.lock().await;
+            // The emulator thread is parked until it resumes, so memory can be read here.
+            if self.core.emu_state() == EmuState::Paused {
+                return copy_rdram(&self.memory);
+            }
+            let (rdram_tx, rdram_rx) = oneshot::channel();
+            requests.push(rdram_tx);
+            rdram_rx
+        };
+        rdram_rx.await.ok()
+    }
+
+    /// Adds or replaces a cheat, then enables or disables it. Enabled cheats are applied on
+    /// every frame until the ROM is closed, and mark the movie being recorded as recorded
+    /// with modified memory.
+    pub(super) fn set_cheat(
+        &self,
+        name: &str,
+        codes: &[CheatCode],
+        enabled: bool,
+    ) -> Result<(), CheatError> {
+        let mut cheats = self.cheats.borrow_mut();
+        match (enabled, cheats.contains_key(name)) {
+            (true, _) => self.core.add_cheat(name, codes)?,
+            (false, true) => self.core.set_cheat_enabled(name, false)?,
+            // The cheat was never added, so there is nothing to disable.
+            (false, false) => return Ok(()),
+        }
+        cheats.insert(name.to_owned(), enabled);
+        self.cheats_active
+            .store(cheats.values().any(|&enabled| enabled), Ordering::Release);
+        Ok(())
+    }
+
+    /// Removes a cheat added with [`CoreRunningState::set_cheat`].
+    pub(super) fn remove_cheat(&self, name: &str) -> Result<(), CheatError> {
+        let mut cheats = self.cheats.borrow_mut();
+        if cheats.remove(name).is_some() {
+            self.core.remove_cheat(name)?;
+        }
+        self.cheats_active
+            .store(cheats.values().any(|&enabled| enabled), Ordering::Release);
+        Ok(())
+    }
+
+    /// Returns whether the cheat with the given name is enabled.
+    pub(super) fn cheat_enabled(&self, name: &str) -> bool {
+        self.cheats.borrow().get(name).copied().unwrap_or(false)
+    }
+
+    /// Returns the ROM and plugins in use, for filling in and checking movie headers.
+    pub(super) fn movie_environment(&self) -> Environment {
+        let rom_header = self.rom_header();
+        let plugin_name = |ptype| {
+            self.plugin_info(ptype)
+                .plugin_name
+                .to_string_lossy()
+                .into_owned()
+        };
+        Environment {
+            rom_name: String::from_utf8_lossy(&rom_header.Name)
+                .trim_end_matches(['\0', ' '])
+                .to_owned(),
+            rom_crc: rom_header.CRC1,
+            rom_cc: rom_header.Country_code as u16,
+            graphics_plugin: plugin_name(PluginType::Graphics),
+            audio_plugin: plugin_name(PluginType::Audio),
+            input_plugin: plugin_name(PluginType::Input),
+            rsp_plugin: plugin_name(PluginType::Rsp),
+        }
+    }
+
     pub(super) fn cfg_open(&self, name: &CStr) -> Result<ConfigSection<'_>, M64PError> {
         self.core.cfg_open(name)
     }
@@ -445,15 +1088,39 @@ impl InputHandler for CoreInputHandler {
         port: std::ffi::c_int,
         mut input: m64prs_sys::Buttons,
     ) -> m64prs_sys::Buttons {
+        self.input_polled.store(true, Ordering::Release);
+        let froze = block_on(self.watch_link.lock())
+            .as_ref()
+            .is_some_and(|link| link.apply_freezes(&self.memory));
         {
             let mut vcr_state = block_on(self.vcr_state.lock());
-            let mut should_drop = false;
             if let Some(vcr_state) = vcr_state.as_mut() {
-                (input, should_drop) = vcr_state.filter_inputs(port, input);
-            }
-            if should_drop {
-                *vcr_state = None;
-                self.notify_main_window(|main_window| main_window.set_vcr_active(false));
+                if froze && vcr_state.note_memory_modified() {
+                    self.notify_main_window(|main_window| main_window.report_modified_memory());
+                }
+                // The end of the movie is handled once, through take_end below.
+                (input, _) = vcr_state.filter_inputs(port, input);
+                if let Some(reset) = vcr_state.take_reset() {
+                    if let Err(err) = self.control.request_reset(reset.is_hard()) {
+                        log::warn!("failed to reset from movie: {}", err);
+                    }
+                }
+                match vcr_state.take_end() {
+                    Some(EndBehavior::Pause) => {
+                        if let Err(err) = self.control.request_pause() {
+                            log::warn!("failed to pause at the end of the movie: {}", err);
+                        }
+                    }
+                    Some(EndBehavior::Record) => {
+                        self.notify_main_window(|main_window| main_window.continue_recording());
+                    }
+                    // Closing the movie restores save memory and removes the journal, which
+                    // can't be done from a core callback.
+                    Some(EndBehavior::Stop) => {
+                        self.notify_main_window(|main_window| main_window.close_movie());
+                    }
+                    _ => (),
+                }
             }
         }
         input
@@ -467,11 +1134,63 @@ impl InputHandler for CoreInputHandler {
     }
 }
 
+impl CoreFrameHandler {
+    fn notify_main_window<F: FnOnce(&MainWindow) + Send + 'static>(&self, f: F) {
+        let main_window_ref = self.main_window_ref.clone();
+        let _ = glib::spawn_future(async move {
+            main_window_ref.upgrade().inspect(f);
+        });
+    }
+}
 impl FrameHandler for CoreFrameHandler {
     fn new_frame(&mut self, _count: std::ffi::c_uint) {
+        let mut froze = false;
+        {
+            let mut watch_link = block_on(self.watch_link.lock());
+            if let Some(link) = watch_link.as_ref() {
+                froze = link.apply_freezes(&self.memory);
+                if !link.send_values(&self.memory) {
+                    *watch_link = None;
+                }
+            }
+        }
+        {
+            let mut rdram_requests = block_on(self.rdram_requests.lock());
+            if !rdram_requests.is_empty() {
+                if let Some(rdram) = copy_rdram(&self.memory) {
+                    for request in rdram_requests.drain(..) {
+                        let _ = request.send(rdram.clone());
+                    }
+                }
+            }
+        }
+
         let mut vcr_state = block_on(self.vcr_state.lock());
         if let Some(vcr_state) = vcr_state.as_mut() {
+            let cheated = self.cheats_active.load(Ordering::Acquire);
+            if (froze || cheated) && vcr_state.note_memory_modified() {
+                self.notify_main_window(|main_window| main_window.report_modified_memory());
+            }
             vcr_state.tick_vi();
+            if vcr_state.checksum_due() {
+                // SAFETY: this is a core callback, so the emulator is stopped until we return.
+                let rdram = unsafe { self.memory.rdram() };
+                if let Some(desync) = rdram.and_then(|rdram| vcr_state.check_memory(rdram)) {
+                    self.notify_main_window(move |main_window| main_window.report_desync(desync));
+                }
+            }
+            if vcr_state.greenzone_due() && vcr_state.greenzone_mut().begin_capture() {
+                self.notify_main_window(|main_window| main_window.capture_greenzone());
+            }
+
+            let mut seek_target = block_on(self.seek_target.lock());
+            if seek_target.is_some_and(|target| vcr_state.reached(target)) {
+                *seek_target = None;
+                if let Err(err) = self.control.request_pause() {
+                    log::warn!("failed to pause after seeking: {}", err);
+                }
+                self.notify_main_window(|main_window| main_window.finish_seek());
+            }
         }
     }
 }
@@ -482,6 +1201,8 @@ impl SaveHandler for CoreSaveHandler {
     fn save_xd(&mut self) -> Result<Box<[u8]>, Box<dyn Error>> {
         let mut vcr_state = block_on(self.vcr_state.lock());
         if let Some(vcr_state) = vcr_state.as_mut() {
+            let (index, vi_count) = (vcr_state.index(), vcr_state.vi_count());
+            vcr_state.greenzone_mut().note_save(index, vi_count);
             Ok(bincode::serialize(&vcr_state.freeze())?.into_boxed_slice())
         } else {
             Ok(Box::new([]))
@@ -1,7 +1,7 @@
 use m64prs_core::{error::M64PError, Core};
 use std::{
     ops::Deref,
-    sync::Arc,
+    sync::{Arc, Mutex},
     thread::{self, JoinHandle},
 };
 
@@ -11,22 +11,35 @@ pub(super) struct RunningCore(Option<RunningCoreInner>);
 #[derive(Debug)]
 struct RunningCoreInner {
     core: Arc<Core>,
-    join_handle: JoinHandle<Result<(), M64PError>>,
+    /// Only empty while emulation is being restarted.
+    join_handle: Mutex<Option<JoinHandle<Result<(), M64PError>>>>,
 }
 
 impl RunningCore {
     pub(super) fn execute(core: Core) -> Self {
         let core = Arc::new(core);
-        let join_handle = {
-            let core = Arc::clone(&core);
-            thread::spawn(move || {
-                let result = core.execute();
-                result
-            })
-        };
+        let join_handle = Mutex::new(Some(spawn_execute(&core)));
         Self(Some(RunningCoreInner { core, join_handle }))
     }
 
+    /// Stops emulation, calls `stopped`, then starts emulation again with the same ROM and
+    /// plugins. Unlike a hard reset, this makes the core read the ROM's save memory again.
+    pub(super) async fn restart<F, R>(&self, stopped: F) -> R
+    where
+        F: FnOnce(&Core) -> R,
+    {
+        let inner = self.0.as_ref().unwrap();
+        let join_handle = inner.join_handle.lock().unwrap().take().unwrap();
+        let _ = inner.core.request_stop();
+        let _ = gio::spawn_blocking(move || join_handle.join().unwrap())
+            .await
+            .unwrap();
+
+        let result = stopped(&inner.core);
+        *inner.join_handle.lock().unwrap() = Some(spawn_execute(&inner.core));
+        result
+    }
+
     pub(super) fn stop(mut self) -> (Core, Result<(), M64PError>) {
         self.0.take().unwrap().stop()
     }
@@ -48,10 +61,19 @@ impl Deref for RunningCore {
     }
 }
 
+fn spawn_execute(core: &Arc<Core>) -> JoinHandle<Result<(), M64PError>> {
+    let core = Arc::clone(core);
+    thread::spawn(move || {
+        let result = core.execute();
+        result
+    })
+}
+
 impl RunningCoreInner {
     fn stop(self) -> (Core, Result<(), M64PError>) {
         let _ = self.core.request_stop();
-        let result = self.join_handle.join().unwrap();
+        let join_handle = self.join_handle.into_inner().unwrap().unwrap();
+        let result = join_handle.join().unwrap();
         let core = Arc::into_inner(self.core).expect("this should be the only reference");
         (core, result)
     }
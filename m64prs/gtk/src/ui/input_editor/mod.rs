@@ -0,0 +1,376 @@
+use m64prs_sys::Buttons;
+
+mod row;
+
+mod inner {
+    use std::cell::{Cell, RefCell};
+
+    use futures::channel::mpsc;
+    use glib::{closure, subclass::InitializingObject};
+    use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
+    use m64prs_sys::{ButtonFlags, Buttons};
+
+    use crate::ui::core::InputSnapshot;
+
+    use super::{row::InputRow, InputEdit};
+
+    /// Button columns, in the order they are shown.
+    const BUTTON_COLUMNS: [(&str, ButtonFlags); 14] = [
+        ("A", ButtonFlags::A),
+        ("B", ButtonFlags::B),
+        ("Z", ButtonFlags::Z),
+        ("S", ButtonFlags::START),
+        ("L", ButtonFlags::L),
+        ("R", ButtonFlags::R),
+        ("C^", ButtonFlags::C_UP),
+        ("Cv", ButtonFlags::C_DOWN),
+        ("C<", ButtonFlags::C_LEFT),
+        ("C>", ButtonFlags::C_RIGHT),
+        ("D^", ButtonFlags::D_UP),
+        ("Dv", ButtonFlags::D_DOWN),
+        ("D<", ButtonFlags::D_LEFT),
+        ("D>", ButtonFlags::D_RIGHT),
+    ];
+
+    #[derive(gtk::CompositeTemplate)]
+    #[template(file = "mod.ui")]
+    pub struct InputEditor {
+        #[template_child]
+        input_view: TemplateChild<gtk::ColumnView>,
+        #[template_child]
+        x_field: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        y_field: TemplateChild<gtk::SpinButton>,
+
+        rows: gio::ListStore,
+        selection: gtk::MultiSelection,
+
+        /// The inputs shown, kept in sync with the rows.
+        inputs: RefCell<Vec<Buttons>>,
+        frame_size: Cell<u32>,
+        /// Index of the next sample to be played.
+        current: Cell<u32>,
+        clipboard: RefCell<Vec<Buttons>>,
+
+        edit_tx: RefCell<Option<mpsc::UnboundedSender<InputEdit>>>,
+        edit_rx: RefCell<Option<mpsc::UnboundedReceiver<InputEdit>>>,
+    }
+
+    #[m64prs_gtk_utils::forward_wrapper(super::InputEditor, vis = pub(in super::super))]
+    impl InputEditor {
+        /// Takes the stream of edits made in the editor. The stream ends when the editor
+        /// is closed.
+        pub(super) fn take_edits(&self) -> Option<mpsc::UnboundedReceiver<InputEdit>> {
+            self.edit_rx.take()
+        }
+
+        /// Updates the editor with the movie's current inputs and position.
+        pub(super) fn update(&self, snapshot: InputSnapshot) {
+            self.highlight(false);
+            self.frame_size.set(snapshot.frame_size.max(1));
+            self.current.set(snapshot.index);
+            if let Some(inputs) = snapshot.inputs {
+                self.set_inputs(inputs);
+            }
+            self.highlight(true);
+        }
+    }
+
+    impl InputEditor {
+        /// Replaces the inputs shown, only rebuilding the rows that changed.
+        fn set_inputs(&self, inputs: Vec<Buttons>) {
+            let old = self.inputs.replace(inputs);
+            let new = self.inputs.borrow();
+
+            let prefix = old
+                .iter()
+                .zip(new.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            // Rows show their index, so every row after an insertion or deletion changes.
+            let suffix = match old.len() == new.len() {
+                true => old[prefix..]
+                    .iter()
+                    .rev()
+                    .zip(new[prefix..].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count(),
+                false => 0,
+            };
+
+            let added: Vec<InputRow> = (prefix..(new.len() - suffix))
+                .map(|index| InputRow::new(index as u32, new[index]))
+                .collect();
+            self.rows
+                .splice(prefix as u32, (old.len() - suffix - prefix) as u32, &added);
+            drop(new);
+            self.highlight(true);
+        }
+
+        /// Marks the rows of the next frame to be played.
+        fn highlight(&self, value: bool) {
+            let start = self.current.get();
+            for index in start..start.saturating_add(self.frame_size.get()) {
+                if let Some(row) = self.rows.item(index).and_downcast::<InputRow>() {
+                    row.set_current(value);
+                }
+            }
+        }
+
+        /// Edits the inputs, and sends the edit to the movie.
+        fn apply(&self, start: u32, len: u32, samples: Vec<Buttons>) {
+            let mut inputs = self.inputs.borrow().clone();
+            let splice_start = (start as usize).min(inputs.len());
+            let splice_end = splice_start.saturating_add(len as usize).min(inputs.len());
+            inputs.splice(splice_start..splice_end, samples.iter().copied());
+            self.set_inputs(inputs);
+
+            if let Some(tx) = &*self.edit_tx.borrow() {
+                let _ = tx.unbounded_send(InputEdit {
+                    start,
+                    len,
+                    samples,
+                });
+            }
+        }
+
+        /// Returns the range of samples covering every selected frame.
+        fn selected_frames(&self) -> Option<(u32, u32)> {
+            let selection = self.selection.selection();
+            if selection.is_empty() {
+                return None;
+            }
+            let frame_size = self.frame_size.get();
+            let len = self.inputs.borrow().len() as u32;
+            let start = selection.minimum() / frame_size * frame_size;
+            let end = (selection.maximum() / frame_size + 1) * frame_size;
+            Some((start, end.min(len)))
+        }
+
+        fn add_column(
+            &self,
+            title: &str,
+            text: impl Fn(&InputRow) -> String + 'static,
+            toggle: Option<ButtonFlags>,
+        ) {
+            let factory = gtk::SignalListItemFactory::new();
+            let editor_ref = self.obj().downgrade();
+            factory.connect_setup(move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let label = gtk::Label::new(None);
+                item.property_expression("item")
+                    .chain_property::<InputRow>("current")
+                    .chain_closure::<Vec<String>>(closure!(
+                        |_: Option<glib::Object>, current: bool| match current {
+                            true => vec!["accent".to_owned()],
+                            false => vec![],
+                        }
+                    ))
+                    .bind(&label, "css-classes", gtk::Widget::NONE);
+
+                if let Some(flag) = toggle {
+                    let gesture = gtk::GestureClick::new();
+                    let item_ref = item.downgrade();
+                    let editor_ref = editor_ref.clone();
+                    gesture.connect_pressed(move |_, _, _, _| {
+                        let (Some(item), Some(editor)) = (item_ref.upgrade(), editor_ref.upgrade())
+                        else {
+                            return;
+                        };
+                        let Some(row) = item.item().and_downcast::<InputRow>() else {
+                            return;
+                        };
+                        let mut sample = row.sample();
+                        sample.button_bits.toggle(flag);
+                        editor.imp().apply(row.index(), 1, vec![sample]);
+                    });
+                    label.add_controller(gesture);
+                }
+                item.set_child(Some(&label));
+            });
+            factory.connect_bind(move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let (Some(row), Some(label)) = (
+                    item.item().and_downcast::<InputRow>(),
+                    item.child().and_downcast::<gtk::Label>(),
+                ) else {
+                    return;
+                };
+                label.set_text(&text(&row));
+            });
+
+            let column = gtk::ColumnViewColumn::new(Some(title), Some(factory));
+            self.input_view.append_column(&column);
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl InputEditor {
+        #[template_callback]
+        fn insert_clicked(&self, _: &gtk::Button) {
+            let start = match self.selected_frames() {
+                Some((start, _)) => start,
+                None => self.inputs.borrow().len() as u32,
+            };
+            let frame_size = self.frame_size.get() as usize;
+            self.apply(start, 0, vec![Buttons::BLANK; frame_size]);
+        }
+
+        #[template_callback]
+        fn delete_clicked(&self, _: &gtk::Button) {
+            if let Some((start, end)) = self.selected_frames() {
+                self.apply(start, end - start, Vec::new());
+                self.selection.unselect_all();
+            }
+        }
+
+        #[template_callback]
+        fn duplicate_clicked(&self, _: &gtk::Button) {
+            if let Some((start, end)) = self.selected_frames() {
+                let samples = self.inputs.borrow()[(start as usize)..(end as usize)].to_vec();
+                self.apply(end, 0, samples);
+            }
+        }
+
+        #[template_callback]
+        fn copy_clicked(&self, _: &gtk::Button) {
+            if let Some((start, end)) = self.selected_frames() {
+                *self.clipboard.borrow_mut() =
+                    self.inputs.borrow()[(start as usize)..(end as usize)].to_vec();
+            }
+        }
+
+        #[template_callback]
+        fn paste_clicked(&self, _: &gtk::Button) {
+            let samples = self.clipboard.borrow().clone();
+            if samples.is_empty() {
+                return;
+            }
+            let start = match self.selected_frames() {
+                Some((start, _)) => start,
+                None => self.inputs.borrow().len() as u32,
+            };
+            self.apply(start, samples.len() as u32, samples);
+        }
+
+        #[template_callback]
+        fn set_stick_clicked(&self, _: &gtk::Button) {
+            let selection = self.selection.selection();
+            if selection.is_empty() {
+                return;
+            }
+            let (first, last) = (selection.minimum(), selection.maximum());
+            let x_axis = self.x_field.value_as_int() as i8;
+            let y_axis = self.y_field.value_as_int() as i8;
+
+            let mut samples = self.inputs.borrow()[(first as usize)..=(last as usize)].to_vec();
+            for (index, sample) in (first..).zip(samples.iter_mut()) {
+                if selection.contains(index) {
+                    sample.x_axis = x_axis;
+                    sample.y_axis = y_axis;
+                }
+            }
+            self.apply(first, last - first + 1, samples);
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for InputEditor {
+        const NAME: &'static str = "M64PRS_InputEditor";
+        type Type = super::InputEditor;
+        type ParentType = gtk::Window;
+
+        fn new() -> Self {
+            let rows = gio::ListStore::new::<InputRow>();
+            let selection = gtk::MultiSelection::new(Some(rows.clone()));
+            let (edit_tx, edit_rx) = mpsc::unbounded();
+
+            Self {
+                input_view: Default::default(),
+                x_field: Default::default(),
+                y_field: Default::default(),
+                rows,
+                selection,
+                inputs: Default::default(),
+                frame_size: Cell::new(1),
+                current: Cell::new(0),
+                clipboard: Default::default(),
+                edit_tx: RefCell::new(Some(edit_tx)),
+                edit_rx: RefCell::new(Some(edit_rx)),
+            }
+        }
+
+        fn class_init(class: &mut Self::Class) {
+            InputRow::ensure_type();
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for InputEditor {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.input_view.set_model(Some(&self.selection));
+
+            self.add_column("#", |row| row.index().to_string(), None);
+            self.add_column("X", |row| row.x_axis().to_string(), None);
+            self.add_column("Y", |row| row.y_axis().to_string(), None);
+            for (title, flag) in BUTTON_COLUMNS {
+                self.add_column(
+                    title,
+                    move |row| match row.buttons() & flag.bits() as u32 != 0 {
+                        true => title.to_owned(),
+                        false => String::new(),
+                    },
+                    Some(flag),
+                );
+            }
+        }
+
+        fn dispose(&self) {
+            self.dispose_template();
+        }
+    }
+    impl WidgetImpl for InputEditor {}
+    impl WindowImpl for InputEditor {
+        fn close_request(&self) -> glib::Propagation {
+            // Ends the stream of edits.
+            self.edit_tx.take();
+            glib::Propagation::Proceed
+        }
+    }
+}
+
+/// An edit made in the [`InputEditor`]: replace `len` samples starting at `start`
+/// with `samples`.
+#[derive(Debug, Clone)]
+pub struct InputEdit {
+    pub start: u32,
+    pub len: u32,
+    pub samples: Vec<Buttons>,
+}
+
+glib::wrapper! {
+    /// Piano-roll view of the active movie's inputs.
+    pub struct InputEditor(ObjectSubclass<inner::InputEditor>)
+    @extends
+        gtk::Window,
+        gtk::Widget,
+    @implements
+        gtk::Accessible,
+        gtk::Buildable,
+        gtk::ConstraintTarget,
+        gtk::Native,
+        gtk::Root,
+        gtk::ShortcutManager;
+}
+
+impl InputEditor {
+    pub(super) fn new() -> Self {
+        glib::Object::new()
+    }
+}
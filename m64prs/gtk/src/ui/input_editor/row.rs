@@ -0,0 +1,57 @@
+use m64prs_sys::{ButtonFlags, Buttons};
+
+mod inner {
+    use std::cell::Cell;
+
+    use gtk::{prelude::*, subclass::prelude::*};
+
+    /// One input sample, as shown in the input editor.
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::InputRow)]
+    pub struct InputRow {
+        #[property(get, construct_only)]
+        index: Cell<u32>,
+        #[property(get, construct_only)]
+        buttons: Cell<u32>,
+        #[property(get, construct_only)]
+        x_axis: Cell<i32>,
+        #[property(get, construct_only)]
+        y_axis: Cell<i32>,
+        /// True if this sample belongs to the next frame to be played.
+        #[property(get, set)]
+        current: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for InputRow {
+        const NAME: &'static str = "M64PRS_InputRow";
+        type Type = super::InputRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for InputRow {}
+}
+
+glib::wrapper! {
+    pub struct InputRow(ObjectSubclass<inner::InputRow>);
+}
+
+impl InputRow {
+    pub fn new(index: u32, sample: Buttons) -> Self {
+        glib::Object::builder()
+            .property("index", index)
+            .property("buttons", sample.button_bits.bits() as u32)
+            .property("x-axis", sample.x_axis as i32)
+            .property("y-axis", sample.y_axis as i32)
+            .build()
+    }
+
+    /// Returns the sample this row shows.
+    pub fn sample(&self) -> Buttons {
+        Buttons {
+            button_bits: ButtonFlags::from_bits_retain(self.buttons() as _),
+            x_axis: self.x_axis() as i8,
+            y_axis: self.y_axis() as i8,
+        }
+    }
+}
@@ -1,18 +1,48 @@
-use std::{cell::RefCell, error::Error, ffi::CString, io, path::Path};
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::CString,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use futures::channel::oneshot;
-use gtk::prelude::*;
-use m64prs_core::{
-    error::PluginLoadError,
-    plugin::{PluginSet, PluginType},
-    Plugin,
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+    StreamExt,
 };
+use gtk::prelude::*;
+use m64prs_core::{cheat::CheatCode, error::PluginLoadError, plugin::PluginSet, Plugin};
 use m64prs_gtk_utils::actions::{BaseAction, StateAction, StateParamAction, TypedActionGroup};
-use m64prs_vcr::{movie::M64File, VcrState};
+use m64prs_vcr::{
+    branch::BranchSet,
+    bundle::{BundleError, MovieBundle},
+    checksum::{ChecksumLog, ChecksumSettings},
+    greenzone::SeekTarget,
+    journal::Journal,
+    movie::{M64File, MovieRepair},
+    VcrState,
+};
 use tr::tr;
 
 use crate::{
-    ui::{main_window::enums::MainEmuState, settings_dialog::SettingsDialog},
+    ui::{
+        branch_dialog::{BranchCommand, BranchDialog},
+        cheat_manager::{
+            database::{self, Cheat},
+            CheatCommand, CheatEntry, CheatManager,
+        },
+        input_editor::InputEditor,
+        main_window::enums::MainEmuState,
+        ram_search::{search::Search, RamSearch, SearchCommand},
+        ram_watch::{
+            watch::{self, Watch},
+            RamWatch,
+        },
+        seek_dialog::SeekDialog,
+        settings_dialog::{SettingsDialog, CHECKSUM_INTERVAL_KEY, VCR_SECTION_KEY},
+    },
     utils::paths::INSTALL_DIRS,
 };
 
@@ -35,6 +65,7 @@ pub fn load_menu() -> gio::MenuModel {
             section(None, [
                 item(&tr!("main_act" => "Pause/Resume"), "app.emu.toggle_pause"),
                 item(&tr!("main_act" => "Frame Advance"), "app.emu.frame_advance"),
+                item(&tr!("main_act" => "Advance to Next Input Poll"), "app.emu.advance_to_poll"),
                 item(&tr!("main_act" => "Reset ROM"), "app.emu.reset_rom"),
             ]),
             section(None, [
@@ -55,6 +86,17 @@ pub fn load_menu() -> gio::MenuModel {
             item(&tr!("main_act" => "Save Movie"), "app.vcr.save_movie"),
             item(&tr!("main_act" => "Close Movie"), "app.vcr.close_movie"),
             item(&tr!("main_act" => "Read-only Mode"), "app.vcr.toggle_read_only"),
+            item(&tr!("main_act" => "Undo Input Change"), "app.vcr.undo"),
+            item(&tr!("main_act" => "Redo Input Change"), "app.vcr.redo"),
+            item(&tr!("main_act" => "Rewind One Frame"), "app.vcr.rewind"),
+            item(&tr!("main_act" => "Seek to Frame..."), "app.vcr.seek"),
+            item(&tr!("main_act" => "Branches..."), "app.vcr.branches"),
+            item(&tr!("main_act" => "Input Editor"), "app.vcr.input_editor"),
+        ]),
+        submenu(Some(&tr!("main_act" => "Tools")), [
+            item(&tr!("main_act" => "RAM Watch"), "app.tools.ram_watch"),
+            item(&tr!("main_act" => "RAM Search"), "app.tools.ram_search"),
+            item(&tr!("main_act" => "Cheats"), "app.tools.cheats"),
         ]),
     ]).upcast()
 }
@@ -72,6 +114,8 @@ pub(super) struct AppActions {
     toggle_pause: StateAction<bool>,
     #[action(name = "emu.frame_advance")]
     frame_advance: BaseAction,
+    #[action(name = "emu.advance_to_poll")]
+    advance_to_poll: BaseAction,
     #[action(name = "emu.reset_rom")]
     reset_rom: BaseAction,
 
@@ -96,6 +140,25 @@ pub(super) struct AppActions {
     close_movie: BaseAction,
     #[action(name = "vcr.toggle_read_only", default = false)]
     toggle_read_only: StateAction<bool>,
+    #[action(name = "vcr.undo")]
+    undo: BaseAction,
+    #[action(name = "vcr.redo")]
+    redo: BaseAction,
+    #[action(name = "vcr.rewind")]
+    rewind: BaseAction,
+    #[action(name = "vcr.seek")]
+    seek: BaseAction,
+    #[action(name = "vcr.branches")]
+    branches: BaseAction,
+    #[action(name = "vcr.input_editor")]
+    input_editor: BaseAction,
+
+    #[action(name = "tools.ram_watch")]
+    ram_watch: BaseAction,
+    #[action(name = "tools.ram_search")]
+    ram_search: BaseAction,
+    #[action(name = "tools.cheats")]
+    cheats: BaseAction,
 }
 
 impl Default for AppActions {
@@ -200,6 +263,7 @@ impl AppActions {
 
         c!(toggle_pause, async toggle_pause_impl);
         c!(frame_advance, async frame_advance_impl);
+        c!(advance_to_poll, async advance_to_poll_impl);
         c!(reset_rom, async reset_rom_impl);
 
         c!(save_slot, async save_slot_impl);
@@ -213,6 +277,16 @@ impl AppActions {
         c!(save_movie, async save_movie_impl);
         c!(close_movie, async close_movie_impl);
         c!(toggle_read_only, async toggle_read_only_impl);
+        c!(undo, async undo_impl);
+        c!(redo, async redo_impl);
+        c!(rewind, async rewind_impl);
+        c!(seek, async seek_impl);
+        c!(branches, async branches_impl);
+        c!(input_editor, async input_editor_impl);
+
+        c!(ram_watch, async ram_watch_impl);
+        c!(ram_search, async ram_search_impl);
+        c!(cheats, async cheats_impl);
     }
 
     fn bind_states(&self, main_window: &MainWindow) {
@@ -288,6 +362,7 @@ impl AppActions {
         b!(toggle_pause."enabled" => emu_active);
         b!(toggle_pause."state" => emu_paused_gvar);
         b!(frame_advance."enabled" => emu_active);
+        b!(advance_to_poll."enabled" => emu_active);
         b!(reset_rom."enabled" => emu_active);
 
         b!(save_slot."enabled" => can_save);
@@ -303,12 +378,71 @@ impl AppActions {
         b!(close_movie."enabled" => has_vcr);
         b!(toggle_read_only."enabled" => emu_active);
         b!(toggle_read_only."state" => vcr_read_only_gvar);
+        b!(undo."enabled" => has_vcr);
+        b!(redo."enabled" => has_vcr);
+        b!(rewind."enabled" => has_vcr);
+        b!(seek."enabled" => has_vcr);
+        b!(branches."enabled" => has_vcr);
+        b!(input_editor."enabled" => has_vcr);
+
+        b!(ram_watch."enabled" => emu_active);
+        b!(ram_search."enabled" => emu_active);
+        b!(cheats."enabled" => emu_active);
     }
 }
 
 // HELPERS
 // =====================
 
+/// Returns the open window of type `W`, if there is one.
+fn find_window<W: IsA<gtk::Window>>() -> Option<W> {
+    gtk::Window::toplevels()
+        .iter::<glib::Object>()
+        .filter_map(Result::ok)
+        .find_map(|window| window.downcast::<W>().ok())
+}
+
+/// Adds watches to the open RAM watch window. If it isn't open, the watches are added to
+/// the ROM's saved watches and the window is opened.
+async fn add_watches(main_window: &MainWindow, watches: Vec<Watch>) -> Result<(), Box<dyn Error>> {
+    if let Some(window) = find_window::<RamWatch>() {
+        window.add_watches(watches);
+        window.present();
+        return Ok(());
+    }
+
+    let rom_crc = {
+        let core_ref = main_window.borrow_core().await;
+        let Some(core) = core_ref.borrow_running() else {
+            return Ok(());
+        };
+        core.rom_header().CRC1
+    };
+    gio::spawn_blocking(move || {
+        let mut saved = watch::load_watches(rom_crc)?;
+        saved.extend(watches);
+        watch::save_watches(rom_crc, &saved)
+    })
+    .await
+    .unwrap()?;
+    main_window.activate_action("app.tools.ram_watch", None)?;
+    Ok(())
+}
+
+/// Reads a file stored next to a movie, returning `None` if it doesn't exist.
+async fn read_sidecar<T: Send + 'static>(
+    path: PathBuf,
+    read: fn(io::BufReader<std::fs::File>) -> io::Result<T>,
+) -> io::Result<Option<T>> {
+    gio::spawn_blocking(move || match std::fs::File::open(path) {
+        Ok(file) => read(io::BufReader::new(file)).map(Some),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    })
+    .await
+    .unwrap()
+}
+
 /// Helper function to lock savestate actions while
 /// one is already happening.
 struct SaveOpGuard<'a> {
@@ -470,22 +604,32 @@ async fn settings_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
 }
 
 async fn toggle_pause_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    let core_ref = main_window.borrow_core().await;
+    let core = core_ref.borrow_running().expect("Core should be running");
+    // Pausing during a seek stops the seek where it is.
+    if !core.cancel_seek().await? {
+        core.toggle_pause()?;
+    }
+    Ok(())
+}
+
+async fn frame_advance_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
     main_window
         .borrow_core()
         .await
         .borrow_running()
         .expect("Core should be running")
-        .toggle_pause()?;
+        .frame_advance()?;
     Ok(())
 }
 
-async fn frame_advance_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+async fn advance_to_poll_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
     main_window
         .borrow_core()
         .await
         .borrow_running()
         .expect("Core should be running")
-        .frame_advance()?;
+        .advance_to_poll()?;
     Ok(())
 }
 
@@ -495,7 +639,8 @@ async fn reset_rom_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>>
         .await
         .borrow_running()
         .expect("Core should be running")
-        .reset(false)?;
+        .reset(false)
+        .await?;
     Ok(())
 }
 
@@ -600,39 +745,27 @@ async fn new_movie_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>>
         let core_ref = main_window.borrow_core().await;
         let core = core_ref.borrow_running().expect("Core should be running");
 
-        let rom_header = core.rom_header();
-        header.rom_cc = rom_header.Country_code as u16;
-        header.rom_crc = rom_header.CRC1;
-
-        let _ = header.graphics_plugin.write_clipped(
-            &core
-                .plugin_info(PluginType::Graphics)
-                .plugin_name
-                .to_string_lossy(),
-        );
-        let _ = header.audio_plugin.write_clipped(
-            &core
-                .plugin_info(PluginType::Audio)
-                .plugin_name
-                .to_string_lossy(),
-        );
-        let _ = header.input_plugin.write_clipped(
-            &core
-                .plugin_info(PluginType::Input)
-                .plugin_name
-                .to_string_lossy(),
-        );
-        let _ = header.rsp_plugin.write_clipped(
-            &core
-                .plugin_info(PluginType::Rsp)
-                .plugin_name
-                .to_string_lossy(),
-        );
+        core.movie_environment().apply_to(&mut header);
+
+        let checksum_interval: i32 = core
+            .cfg_open(VCR_SECTION_KEY)
+            .ok()
+            .and_then(|sect| sect.get_cast_or(0, CHECKSUM_INTERVAL_KEY).ok())
+            .unwrap_or(0);
 
-        let vcr_state = VcrState::new(path, header, false);
+        let mut vcr_state = VcrState::new(path, header, false);
+        if checksum_interval > 0 {
+            vcr_state.set_checksums(Some(ChecksumLog::new(ChecksumSettings {
+                interval: checksum_interval as u32,
+                regions: Vec::new(),
+            })));
+        }
         core.set_read_only(false);
         core.set_vcr_state(vcr_state, true).await?;
     };
+    // Write the movie out immediately, so that there's a file to recover into
+    // if the emulator crashes.
+    save_movie_impl(main_window).await?;
 
     Ok(())
 }
@@ -643,17 +776,132 @@ async fn load_movie_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>>
         Some(file) => file,
         None => return Ok(()),
     };
-    let reader = movie_file
-        .read_future(glib::Priority::DEFAULT)
-        .await?
-        .into_async_buf_read(4096);
+    let movie_path = movie_file.path().unwrap();
+
+    // Damaged movies are only repaired if the user agrees, since saving a repaired movie
+    // replaces the original.
+    let is_bundle = MovieBundle::is_bundle_path(&movie_path);
+    let (mut movie, bundle_files, checksums, branches) = if is_bundle {
+        let read_bundle = |lenient: bool| {
+            let path = movie_path.clone();
+            gio::spawn_blocking(move || -> Result<_, BundleError> {
+                let reader = io::BufReader::new(std::fs::File::open(path)?);
+                match lenient {
+                    true => MovieBundle::read_from_lenient(reader),
+                    false => MovieBundle::read_from(reader).map(|bundle| (bundle, Vec::new())),
+                }
+            })
+        };
+        let bundle = match read_bundle(false).await.unwrap() {
+            Ok((bundle, _)) => bundle,
+            Err(BundleError::Movie(err)) if err.is_repairable() => {
+                let (bundle, repairs) = read_bundle(true).await.unwrap()?;
+                if !confirm_repairs(main_window, &repairs).await {
+                    return Ok(());
+                }
+                bundle
+            }
+            Err(err) => return Err(err.into()),
+        };
+        (bundle.movie, Some(bundle.files), None, None)
+    } else {
+        let (data, _) = movie_file.load_contents_future().await?;
+        let movie = match M64File::read_from(&data[..]) {
+            Ok(movie) => movie,
+            Err(err) if err.is_repairable() => {
+                let (movie, repairs) = M64File::read_from_lenient(&data[..])?;
+                if !confirm_repairs(main_window, &repairs).await {
+                    return Ok(());
+                }
+                movie
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let checksums = read_sidecar(ChecksumLog::path_for(&movie_path), ChecksumLog::read_from)
+            .await
+            .unwrap_or_else(|err| {
+                log::warn!("failed to read movie checksums: {}", err);
+                None
+            });
+        let branches = read_sidecar(BranchSet::path_for(&movie_path), BranchSet::read_from)
+            .await
+            .unwrap_or_else(|err| {
+                log::warn!("failed to read movie branches: {}", err);
+                None
+            });
+        (movie, None, checksums, branches)
+    };
+
+    // If the emulator crashed while this movie was being recorded, offer to restore it.
+    let recovered = {
+        let movie_path = movie_path.clone();
+        gio::spawn_blocking(move || Journal::recover(&movie_path))
+            .await
+            .unwrap()
+    };
+    match recovered {
+        Ok(Some(recovered)) => {
+            let recover = main_window
+                .show_confirm_dialog(
+                    &tr!("This movie has unsaved changes from a previous session."),
+                    &tr!("Do you want to recover them?"),
+                    &tr!("Recover"),
+                    &tr!("Discard"),
+                )
+                .await;
+            if recover {
+                movie = recovered;
+            }
+        }
+        Ok(None) => (),
+        Err(err) => log::warn!("failed to read movie journal: {}", err),
+    }
 
-    let movie = M64File::read_from_async(reader).await?;
+    let mismatches = {
+        let core_ref = main_window.borrow_core().await;
+        let core = core_ref.borrow_running().expect("Core should be running");
+        core.movie_environment().check(&movie.header)
+    };
+    if !mismatches.is_empty() {
+        let detail = mismatches
+            .iter()
+            .map(|mismatch| format!("- {}", mismatch))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let proceed = main_window
+            .show_confirm_dialog(
+                &tr!("This movie was recorded with a different ROM or plugins, and may desync."),
+                &detail,
+                &tr!("Load Anyway"),
+                &tr!("Cancel"),
+            )
+            .await;
+        if !proceed {
+            return Ok(());
+        }
+    }
 
     {
         let core_ref = main_window.borrow_core().await;
         let core = core_ref.borrow_running().expect("Core should be running");
-        let vcr_state = VcrState::with_m64(movie_file.path().unwrap(), movie, true);
+        let mut vcr_state = match bundle_files {
+            Some(files) => VcrState::with_bundle(
+                movie_path,
+                MovieBundle {
+                    files,
+                    ..MovieBundle::new(movie)
+                },
+                true,
+            ),
+            None => VcrState::with_m64(movie_path, movie, true),
+        };
+        if checksums.is_some() {
+            vcr_state.set_checksums(checksums);
+        }
+        if let Some(branches) = branches {
+            vcr_state.set_branches(branches);
+        }
         core.set_read_only(true);
         core.set_vcr_state(vcr_state, false).await?;
     }
@@ -661,48 +909,63 @@ async fn load_movie_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>>
     Ok(())
 }
 
+/// Asks whether to load a damaged movie with the given repairs.
+async fn confirm_repairs(main_window: &MainWindow, repairs: &[MovieRepair]) -> bool {
+    let detail = repairs
+        .iter()
+        .map(|repair| format!("- {}", repair))
+        .collect::<Vec<_>>()
+        .join("\n");
+    main_window
+        .show_confirm_dialog(
+            &tr!("This movie is damaged. Do you want to load it with these repairs?"),
+            &tr!(
+                "{}\n\nSaving the movie in read-write mode will replace the original file.",
+                detail
+            ),
+            &tr!("Repair"),
+            &tr!("Cancel"),
+        )
+        .await
+}
+
 async fn save_movie_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
     let core_state = main_window.borrow_core().await;
     let running_state = core_state.borrow_running().expect("Core should be running");
     let exported = running_state.export_vcr().await;
 
     if let Some((path, data)) = exported {
-        gio::spawn_blocking(move || -> Result<(), io::Error> {
-            let mut file = std::fs::File::create(&path)?;
-            data.write_into(&mut file)?;
-            Ok(())
-        })
-        .await
-        .unwrap()?;
+        gio::spawn_blocking(move || data.save(&path))
+            .await
+            .unwrap()?;
+        running_state.checkpoint_vcr_journal().await;
     }
 
     Ok(())
 }
 
 async fn close_movie_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
-    let vcr_state = main_window
-        .borrow_core()
-        .await
-        .borrow_running()
-        .expect("Core should be running")
-        .unset_vcr_state()
-        .await;
+    let core_ref = main_window.borrow_core().await;
+    let core = core_ref.borrow_running().expect("Core should be running");
+    let vcr_state = core.unset_vcr_state().await;
     let vcr_read_only = main_window.vcr_read_only();
 
+    let Some(mut vcr_state) = vcr_state else {
+        return Ok(());
+    };
+
     // Only save if we're not in read-only mode.
-    if let (false, Some(vcr_state)) = (vcr_read_only, vcr_state) {
+    if !vcr_read_only {
         // GIO async doesn't work for us. Move the data to a blocking task,
         // then await that instead.
         let (path, data) = vcr_state.export();
-        gio::spawn_blocking(move || -> Result<(), io::Error> {
-            let mut file = std::fs::File::create(&path)?;
-            data.write_into(&mut file)?;
-
-            Ok(())
-        })
-        .await
-        .unwrap()?;
+        gio::spawn_blocking(move || data.save(&path))
+            .await
+            .unwrap()?;
     }
+    // The movie is either saved or intentionally discarded, so the journal is no longer needed.
+    vcr_state.remove_journal()?;
+    core.restore_save_ram(&mut vcr_state).await?;
 
     Ok(())
 }
@@ -716,3 +979,419 @@ async fn toggle_read_only_impl(main_window: &MainWindow) -> Result<(), Box<dyn E
         .toggle_read_only();
     Ok(())
 }
+
+async fn undo_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    let core_ref = main_window.borrow_core().await;
+    let core = core_ref.borrow_running().expect("Core should be running");
+    if !core.undo_vcr().await {
+        main_window.error_bell();
+    }
+    Ok(())
+}
+
+async fn redo_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    let core_ref = main_window.borrow_core().await;
+    let core = core_ref.borrow_running().expect("Core should be running");
+    if !core.redo_vcr().await {
+        main_window.error_bell();
+    }
+    Ok(())
+}
+
+async fn rewind_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    let core_ref = main_window.borrow_core().await;
+    let core = core_ref.borrow_running().expect("Core should be running");
+    if let Some(target) = core.vcr_previous_frame().await {
+        core.seek(SeekTarget::Index(target)).await?;
+    }
+    Ok(())
+}
+
+async fn seek_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    let frame_size = {
+        let core_ref = main_window.borrow_core().await;
+        let core = core_ref.borrow_running().expect("Core should be running");
+        match core.vcr_frame_size().await {
+            Some(frame_size) => frame_size,
+            None => return Ok(()),
+        }
+    };
+
+    let dialog = SeekDialog::new();
+    let Some(target) = dialog.prompt(Some(main_window), frame_size).await else {
+        return Ok(());
+    };
+
+    {
+        let core_ref = main_window.borrow_core().await;
+        let core = core_ref.borrow_running().expect("Core should be running");
+        if let Err(err) = core.seek(target).await {
+            dialog.close();
+            return Err(err);
+        }
+    }
+
+    // The frame handler pauses the emulator once the target is reached; this only
+    // tracks progress, and stops the seek if the dialog is closed.
+    loop {
+        glib::timeout_future(Duration::from_millis(50)).await;
+        let core_ref = main_window.borrow_core().await;
+        let Some(core) = core_ref.borrow_running() else {
+            break;
+        };
+        if dialog.cancelled() {
+            core.cancel_seek().await?;
+            break;
+        }
+        match core.seek_progress().await {
+            Some((position, target)) => dialog.set_progress(position, target),
+            None => break,
+        }
+    }
+    dialog.close();
+    Ok(())
+}
+
+async fn branches_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    let dialog = BranchDialog::new();
+    loop {
+        {
+            let core_ref = main_window.borrow_core().await;
+            let Some(core) = core_ref.borrow_running() else {
+                break;
+            };
+            let (Some(branches), Some(frame_size)) =
+                (core.vcr_branches().await, core.vcr_frame_size().await)
+            else {
+                break;
+            };
+            dialog.set_branches(&branches, frame_size);
+        }
+
+        let Some(command) = dialog.next_command(Some(main_window)).await else {
+            return Ok(());
+        };
+
+        let result = {
+            let core_ref = main_window.borrow_core().await;
+            let Some(core) = core_ref.borrow_running() else {
+                break;
+            };
+            match command {
+                BranchCommand::Create(name) => core.create_branch(name).await,
+                BranchCommand::Load(index) => core.load_branch(index).await,
+                BranchCommand::Promote(index) => core.promote_branch(index).await,
+                BranchCommand::Remove(index) => {
+                    core.remove_branch(index).await;
+                    Ok(())
+                }
+                BranchCommand::Compare(index) => {
+                    let text = match core.compare_branch(index).await {
+                        Some(cmp) => match cmp.first_difference {
+                            None => tr!("The branch matches the current movie."),
+                            Some(first) => tr!(
+                                "The branch first differs at input {}. {} inputs differ; the branch has {} inputs and the movie has {}.",
+                                first,
+                                cmp.changed_samples,
+                                cmp.branch_length,
+                                cmp.other_length
+                            ),
+                        },
+                        None => String::new(),
+                    };
+                    dialog.set_comparison(&text);
+                    Ok(())
+                }
+            }
+        };
+        if let Err(err) = result {
+            main_window
+                .show_error_dialog(&tr!("Branch operation failed"), &err)
+                .await;
+        }
+    }
+    // The movie was closed while the dialog was open.
+    dialog.close();
+    Ok(())
+}
+
+async fn input_editor_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    let editor = InputEditor::new();
+    editor.set_transient_for(Some(main_window));
+    let mut edits = editor
+        .take_edits()
+        .expect("edits should only be taken once");
+    editor.present();
+
+    // Refresh the editor periodically, applying its edits to the movie as they come in.
+    // The stream of edits ends when the editor is closed.
+    let mut known_revision = None;
+    loop {
+        {
+            let core_ref = main_window.borrow_core().await;
+            let Some(core) = core_ref.borrow_running() else {
+                break;
+            };
+            let Some(snapshot) = core.vcr_inputs(known_revision).await else {
+                break;
+            };
+            known_revision = Some(snapshot.revision);
+            editor.update(snapshot);
+        }
+
+        let tick = glib::timeout_future(Duration::from_millis(100));
+        match future::select(edits.next(), tick).await {
+            Either::Left((Some(edit), _)) => {
+                let core_ref = main_window.borrow_core().await;
+                let Some(core) = core_ref.borrow_running() else {
+                    break;
+                };
+                core.splice_vcr_inputs(edit.start, edit.len, &edit.samples)
+                    .await;
+            }
+            Either::Left((None, _)) => return Ok(()),
+            Either::Right(_) => (),
+        }
+    }
+    // The movie was closed while the editor was open.
+    editor.close();
+    Ok(())
+}
+
+async fn ram_watch_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    if let Some(window) = find_window::<RamWatch>() {
+        window.present();
+        return Ok(());
+    }
+    let rom_crc = {
+        let core_ref = main_window.borrow_core().await;
+        let Some(core) = core_ref.borrow_running() else {
+            return Ok(());
+        };
+        core.rom_header().CRC1
+    };
+    let watches = gio::spawn_blocking(move || watch::load_watches(rom_crc))
+        .await
+        .unwrap()?;
+
+    let window = RamWatch::new();
+    window.set_transient_for(Some(main_window));
+    window.set_watches(watches.clone());
+    let mut changes = window
+        .take_changes()
+        .expect("changes should only be taken once");
+    window.present();
+
+    let mut values = {
+        let core_ref = main_window.borrow_core().await;
+        let Some(core) = core_ref.borrow_running() else {
+            window.close();
+            return Ok(());
+        };
+        core.watch_memory(watches).await
+    };
+
+    // Show values as the frame handler sends them, and save the watches whenever they change.
+    // The stream of values ends when the ROM is closed.
+    loop {
+        match future::select(changes.next(), values.next()).await {
+            Either::Left((Some(watches), _)) => {
+                {
+                    let core_ref = main_window.borrow_core().await;
+                    if let Some(core) = core_ref.borrow_running() {
+                        core.set_watches(watches.clone()).await;
+                    }
+                }
+                let result = gio::spawn_blocking(move || watch::save_watches(rom_crc, &watches))
+                    .await
+                    .unwrap();
+                if let Err(err) = result {
+                    log::warn!("failed to save watches: {}", err);
+                }
+            }
+            Either::Left((None, _)) => return Ok(()),
+            Either::Right((Some(mut latest), _)) => {
+                // Only the latest values are worth showing.
+                while let Ok(Some(next)) = values.try_next() {
+                    latest = next;
+                }
+                window.update_values(&latest);
+            }
+            Either::Right((None, _)) => break,
+        }
+    }
+    // The ROM was closed while the window was open.
+    window.close();
+    Ok(())
+}
+
+async fn ram_search_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    /// Maximum number of candidates shown.
+    const RESULT_LIMIT: usize = 1000;
+
+    let window = RamSearch::new();
+    window.set_transient_for(Some(main_window));
+    let mut commands = window
+        .take_commands()
+        .expect("commands should only be taken once");
+    window.present();
+
+    // The stream of commands ends when the window is closed.
+    let mut search: Option<Search> = None;
+    while let Some(command) = commands.next().await {
+        let (value_type, filter) = match command {
+            SearchCommand::Reset(value_type) => (value_type, None),
+            SearchCommand::Filter(comparison, target) => match &search {
+                Some(search) => (search.value_type(), Some((comparison, target))),
+                None => continue,
+            },
+            SearchCommand::Watch(watches) => {
+                add_watches(main_window, watches).await?;
+                continue;
+            }
+        };
+
+        let snapshot = {
+            let core_ref = main_window.borrow_core().await;
+            let Some(core) = core_ref.borrow_running() else {
+                break;
+            };
+            core.snapshot_rdram().await
+        };
+        let Some(snapshot) = snapshot else {
+            break;
+        };
+
+        let updated = match (search.take(), filter) {
+            (Some(mut current), Some((comparison, target))) => gio::spawn_blocking(move || {
+                current.filter(snapshot, comparison, target);
+                current
+            })
+            .await
+            .unwrap(),
+            _ => Search::new(value_type, snapshot),
+        };
+        window.show_results(
+            updated.value_type(),
+            updated.candidate_count(),
+            updated.results(RESULT_LIMIT),
+        );
+        search = Some(updated);
+    }
+    // The ROM was closed while the window was open.
+    window.close();
+    Ok(())
+}
+
+async fn cheats_impl(main_window: &MainWindow) -> Result<(), Box<dyn Error>> {
+    if let Some(window) = find_window::<CheatManager>() {
+        window.present();
+        return Ok(());
+    }
+    let (rom_crc, rom_key) = {
+        let core_ref = main_window.borrow_core().await;
+        let Some(core) = core_ref.borrow_running() else {
+            return Ok(());
+        };
+        let rom_header = core.rom_header();
+        (rom_header.CRC1, database::rom_key(&rom_header))
+    };
+    let (database_cheats, mut custom_cheats) = gio::spawn_blocking(move || -> io::Result<_> {
+        Ok((
+            database::load_database(&rom_key)?,
+            database::load_custom_cheats(rom_crc)?,
+        ))
+    })
+    .await
+    .unwrap()?;
+
+    let entries = {
+        let core_ref = main_window.borrow_core().await;
+        let Some(core) = core_ref.borrow_running() else {
+            return Ok(());
+        };
+        let entry = |cheat: &Cheat, custom| CheatEntry {
+            cheat: cheat.clone(),
+            custom,
+            enabled: core.cheat_enabled(&cheat.name),
+        };
+        database_cheats
+            .iter()
+            .map(|cheat| entry(cheat, false))
+            .chain(custom_cheats.iter().map(|cheat| entry(cheat, true)))
+            .collect()
+    };
+
+    let window = CheatManager::new();
+    window.set_transient_for(Some(main_window));
+    window.set_entries(entries);
+    let mut commands = window
+        .take_commands()
+        .expect("commands should only be taken once");
+    window.present();
+
+    // The stream of commands ends when the window is closed.
+    while let Some(command) = commands.next().await {
+        let save = {
+            let core_ref = main_window.borrow_core().await;
+            let Some(core) = core_ref.borrow_running() else {
+                break;
+            };
+            let apply = |cheat: &Cheat, enabled| {
+                CheatCode::parse_list(&cheat.codes)
+                    .and_then(|codes| core.set_cheat(&cheat.name, &codes, enabled))
+            };
+            match command {
+                CheatCommand::SetEnabled(cheat, enabled) => {
+                    if let Err(err) = apply(&cheat, enabled) {
+                        window.show_enabled(&cheat.name, core.cheat_enabled(&cheat.name));
+                        window.show_error(
+                            &tr!("cheat_manager" => "Failed to apply cheat"),
+                            &err.to_string(),
+                        );
+                    }
+                    false
+                }
+                CheatCommand::Save(cheat) => {
+                    // Replace the codes of an enabled cheat straight away.
+                    if core.cheat_enabled(&cheat.name) {
+                        if let Err(err) = apply(&cheat, true) {
+                            window.show_error(
+                                &tr!("cheat_manager" => "Failed to apply cheat"),
+                                &err.to_string(),
+                            );
+                        }
+                    }
+                    match custom_cheats
+                        .iter_mut()
+                        .find(|other| other.name == cheat.name)
+                    {
+                        Some(other) => *other = cheat,
+                        None => custom_cheats.push(cheat),
+                    }
+                    true
+                }
+                CheatCommand::Remove(name) => {
+                    if let Err(err) = core.remove_cheat(&name) {
+                        log::warn!("failed to remove cheat: {}", err);
+                    }
+                    custom_cheats.retain(|other| other.name != name);
+                    true
+                }
+            }
+        };
+        if save {
+            let cheats = custom_cheats.clone();
+            let result =
+                gio::spawn_blocking(move || database::save_custom_cheats(rom_crc, &cheats))
+                    .await
+                    .unwrap();
+            if let Err(err) = result {
+                log::warn!("failed to save cheats: {}", err);
+            }
+        }
+    }
+    // The ROM was closed while the window was open.
+    window.close();
+    Ok(())
+}
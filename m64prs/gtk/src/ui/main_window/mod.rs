@@ -6,7 +6,7 @@ pub mod enums;
 mod menu;
 
 mod inner {
-    use std::{cell::Cell, error::Error, path::PathBuf};
+    use std::{cell::Cell, error::Error, path::PathBuf, time::Duration};
 
     use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
     use glib::{
@@ -20,7 +20,8 @@ mod inner {
     use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
     use m64prs_gtk_utils::actions::TypedActionGroup as _;
     use m64prs_sys::EmuState;
-    use m64prs_vcr::movie::M64Header;
+    use m64prs_vcr::{checksum::Desync, movie::M64Header};
+    use tr::tr;
 
     use crate::{
         controls::{
@@ -28,7 +29,7 @@ mod inner {
             compositor_view::native::{NativeView, NativeViewAttributes, NativeViewKey},
         },
         ui::{
-            core::{CoreReadyState, CoreState},
+            core::{CoreReadyState, CoreState, VcrStats},
             movie_dialog::MovieDialog,
         },
     };
@@ -59,6 +60,8 @@ mod inner {
         new_movie_dialog: TemplateChild<MovieDialog>,
         #[template_child]
         load_movie_dialog: TemplateChild<MovieDialog>,
+        #[template_child]
+        vcr_status: TemplateChild<gtk::Label>,
 
         // properties
         #[property(get, construct_only, builder(MainViewState::RomBrowser))]
@@ -89,6 +92,18 @@ mod inner {
         pub(super) fn set_emu_state(&self, emu_state: EmuState) {
             self.emu_state.set(emu_state.into());
             self.obj().notify_emu_state();
+
+            if emu_state == EmuState::Paused {
+                // Keep going if we're advancing to the next input poll.
+                let this = self.obj().clone();
+                glib::spawn_future_local(async move {
+                    if let Some(running) = this.borrow_core().await.borrow_running() {
+                        if let Err(err) = running.continue_advance_to_poll() {
+                            log::warn!("failed to advance to next input poll: {}", err);
+                        }
+                    }
+                });
+            }
         }
 
         pub(super) fn set_saving_state(&self, saving_state: bool) {
@@ -111,6 +126,93 @@ mod inner {
             self.obj().notify_vcr_read_only();
         }
 
+        pub(super) fn capture_greenzone(&self) {
+            let this = self.obj().clone();
+            glib::spawn_future_local(async move {
+                if let Some(running) = this.borrow_core().await.borrow_running() {
+                    running.capture_greenzone().await;
+                }
+            });
+        }
+
+        /// Switches the frontend to read-write mode after a movie continued recording
+        /// at its end.
+        pub(super) fn continue_recording(&self) {
+            let this = self.obj().clone();
+            glib::spawn_future_local(async move {
+                if let Some(running) = this.borrow_core().await.borrow_running() {
+                    running.set_read_only(false);
+                }
+            });
+        }
+
+        /// Closes the movie after playback reached its end, putting back the user's save
+        /// memory like closing it from the menu.
+        pub(super) fn close_movie(&self) {
+            let this = self.obj().clone();
+            glib::spawn_future_local(async move {
+                if let Err(err) = this.activate_action("app.vcr.close_movie", None) {
+                    log::error!("failed to close the movie: {}", err);
+                }
+            });
+        }
+
+        pub(super) fn finish_seek(&self) {
+            let this = self.obj().clone();
+            glib::spawn_future_local(async move {
+                if let Some(running) = this.borrow_core().await.borrow_running() {
+                    running.finish_seek().await;
+                }
+            });
+        }
+
+        pub(super) fn report_desync(&self, desync: Desync) {
+            let this = self.obj().clone();
+            glib::spawn_future_local(async move {
+                this.show_message_dialog(
+                    &tr!("The movie has desynced."),
+                    &tr!(
+                        "Emulated memory first differed from the recording at VI {} (input {}).",
+                        desync.vi,
+                        desync.index
+                    ),
+                )
+                .await;
+            });
+        }
+
+        pub(super) fn report_modified_memory(&self) {
+            let this = self.obj().clone();
+            glib::spawn_future_local(async move {
+                this.show_message_dialog(
+                    &tr!("Memory was modified during recording."),
+                    &tr!("The movie is now marked as recorded with frozen values or cheats, and can't be verified."),
+                )
+                .await;
+            });
+        }
+
+        pub(super) fn set_vcr_stats(&self, stats: Option<VcrStats>) {
+            let Some(stats) = stats else {
+                self.vcr_status.set_label("");
+                return;
+            };
+            let elapsed = stats.session.elapsed.as_secs();
+            self.vcr_status.set_label(&tr!(
+                "Input {}/{} | VI {} | Lag {} (+{} this session) | Rerecords {} (+{} this session) | Session {}:{:02}:{:02}",
+                stats.index,
+                stats.length_samples,
+                stats.vi_count,
+                stats.lag_count,
+                stats.session.lag_frames,
+                stats.rerecord_count,
+                stats.session.rerecords,
+                elapsed / 3600,
+                (elapsed / 60) % 60,
+                elapsed % 60
+            ));
+        }
+
         pub(super) fn set_current_view(&self, main_view: MainViewState) {
             self.current_view.set(main_view);
             {
@@ -146,6 +248,30 @@ mod inner {
             let _ = self.error_dialog.choose_future(Some(&*self.obj())).await;
         }
 
+        pub(super) async fn show_message_dialog(&self, header: &str, detail: &str) {
+            self.error_dialog.set_message(header);
+            self.error_dialog.set_detail(detail);
+            let _ = self.error_dialog.choose_future(Some(&*self.obj())).await;
+        }
+
+        pub(super) async fn show_confirm_dialog(
+            &self,
+            message: &str,
+            detail: &str,
+            accept_label: &str,
+            reject_label: &str,
+        ) -> bool {
+            let dialog = gtk::AlertDialog::builder()
+                .modal(true)
+                .message(message)
+                .detail(detail)
+                .buttons([accept_label, reject_label])
+                .default_button(0)
+                .cancel_button(1)
+                .build();
+            matches!(dialog.choose_future(Some(&*self.obj())).await, Ok(0))
+        }
+
         pub(super) async fn show_save_state_dialog(&self) -> Result<gio::File, glib::Error> {
             self.save_state_dialog.save_future(Some(&*self.obj())).await
         }
@@ -233,6 +359,27 @@ mod inner {
                     *this.borrow_core_mut().await = ready_state.into();
                 });
             }
+
+            {
+                // Periodically refresh the movie status line.
+                const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+                let this_weak = self.obj().downgrade();
+                glib::timeout_add_local(STATUS_INTERVAL, move || {
+                    let Some(this) = this_weak.upgrade() else {
+                        return glib::ControlFlow::Break;
+                    };
+                    if this.vcr_active() {
+                        glib::spawn_future_local(async move {
+                            let stats = match this.borrow_core().await.borrow_running() {
+                                Some(running) => running.vcr_stats().await,
+                                None => None,
+                            };
+                            this.set_vcr_stats(stats);
+                        });
+                    }
+                    glib::ControlFlow::Continue
+                });
+            }
         }
 
         fn dispose(&self) {
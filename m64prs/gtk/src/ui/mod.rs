@@ -1,13 +1,25 @@
 use gio::ApplicationFlags;
 use gtk::prelude::*;
 
+mod branch_dialog;
+mod cheat_manager;
 mod core;
+mod input_editor;
 mod main_window;
 mod movie_dialog;
+mod ram_search;
+mod ram_watch;
+mod seek_dialog;
 mod settings_dialog;
 
+use branch_dialog::BranchDialog;
+use cheat_manager::CheatManager;
+use input_editor::InputEditor;
 use main_window::MainWindow;
 use movie_dialog::MovieDialog;
+use ram_search::RamSearch;
+use ram_watch::RamWatch;
+use seek_dialog::SeekDialog;
 use settings_dialog::SettingsDialog;
 
 const APP_ID: &str = "io.github.jgcodes.m64prs";
@@ -19,7 +31,13 @@ struct AppDialogError(String);
 pub fn run_ui() {
     // this catches some template errors early
     MainWindow::ensure_type();
+    BranchDialog::ensure_type();
+    CheatManager::ensure_type();
+    InputEditor::ensure_type();
     MovieDialog::ensure_type();
+    RamSearch::ensure_type();
+    RamWatch::ensure_type();
+    SeekDialog::ensure_type();
     SettingsDialog::ensure_type();
 
     let app = gtk::Application::new(Some(APP_ID), ApplicationFlags::FLAGS_NONE);
@@ -34,6 +34,7 @@ mod inner {
             header.start_flags = window.start_type().into();
             header.author.write_clipped(&window.author());
             header.description.write_clipped(&window.description());
+            header.set_extended(window.extended_info());
 
             println!("start flags: {:?}", header.start_flags);
 
@@ -10,7 +10,10 @@ mod inner {
     use futures::channel::oneshot;
     use glib::{subclass::InitializingObject, translate::IntoGlib};
     use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
-    use m64prs_vcr::movie::M64Header;
+    use m64prs_vcr::{
+        bundle::{BundleError, MovieBundle},
+        movie::{ExtendedFlags, ExtendedInfo, M64Header},
+    };
 
     use crate::{
         controls::SizedTextBuffer,
@@ -32,6 +35,12 @@ mod inner {
         #[template_child]
         eeprom_btn: TemplateChild<gtk::ToggleButton>,
         #[template_child]
+        extended_check: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        wii_vc_check: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        authorship_field: TemplateChild<gtk::Entry>,
+        #[template_child]
         file_dialog: TemplateChild<gtk::FileDialog>,
         #[template_child]
         error_dialog: TemplateChild<gtk::AlertDialog>,
@@ -89,6 +98,30 @@ mod inner {
                 .text(&buffer.start_iter(), &buffer.end_iter(), true)
                 .to_string()
         }
+
+        pub(super) fn extended_info(&self) -> Option<ExtendedInfo> {
+            if !self.extended_check.is_active() {
+                return None;
+            }
+
+            let mut flags = ExtendedFlags::empty();
+            flags.set(ExtendedFlags::WII_VC, self.wii_vc_check.is_active());
+
+            // The tag is padded with zeroes, and non-ASCII text is truncated.
+            let mut authorship_tag = [0u8; 4];
+            self.authorship_field
+                .text()
+                .bytes()
+                .take_while(u8::is_ascii)
+                .zip(&mut authorship_tag)
+                .for_each(|(src, dst)| *dst = src);
+
+            Some(ExtendedInfo {
+                flags,
+                authorship_tag,
+                ..Default::default()
+            })
+        }
     }
 
     #[gtk::template_callbacks]
@@ -160,7 +193,15 @@ mod inner {
                 );
             }
             if self.load.get() {
-                let header = {
+                let path = file.path().unwrap();
+                let header = if MovieBundle::is_bundle_path(&path) {
+                    gio::spawn_blocking(move || -> Result<M64Header, BundleError> {
+                        let file = std::fs::File::open(&path)?;
+                        MovieBundle::read_header(std::io::BufReader::new(file))
+                    })
+                    .await
+                    .unwrap()?
+                } else {
                     let mut file_reader = file
                         .read_future(glib::Priority::DEFAULT)
                         .await?
@@ -175,6 +216,19 @@ mod inner {
                 if let Some(start_type) = MovieStartType::try_from(header.start_flags).ok() {
                     self.obj().set_start_type(start_type);
                 }
+                let extended = header.extended();
+                self.extended_check.set_active(extended.is_some());
+                self.wii_vc_check.set_active(
+                    extended.is_some_and(|ext| ext.flags.contains(ExtendedFlags::WII_VC)),
+                );
+                let authorship_tag: String = extended.map_or_else(String::new, |ext| {
+                    ext.authorship_tag
+                        .iter()
+                        .take_while(|&&c| c != 0)
+                        .map(|&c| c as char)
+                        .collect()
+                });
+                self.authorship_field.set_text(&authorship_tag);
             }
             self.obj().set_cur_file(Some(file));
             Ok(())
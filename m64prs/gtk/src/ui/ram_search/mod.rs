@@ -0,0 +1,259 @@
+use search::{Comparison, Target};
+
+use super::ram_watch::watch::{ValueType, Watch};
+
+mod row;
+pub mod search;
+
+mod inner {
+    use std::cell::{Cell, RefCell};
+
+    use futures::channel::mpsc;
+    use glib::subclass::InitializingObject;
+    use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
+    use tr::tr;
+
+    use crate::ui::ram_watch::watch::{DisplayFormat, ValueType, Watch};
+
+    use super::{
+        row::ResultRow,
+        search::{Comparison, SearchResult, Target},
+        SearchCommand,
+    };
+
+    #[derive(gtk::CompositeTemplate)]
+    #[template(file = "mod.ui")]
+    pub struct RamSearch {
+        #[template_child]
+        result_view: TemplateChild<gtk::ColumnView>,
+        #[template_child]
+        type_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        comparison_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        target_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        value_field: TemplateChild<gtk::Entry>,
+        #[template_child]
+        count_label: TemplateChild<gtk::Label>,
+
+        rows: gio::ListStore,
+        selection: gtk::MultiSelection,
+
+        /// Type of the search in progress, or `None` if no search was started.
+        value_type: Cell<Option<ValueType>>,
+
+        command_tx: RefCell<Option<mpsc::UnboundedSender<SearchCommand>>>,
+        command_rx: RefCell<Option<mpsc::UnboundedReceiver<SearchCommand>>>,
+    }
+
+    #[m64prs_gtk_utils::forward_wrapper(super::RamSearch, vis = pub(in super::super))]
+    impl RamSearch {
+        /// Takes the stream of commands from the window. The stream ends when the window
+        /// is closed.
+        pub(super) fn take_commands(&self) -> Option<mpsc::UnboundedReceiver<SearchCommand>> {
+            self.command_rx.take()
+        }
+
+        /// Shows the candidates of the search in progress.
+        pub(super) fn show_results(
+            &self,
+            value_type: ValueType,
+            count: usize,
+            results: Vec<SearchResult>,
+        ) {
+            self.value_type.set(Some(value_type));
+            let rows: Vec<ResultRow> = results
+                .into_iter()
+                .map(|result| {
+                    let value = value_type.format_raw(result.value, DisplayFormat::Decimal);
+                    ResultRow::new(result.address, value)
+                })
+                .collect();
+            self.count_label.set_text(&match count > rows.len() {
+                true => {
+                    tr!("ram_search" => "{} candidates, showing the first {}", count, rows.len())
+                }
+                false => tr!("ram_search" => "{} candidates", count),
+            });
+            self.rows.splice(0, self.rows.n_items(), &rows);
+        }
+    }
+
+    impl RamSearch {
+        fn send(&self, command: SearchCommand) {
+            if let Some(tx) = &*self.command_tx.borrow() {
+                let _ = tx.unbounded_send(command);
+            }
+        }
+
+        fn add_column(&self, title: &str, text: impl Fn(&ResultRow) -> String + 'static) {
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(|_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let label = gtk::Label::new(None);
+                label.set_xalign(0.0);
+                item.set_child(Some(&label));
+            });
+            factory.connect_bind(move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let (Some(row), Some(label)) = (
+                    item.item().and_downcast::<ResultRow>(),
+                    item.child().and_downcast::<gtk::Label>(),
+                ) else {
+                    return;
+                };
+                label.set_text(&text(&row));
+            });
+
+            let column = gtk::ColumnViewColumn::new(Some(title), Some(factory));
+            column.set_resizable(true);
+            self.result_view.append_column(&column);
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl RamSearch {
+        #[template_callback]
+        fn new_search_clicked(&self, _: &gtk::Button) {
+            let value_type = ValueType::ALL[self.type_dropdown.selected() as usize];
+            self.send(SearchCommand::Reset(value_type));
+        }
+
+        #[template_callback]
+        fn filter_clicked(&self, _: &gtk::Button) {
+            let Some(value_type) = self.value_type.get() else {
+                self.obj().error_bell();
+                return;
+            };
+            let comparison = Comparison::ALL[self.comparison_dropdown.selected() as usize];
+            let target = match self.target_dropdown.selected() {
+                0 => Target::Previous,
+                _ => match value_type.parse_raw(&self.value_field.text()) {
+                    Some(value) => Target::Value(value),
+                    None => {
+                        self.obj().error_bell();
+                        return;
+                    }
+                },
+            };
+            self.send(SearchCommand::Filter(comparison, target));
+        }
+
+        #[template_callback]
+        fn watch_clicked(&self, _: &gtk::Button) {
+            let Some(value_type) = self.value_type.get() else {
+                return;
+            };
+            let selection = self.selection.selection();
+            let watches: Vec<Watch> = (0..self.rows.n_items())
+                .filter(|&index| selection.contains(index))
+                .filter_map(|index| self.rows.item(index).and_downcast::<ResultRow>())
+                .map(|row| Watch {
+                    label: String::new(),
+                    address: row.address(),
+                    offsets: Vec::new(),
+                    value_type,
+                    format: DisplayFormat::Decimal,
+                    frozen: None,
+                })
+                .collect();
+            if !watches.is_empty() {
+                self.send(SearchCommand::Watch(watches));
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RamSearch {
+        const NAME: &'static str = "M64PRS_RamSearch";
+        type Type = super::RamSearch;
+        type ParentType = gtk::Window;
+
+        fn new() -> Self {
+            let rows = gio::ListStore::new::<ResultRow>();
+            let selection = gtk::MultiSelection::new(Some(rows.clone()));
+            let (command_tx, command_rx) = mpsc::unbounded();
+
+            Self {
+                result_view: Default::default(),
+                type_dropdown: Default::default(),
+                comparison_dropdown: Default::default(),
+                target_dropdown: Default::default(),
+                value_field: Default::default(),
+                count_label: Default::default(),
+                rows,
+                selection,
+                value_type: Cell::new(None),
+                command_tx: RefCell::new(Some(command_tx)),
+                command_rx: RefCell::new(Some(command_rx)),
+            }
+        }
+
+        fn class_init(class: &mut Self::Class) {
+            ResultRow::ensure_type();
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for RamSearch {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.result_view.set_model(Some(&self.selection));
+
+            self.add_column(&tr!("ram_search" => "Address"), |row| {
+                format!("{:08X}", row.address())
+            });
+            self.add_column(&tr!("ram_search" => "Value"), |row| row.value());
+        }
+
+        fn dispose(&self) {
+            self.dispose_template();
+        }
+    }
+    impl WidgetImpl for RamSearch {}
+    impl WindowImpl for RamSearch {
+        fn close_request(&self) -> glib::Propagation {
+            // Ends the stream of commands.
+            self.command_tx.take();
+            glib::Propagation::Proceed
+        }
+    }
+}
+
+/// A request from the [`RamSearch`] window.
+#[derive(Debug, Clone)]
+pub enum SearchCommand {
+    /// Start a new search for values of the given type.
+    Reset(ValueType),
+    /// Narrow down the candidates using a new snapshot.
+    Filter(Comparison, Target),
+    /// Add watches for the selected candidates to the RAM watch.
+    Watch(Vec<Watch>),
+}
+
+glib::wrapper! {
+    /// Window for finding memory addresses by how their values change.
+    pub struct RamSearch(ObjectSubclass<inner::RamSearch>)
+    @extends
+        gtk::Window,
+        gtk::Widget,
+    @implements
+        gtk::Accessible,
+        gtk::Buildable,
+        gtk::ConstraintTarget,
+        gtk::Native,
+        gtk::Root,
+        gtk::ShortcutManager;
+}
+
+impl RamSearch {
+    pub(super) fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,37 @@
+mod inner {
+    use std::cell::{Cell, RefCell};
+
+    use gtk::{prelude::*, subclass::prelude::*};
+
+    /// One candidate, as shown in the RAM search window.
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::ResultRow)]
+    pub struct ResultRow {
+        #[property(get, construct_only)]
+        address: Cell<u32>,
+        #[property(get, construct_only)]
+        value: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ResultRow {
+        const NAME: &'static str = "M64PRS_ResultRow";
+        type Type = super::ResultRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for ResultRow {}
+}
+
+glib::wrapper! {
+    pub struct ResultRow(ObjectSubclass<inner::ResultRow>);
+}
+
+impl ResultRow {
+    pub fn new(address: u32, value: String) -> Self {
+        glib::Object::builder()
+            .property("address", address)
+            .property("value", value)
+            .build()
+    }
+}
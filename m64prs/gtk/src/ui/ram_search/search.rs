@@ -0,0 +1,166 @@
+//! Narrowing down RDRAM addresses by comparing snapshots.
+
+use std::cmp::Ordering;
+
+use crate::ui::ram_watch::watch::{self, ValueType};
+
+/// How a candidate's value is compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+}
+
+/// What a candidate's value is compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// The value at the previous snapshot. Comparing with [`Comparison::NotEqual`] finds
+    /// changed values, and [`Comparison::Equal`] unchanged ones.
+    Previous,
+    /// A specific value, as bits from [`ValueType::parse_raw`].
+    Value(u64),
+}
+
+/// A remaining candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    pub address: u32,
+    /// Bits of the value at the latest snapshot.
+    pub value: u64,
+}
+
+/// A RAM search in progress.
+#[derive(Debug)]
+pub struct Search {
+    value_type: ValueType,
+    /// RDRAM at the latest snapshot, in the N64's byte order.
+    snapshot: Box<[u8]>,
+    /// Offsets of the remaining candidates, or `None` if every aligned offset is a candidate.
+    candidates: Option<Vec<u32>>,
+}
+
+impl Comparison {
+    pub const ALL: [Comparison; 6] = [
+        Comparison::Equal,
+        Comparison::NotEqual,
+        Comparison::Less,
+        Comparison::Greater,
+        Comparison::LessEqual,
+        Comparison::GreaterEqual,
+    ];
+
+    /// Checks the result of comparing a value to the target. `None` means the values are
+    /// unordered (e.g. NaN), which only counts as not equal.
+    fn matches(self, ordering: Option<Ordering>) -> bool {
+        match ordering {
+            Some(ordering) => match self {
+                Comparison::Equal => ordering.is_eq(),
+                Comparison::NotEqual => ordering.is_ne(),
+                Comparison::Less => ordering.is_lt(),
+                Comparison::Greater => ordering.is_gt(),
+                Comparison::LessEqual => ordering.is_le(),
+                Comparison::GreaterEqual => ordering.is_ge(),
+            },
+            None => self == Comparison::NotEqual,
+        }
+    }
+}
+
+impl Search {
+    /// Starts a search where every aligned address is a candidate.
+    pub fn new(value_type: ValueType, snapshot: Box<[u8]>) -> Self {
+        Self {
+            value_type,
+            snapshot,
+            candidates: None,
+        }
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// Returns the number of remaining candidates.
+    pub fn candidate_count(&self) -> usize {
+        match &self.candidates {
+            Some(candidates) => candidates.len(),
+            None => self.snapshot.len() / self.value_type.size() as usize,
+        }
+    }
+
+    /// Keeps the candidates whose value in `snapshot` compares to the target as requested,
+    /// then makes `snapshot` the latest snapshot.
+    pub fn filter(&mut self, snapshot: Box<[u8]>, comparison: Comparison, target: Target) {
+        let size = self.value_type.size() as usize;
+        let end = self
+            .snapshot
+            .len()
+            .min(snapshot.len())
+            .saturating_sub(size - 1);
+        let keep = |offset: &u32| {
+            let offset = *offset as usize;
+            if offset >= end {
+                return false;
+            }
+            let value = read_raw(&snapshot, offset, size);
+            let target = match target {
+                Target::Previous => read_raw(&self.snapshot, offset, size),
+                Target::Value(value) => value,
+            };
+            comparison.matches(compare(self.value_type, value, target))
+        };
+
+        let candidates = match self.candidates.take() {
+            Some(mut candidates) => {
+                candidates.retain(keep);
+                candidates
+            }
+            None => (0..end as u32).step_by(size).filter(keep).collect(),
+        };
+        self.candidates = Some(candidates);
+        self.snapshot = snapshot;
+    }
+
+    /// Returns up to `limit` of the remaining candidates.
+    pub fn results(&self, limit: usize) -> Vec<SearchResult> {
+        let size = self.value_type.size() as usize;
+        let result = |offset: u32| SearchResult {
+            address: watch::to_virtual(offset),
+            value: read_raw(&self.snapshot, offset as usize, size),
+        };
+        match &self.candidates {
+            Some(candidates) => candidates
+                .iter()
+                .take(limit)
+                .map(|&offset| result(offset))
+                .collect(),
+            None => (0..self.snapshot.len().saturating_sub(size - 1) as u32)
+                .step_by(size)
+                .take(limit)
+                .map(result)
+                .collect(),
+        }
+    }
+}
+
+/// Reads a big-endian value from a snapshot.
+fn read_raw(snapshot: &[u8], offset: usize, size: usize) -> u64 {
+    snapshot[offset..(offset + size)]
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | byte as u64)
+}
+
+/// Compares two values of the given type.
+fn compare(value_type: ValueType, a: u64, b: u64) -> Option<Ordering> {
+    match value_type {
+        ValueType::U8 | ValueType::U16 | ValueType::U32 => Some(a.cmp(&b)),
+        ValueType::S8 => Some((a as u8 as i8).cmp(&(b as u8 as i8))),
+        ValueType::S16 => Some((a as u16 as i16).cmp(&(b as u16 as i16))),
+        ValueType::S32 => Some((a as u32 as i32).cmp(&(b as u32 as i32))),
+        ValueType::F32 => f32::from_bits(a as u32).partial_cmp(&f32::from_bits(b as u32)),
+    }
+}
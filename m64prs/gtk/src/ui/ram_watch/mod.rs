@@ -0,0 +1,360 @@
+mod row;
+pub mod watch;
+
+mod inner {
+    use std::cell::RefCell;
+
+    use futures::channel::mpsc;
+    use glib::subclass::InitializingObject;
+    use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
+    use tr::tr;
+
+    use super::{
+        row::WatchRow,
+        watch::{self, DisplayFormat, ValueType, Watch},
+    };
+
+    #[derive(gtk::CompositeTemplate)]
+    #[template(file = "mod.ui")]
+    pub struct RamWatch {
+        #[template_child]
+        watch_view: TemplateChild<gtk::ColumnView>,
+        #[template_child]
+        address_field: TemplateChild<gtk::Entry>,
+        #[template_child]
+        label_field: TemplateChild<gtk::Entry>,
+        #[template_child]
+        type_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        format_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        freeze_field: TemplateChild<gtk::Entry>,
+        #[template_child]
+        import_dialog: TemplateChild<gtk::FileDialog>,
+
+        rows: gio::ListStore,
+        selection: gtk::MultiSelection,
+
+        /// The watches shown, kept in sync with the rows.
+        watches: RefCell<Vec<Watch>>,
+        /// The latest values of the watches.
+        values: RefCell<Vec<Option<u64>>>,
+
+        change_tx: RefCell<Option<mpsc::UnboundedSender<Vec<Watch>>>>,
+        change_rx: RefCell<Option<mpsc::UnboundedReceiver<Vec<Watch>>>>,
+    }
+
+    #[m64prs_gtk_utils::forward_wrapper(super::RamWatch, vis = pub(in super::super))]
+    impl RamWatch {
+        /// Takes the stream of changes to the watch list. The stream ends when the window
+        /// is closed.
+        pub(super) fn take_changes(&self) -> Option<mpsc::UnboundedReceiver<Vec<Watch>>> {
+            self.change_rx.take()
+        }
+
+        /// Replaces the watches shown, without reporting a change.
+        pub(super) fn set_watches(&self, watches: Vec<Watch>) {
+            let rows: Vec<WatchRow> = watches.iter().map(WatchRow::new).collect();
+            *self.watches.borrow_mut() = watches;
+            self.rows.splice(0, self.rows.n_items(), &rows);
+        }
+
+        /// Adds watches to the end of the list, and reports the change.
+        pub(super) fn add_watches(&self, watches: Vec<Watch>) {
+            self.apply(|current| current.extend(watches));
+        }
+
+        /// Shows the watches' latest values.
+        pub(super) fn update_values(&self, values: &[Option<u64>]) {
+            let watches = self.watches.borrow();
+            for (index, (watch, value)) in watches.iter().zip(values).enumerate() {
+                let Some(row) = self.rows.item(index as u32).and_downcast::<WatchRow>() else {
+                    continue;
+                };
+                let text = value.map_or_else(String::new, |raw| watch.format_value(raw));
+                if row.value() != text {
+                    row.set_value(text);
+                }
+            }
+            *self.values.borrow_mut() = values.to_vec();
+        }
+    }
+
+    impl RamWatch {
+        /// Changes the watch list, and reports the change.
+        fn apply(&self, f: impl FnOnce(&mut Vec<Watch>)) {
+            let mut watches = self.watches.borrow().clone();
+            f(&mut watches);
+            self.set_watches(watches.clone());
+
+            if let Some(tx) = &*self.change_tx.borrow() {
+                let _ = tx.unbounded_send(watches);
+            }
+        }
+
+        /// Builds a watch from the fields, or returns `None` if the address is invalid.
+        fn watch_from_fields(&self) -> Option<Watch> {
+            let (address, offsets) = Watch::parse_address(&self.address_field.text())?;
+            let value_type = ValueType::ALL[self.type_dropdown.selected() as usize];
+            let format = DisplayFormat::ALL[self.format_dropdown.selected() as usize];
+            Some(Watch {
+                label: self.label_field.text().into(),
+                address,
+                offsets,
+                value_type,
+                format,
+                frozen: None,
+            })
+        }
+
+        /// Fills the fields from a watch.
+        fn fill_fields(&self, watch: &Watch) {
+            self.address_field.set_text(&watch.address_text());
+            self.label_field.set_text(&watch.label);
+            let position = |ty| ValueType::ALL.iter().position(|&other| other == ty);
+            self.type_dropdown
+                .set_selected(position(watch.value_type).unwrap_or(0) as u32);
+            let position = |fmt| DisplayFormat::ALL.iter().position(|&other| other == fmt);
+            self.format_dropdown
+                .set_selected(position(watch.format).unwrap_or(0) as u32);
+        }
+
+        /// Returns the indices of the selected watches.
+        fn selected_indices(&self) -> Vec<usize> {
+            let selection = self.selection.selection();
+            (0..self.rows.n_items())
+                .filter(|&index| selection.contains(index))
+                .map(|index| index as usize)
+                .collect()
+        }
+
+        async fn import(&self) {
+            let obj = self.obj();
+            let Ok(file) = self.import_dialog.open_future(Some(&*obj)).await else {
+                return;
+            };
+            let watches = match file.load_contents_future().await {
+                Ok((data, _)) => watch::parse_watch_file(&String::from_utf8_lossy(&data)),
+                Err(err) => {
+                    self.show_error(&err.to_string());
+                    return;
+                }
+            };
+            if watches.is_empty() {
+                self.show_error(&tr!("ram_watch" => "No watches could be read from the file."));
+                return;
+            }
+            self.add_watches(watches);
+        }
+
+        fn show_error(&self, detail: &str) {
+            let dialog = gtk::AlertDialog::builder()
+                .modal(true)
+                .message(tr!("ram_watch" => "Failed to import watches"))
+                .detail(detail)
+                .build();
+            dialog.show(Some(&*self.obj()));
+        }
+
+        fn add_column(&self, title: &str, property: &'static str) {
+            let factory = gtk::SignalListItemFactory::new();
+            factory.connect_setup(move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let label = gtk::Label::new(None);
+                label.set_xalign(0.0);
+                item.property_expression("item")
+                    .chain_property::<WatchRow>(property)
+                    .bind(&label, "label", gtk::Widget::NONE);
+                item.set_child(Some(&label));
+            });
+
+            let column = gtk::ColumnViewColumn::new(Some(title), Some(factory));
+            column.set_resizable(true);
+            self.watch_view.append_column(&column);
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl RamWatch {
+        #[template_callback]
+        fn add_clicked(&self, _: &gtk::Button) {
+            match self.watch_from_fields() {
+                Some(watch) => self.apply(|watches| watches.push(watch)),
+                None => self.obj().error_bell(),
+            }
+        }
+
+        #[template_callback]
+        fn update_clicked(&self, _: &gtk::Button) {
+            let indices = self.selected_indices();
+            let (Some(watch), &[index]) = (self.watch_from_fields(), &indices[..]) else {
+                self.obj().error_bell();
+                return;
+            };
+            self.apply(|watches| watches[index] = watch);
+            self.selection.select_item(index as u32, true);
+        }
+
+        #[template_callback]
+        fn remove_clicked(&self, _: &gtk::Button) {
+            let indices = self.selected_indices();
+            if indices.is_empty() {
+                return;
+            }
+            self.apply(|watches| {
+                for index in indices.into_iter().rev() {
+                    watches.remove(index);
+                }
+            });
+        }
+
+        #[template_callback]
+        fn freeze_clicked(&self, _: &gtk::Button) {
+            let indices = self.selected_indices();
+            if indices.is_empty() {
+                return;
+            }
+            // Freeze at the value typed in, or at the current value if none was.
+            let text = self.freeze_field.text();
+            let mut frozen = Vec::with_capacity(indices.len());
+            {
+                let watches = self.watches.borrow();
+                let values = self.values.borrow();
+                for &index in &indices {
+                    let value = match text.trim().is_empty() {
+                        true => values.get(index).copied().flatten(),
+                        false => watches[index].value_type.parse_raw(&text),
+                    };
+                    let Some(value) = value else {
+                        self.obj().error_bell();
+                        return;
+                    };
+                    frozen.push((index, value));
+                }
+            }
+            self.apply(|watches| {
+                for (index, value) in frozen {
+                    watches[index].frozen = Some(value);
+                }
+            });
+        }
+
+        #[template_callback]
+        fn unfreeze_clicked(&self, _: &gtk::Button) {
+            let indices = self.selected_indices();
+            self.apply(|watches| {
+                for index in indices {
+                    watches[index].frozen = None;
+                }
+            });
+        }
+
+        #[template_callback]
+        fn import_clicked(&self, _: &gtk::Button) {
+            let obj = self.obj().clone();
+            glib::spawn_future_local(async move {
+                obj.imp().import().await;
+            });
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RamWatch {
+        const NAME: &'static str = "M64PRS_RamWatch";
+        type Type = super::RamWatch;
+        type ParentType = gtk::Window;
+
+        fn new() -> Self {
+            let rows = gio::ListStore::new::<WatchRow>();
+            let selection = gtk::MultiSelection::new(Some(rows.clone()));
+            let (change_tx, change_rx) = mpsc::unbounded();
+
+            Self {
+                watch_view: Default::default(),
+                address_field: Default::default(),
+                label_field: Default::default(),
+                type_dropdown: Default::default(),
+                format_dropdown: Default::default(),
+                freeze_field: Default::default(),
+                import_dialog: Default::default(),
+                rows,
+                selection,
+                watches: Default::default(),
+                values: Default::default(),
+                change_tx: RefCell::new(Some(change_tx)),
+                change_rx: RefCell::new(Some(change_rx)),
+            }
+        }
+
+        fn class_init(class: &mut Self::Class) {
+            WatchRow::ensure_type();
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for RamWatch {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.watch_view.set_model(Some(&self.selection));
+
+            self.add_column(&tr!("ram_watch" => "Label"), "label");
+            self.add_column(&tr!("ram_watch" => "Address"), "address");
+            self.add_column(&tr!("ram_watch" => "Type"), "kind");
+            self.add_column(&tr!("ram_watch" => "Value"), "value");
+            self.add_column(&tr!("ram_watch" => "Frozen"), "frozen");
+
+            // Selecting a single watch loads it into the fields, so it can be updated.
+            let this_ref = self.obj().downgrade();
+            self.selection.connect_selection_changed(move |_, _, _| {
+                let Some(this) = this_ref.upgrade() else {
+                    return;
+                };
+                let this = this.imp();
+                if let [index] = this.selected_indices()[..] {
+                    let watch = this.watches.borrow().get(index).cloned();
+                    if let Some(watch) = watch {
+                        this.fill_fields(&watch);
+                    }
+                }
+            });
+        }
+
+        fn dispose(&self) {
+            self.dispose_template();
+        }
+    }
+    impl WidgetImpl for RamWatch {}
+    impl WindowImpl for RamWatch {
+        fn close_request(&self) -> glib::Propagation {
+            // Ends the stream of changes.
+            self.change_tx.take();
+            glib::Propagation::Proceed
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Window showing the values of watched memory locations.
+    pub struct RamWatch(ObjectSubclass<inner::RamWatch>)
+    @extends
+        gtk::Window,
+        gtk::Widget,
+    @implements
+        gtk::Accessible,
+        gtk::Buildable,
+        gtk::ConstraintTarget,
+        gtk::Native,
+        gtk::Root,
+        gtk::ShortcutManager;
+}
+
+impl RamWatch {
+    pub(super) fn new() -> Self {
+        glib::Object::new()
+    }
+}
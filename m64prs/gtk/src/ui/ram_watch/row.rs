@@ -0,0 +1,54 @@
+use super::watch::Watch;
+
+mod inner {
+    use std::cell::RefCell;
+
+    use gtk::{prelude::*, subclass::prelude::*};
+
+    /// One watch, as shown in the RAM watch window.
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::WatchRow)]
+    pub struct WatchRow {
+        #[property(get, construct_only)]
+        label: RefCell<String>,
+        #[property(get, construct_only)]
+        address: RefCell<String>,
+        #[property(get, construct_only)]
+        kind: RefCell<String>,
+        /// The formatted value, or empty if it can't be read.
+        #[property(get, set)]
+        value: RefCell<String>,
+        /// The formatted frozen value, or empty if the value isn't frozen.
+        #[property(get, construct_only)]
+        frozen: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WatchRow {
+        const NAME: &'static str = "M64PRS_WatchRow";
+        type Type = super::WatchRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for WatchRow {}
+}
+
+glib::wrapper! {
+    pub struct WatchRow(ObjectSubclass<inner::WatchRow>);
+}
+
+impl WatchRow {
+    pub fn new(watch: &Watch) -> Self {
+        glib::Object::builder()
+            .property("label", &watch.label)
+            .property("address", watch.address_text())
+            .property("kind", format!("{} {}", watch.value_type, watch.format))
+            .property(
+                "frozen",
+                watch
+                    .frozen
+                    .map_or_else(String::new, |raw| watch.format_value(raw)),
+            )
+            .build()
+    }
+}
@@ -0,0 +1,405 @@
+//! RAM watch entries, and the files they are stored in.
+
+use std::{
+    fmt::{self, Display},
+    fs, io,
+    path::PathBuf,
+};
+
+use m64prs_core::memory::MemoryHandle;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::paths::CONFIG_DIR;
+
+/// Base address of KSEG0, where RDRAM is directly mapped.
+const KSEG0_BASE: u32 = 0x8000_0000;
+
+/// Type of a watched value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ValueType {
+    U8,
+    S8,
+    U16,
+    S16,
+    U32,
+    S32,
+    F32,
+}
+
+/// How a watched value is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum DisplayFormat {
+    #[default]
+    Decimal,
+    Hex,
+    Binary,
+}
+
+/// A watched memory location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watch {
+    pub label: String,
+    /// Address of the value, or of the first pointer if `offsets` isn't empty.
+    pub address: u32,
+    /// Offsets to add after following each pointer, starting from `address`.
+    #[serde(default)]
+    pub offsets: Vec<u32>,
+    pub value_type: ValueType,
+    #[serde(default)]
+    pub format: DisplayFormat,
+    /// Bits written to the value on every frame and input poll, if it is frozen. Freezes
+    /// aren't saved.
+    #[serde(skip)]
+    pub frozen: Option<u64>,
+}
+
+impl ValueType {
+    pub const ALL: [ValueType; 7] = [
+        ValueType::U8,
+        ValueType::S8,
+        ValueType::U16,
+        ValueType::S16,
+        ValueType::U32,
+        ValueType::S32,
+        ValueType::F32,
+    ];
+
+    /// Size of the value in bytes.
+    pub fn size(self) -> u32 {
+        match self {
+            ValueType::U8 | ValueType::S8 => 1,
+            ValueType::U16 | ValueType::S16 => 2,
+            ValueType::U32 | ValueType::S32 | ValueType::F32 => 4,
+        }
+    }
+
+    /// Reads the value's bits from `address`, zero-extended.
+    pub fn read_raw(self, memory: &MemoryHandle, address: u32) -> Option<u64> {
+        match self.size() {
+            1 => memory.read::<u8>(address).map(u64::from),
+            2 => memory.read::<u16>(address).map(u64::from),
+            _ => memory.read::<u32>(address).map(u64::from),
+        }
+        .ok()
+    }
+
+    /// Writes the value's bits to `address`, truncated to its size.
+    pub fn write_raw(self, memory: &MemoryHandle, address: u32, raw: u64) -> bool {
+        match self.size() {
+            1 => memory.write(address, raw as u8),
+            2 => memory.write(address, raw as u16),
+            _ => memory.write(address, raw as u32),
+        }
+        .is_ok()
+    }
+
+    /// Parses a value typed by the user, returning its bits. Integers can be given in
+    /// decimal, or in hex with a `0x` prefix.
+    pub fn parse_raw(self, text: &str) -> Option<u64> {
+        let text = text.trim();
+        if self == ValueType::F32 {
+            return text.parse::<f32>().ok().map(|value| value.to_bits() as u64);
+        }
+        let value = match text.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+            None => text.parse::<i64>().ok()?,
+        };
+        let bits = self.size() * 8;
+        let (min, max) = match self {
+            ValueType::S8 | ValueType::S16 | ValueType::S32 => {
+                (-(1i64 << (bits - 1)), (1i64 << bits) - 1)
+            }
+            _ => (0, (1i64 << bits) - 1),
+        };
+        (min..=max)
+            .contains(&value)
+            .then_some(value as u64 & ((1u64 << bits) - 1))
+    }
+
+    /// Formats raw bits read by [`ValueType::read_raw`].
+    pub fn format_raw(self, raw: u64, format: DisplayFormat) -> String {
+        let digits = self.size() as usize * 2;
+        match format {
+            DisplayFormat::Hex => format!("{:0digits$X}", raw),
+            DisplayFormat::Binary => format!("{:0width$b}", raw, width = digits * 4),
+            DisplayFormat::Decimal => match self {
+                ValueType::U8 | ValueType::U16 | ValueType::U32 => raw.to_string(),
+                ValueType::S8 => (raw as u8 as i8).to_string(),
+                ValueType::S16 => (raw as u16 as i16).to_string(),
+                ValueType::S32 => (raw as u32 as i32).to_string(),
+                ValueType::F32 => f32::from_bits(raw as u32).to_string(),
+            },
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValueType::U8 => "u8",
+            ValueType::S8 => "s8",
+            ValueType::U16 => "u16",
+            ValueType::S16 => "s16",
+            ValueType::U32 => "u32",
+            ValueType::S32 => "s32",
+            ValueType::F32 => "float",
+        })
+    }
+}
+
+impl DisplayFormat {
+    pub const ALL: [DisplayFormat; 3] = [
+        DisplayFormat::Decimal,
+        DisplayFormat::Hex,
+        DisplayFormat::Binary,
+    ];
+}
+
+impl Display for DisplayFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DisplayFormat::Decimal => "Decimal",
+            DisplayFormat::Hex => "Hex",
+            DisplayFormat::Binary => "Binary",
+        })
+    }
+}
+
+impl Watch {
+    /// Follows the watch's pointer chain, returning the address of the value.
+    pub fn resolve(&self, memory: &MemoryHandle) -> Option<u32> {
+        let mut address = self.address;
+        for &offset in &self.offsets {
+            let pointer = memory.read::<u32>(address).ok()?;
+            if pointer == 0 {
+                return None;
+            }
+            address = pointer.wrapping_add(offset);
+        }
+        Some(address)
+    }
+
+    /// Reads the watched value's bits. Returns `None` if the pointer chain is broken
+    /// or the address can't be read.
+    pub fn read(&self, memory: &MemoryHandle) -> Option<u64> {
+        self.value_type.read_raw(memory, self.resolve(memory)?)
+    }
+
+    /// Writes the frozen value, if there is one.
+    /// # Return value
+    /// True if the value was written.
+    pub fn apply_freeze(&self, memory: &MemoryHandle) -> bool {
+        let Some(raw) = self.frozen else {
+            return false;
+        };
+        self.resolve(memory)
+            .is_some_and(|address| self.value_type.write_raw(memory, address, raw))
+    }
+
+    /// Formats a value read by [`Watch::read`].
+    pub fn format_value(&self, raw: u64) -> String {
+        self.value_type.format_raw(raw, self.format)
+    }
+
+    /// Formats the address and pointer chain, in the form accepted by
+    /// [`Watch::parse_address`].
+    pub fn address_text(&self) -> String {
+        let mut text = format!("{:08X}", self.address);
+        for offset in &self.offsets {
+            text.push_str(&format!(", {:X}", offset));
+        }
+        text
+    }
+
+    /// Parses an address, optionally followed by pointer offsets, as comma-separated hex
+    /// numbers. Addresses in RDRAM's physical range are mapped to KSEG0.
+    pub fn parse_address(text: &str) -> Option<(u32, Vec<u32>)> {
+        let mut parts = text
+            .split(',')
+            .map(|part| u32::from_str_radix(part.trim().trim_start_matches("0x"), 16));
+        let address = to_virtual(parts.next()?.ok()?);
+        let offsets = parts.collect::<Result<Vec<_>, _>>().ok()?;
+        Some((address, offsets))
+    }
+}
+
+/// Maps addresses in RDRAM's physical range to KSEG0, leaving other addresses unchanged.
+pub fn to_virtual(address: u32) -> u32 {
+    match address < KSEG0_BASE {
+        true => address | KSEG0_BASE,
+        false => address,
+    }
+}
+
+/// Parses a Mupen64-rr or BizHawk watch file, skipping entries that can't be represented.
+///
+/// Mupen64-rr lines are `index, address, size, type, endian, label`, while BizHawk lines are
+/// `address, size, type, big-endian, [domain,] label`, all separated by tabs. Sizes are
+/// `b`, `w` or `d`, and types are `u`, `s`, `h`, `b` or `f`.
+pub fn parse_watch_file(text: &str) -> Vec<Watch> {
+    let mut watches = Vec::new();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (address, size, kind, label) = match fields.as_slice() {
+            // Mupen64-rr
+            [_, address, size, kind, _, label @ ..]
+                if address.len() > 1 && size.len() == 1 && !label.is_empty() =>
+            {
+                (*address, *size, *kind, label.join("\t"))
+            }
+            // BizHawk, with a domain per watch
+            [address, size, kind, _, domain, label @ ..]
+                if size.len() == 1 && !label.is_empty() =>
+            {
+                if !matches!(*domain, "RDRAM" | "System Bus") {
+                    continue;
+                }
+                (*address, *size, *kind, label.join("\t"))
+            }
+            // BizHawk, with a domain for the whole file
+            [address, size, kind, _, label] if size.len() == 1 => {
+                (*address, *size, *kind, (*label).to_owned())
+            }
+            _ => continue,
+        };
+
+        let Ok(address) = u32::from_str_radix(address, 16) else {
+            continue;
+        };
+        let (value_type, format) = match (size, kind) {
+            ("d", "f") => (ValueType::F32, DisplayFormat::Decimal),
+            ("b", "s") => (ValueType::S8, DisplayFormat::Decimal),
+            ("w", "s") => (ValueType::S16, DisplayFormat::Decimal),
+            ("d", "s") => (ValueType::S32, DisplayFormat::Decimal),
+            (size, kind) => {
+                let value_type = match size {
+                    "b" => ValueType::U8,
+                    "w" => ValueType::U16,
+                    "d" => ValueType::U32,
+                    // Separators and unknown sizes
+                    _ => continue,
+                };
+                let format = match kind {
+                    "u" => DisplayFormat::Decimal,
+                    "b" => DisplayFormat::Binary,
+                    _ => DisplayFormat::Hex,
+                };
+                (value_type, format)
+            }
+        };
+        watches.push(Watch {
+            label,
+            address: to_virtual(address),
+            offsets: Vec::new(),
+            value_type,
+            format,
+            frozen: None,
+        });
+    }
+    watches
+}
+
+/// Path of the file storing the watches for a ROM.
+fn watch_list_path(rom_crc: u32) -> PathBuf {
+    CONFIG_DIR
+        .join("watches")
+        .join(format!("{:08X}.json", rom_crc))
+}
+
+/// Loads the saved watches for a ROM.
+pub fn load_watches(rom_crc: u32) -> io::Result<Vec<Watch>> {
+    match fs::read(watch_list_path(rom_crc)) {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Saves the watches for a ROM.
+pub fn save_watches(rom_crc: u32, watches: &[Watch]) -> io::Result<()> {
+    let path = watch_list_path(rom_crc);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(watches)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn watch(label: &str, address: u32, value_type: ValueType, format: DisplayFormat) -> Watch {
+        Watch {
+            label: label.to_owned(),
+            address,
+            offsets: Vec::new(),
+            value_type,
+            format,
+            frozen: None,
+        }
+    }
+
+    #[test]
+    fn parse_mupen64_rr() {
+        let text = "0\t80339E3C\td\tf\t1\tMario X\n\
+                    1\t8033B1AC\tw\th\t1\tAction\n\
+                    2\t00339EDA\tb\ts\t1\tHealth\n";
+        assert_eq!(
+            parse_watch_file(text),
+            [
+                watch(
+                    "Mario X",
+                    0x8033_9E3C,
+                    ValueType::F32,
+                    DisplayFormat::Decimal
+                ),
+                watch("Action", 0x8033_B1AC, ValueType::U16, DisplayFormat::Hex),
+                watch("Health", 0x8033_9EDA, ValueType::S8, DisplayFormat::Decimal),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_bizhawk_file_domain() {
+        let text = "SystemID N64\n\
+                    Domain RDRAM\n\
+                    00339E3C\td\tf\t1\tMario X\n\
+                    0\tS\t_\t1\t\n\
+                    0033B17C\tb\tb\t1\tFlags\n";
+        assert_eq!(
+            parse_watch_file(text),
+            [
+                watch(
+                    "Mario X",
+                    0x8033_9E3C,
+                    ValueType::F32,
+                    DisplayFormat::Decimal
+                ),
+                watch("Flags", 0x8033_B17C, ValueType::U8, DisplayFormat::Binary),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_bizhawk_watch_domain() {
+        let text = "00339E3C\td\tu\t1\tRDRAM\tTimer\n\
+                    00000010\tw\th\t1\tROM\tHeader\n\
+                    80339EDA\tw\ts\t1\tSystem Bus\tSpeed\n";
+        assert_eq!(
+            parse_watch_file(text),
+            [
+                watch("Timer", 0x8033_9E3C, ValueType::U32, DisplayFormat::Decimal),
+                watch("Speed", 0x8033_9EDA, ValueType::S16, DisplayFormat::Decimal),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_address_offsets() {
+        assert_eq!(
+            Watch::parse_address("0x00361158, 4C, 0x10"),
+            Some((0x8036_1158, vec![0x4C, 0x10]))
+        );
+        assert_eq!(Watch::parse_address("80361158, zz"), None);
+    }
+}
@@ -0,0 +1,155 @@
+mod inner {
+    use std::cell::{Cell, RefCell};
+
+    use futures::channel::oneshot;
+    use glib::subclass::InitializingObject;
+    use gtk::{prelude::*, subclass::prelude::*, TemplateChild};
+    use m64prs_vcr::greenzone::SeekTarget;
+    use tr::tr;
+
+    #[derive(Default, gtk::CompositeTemplate, glib::Properties)]
+    #[template(file = "mod.ui")]
+    #[properties(wrapper_type = super::SeekDialog)]
+    pub struct SeekDialog {
+        #[template_child]
+        target_field: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        unit_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        progress_bar: TemplateChild<gtk::ProgressBar>,
+
+        /// True once a target was chosen, while the seek runs.
+        #[property(get, set, default_value = false)]
+        seeking: Cell<bool>,
+
+        response_tx: RefCell<Option<oneshot::Sender<bool>>>,
+        start: Cell<Option<u32>>,
+    }
+
+    #[m64prs_gtk_utils::forward_wrapper(super::SeekDialog, vis = pub(in super::super))]
+    impl SeekDialog {
+        /// Asks for a seek target. Frame targets are converted to sample indices using
+        /// `frame_size`, the number of input samples per frame.
+        pub(super) async fn prompt(
+            &self,
+            transient_for: Option<&impl IsA<gtk::Window>>,
+            frame_size: u32,
+        ) -> Option<SeekTarget> {
+            let (tx, rx) = oneshot::channel();
+            *self.response_tx.borrow_mut() = Some(tx);
+
+            self.obj().set_transient_for(transient_for);
+            self.obj().present();
+            if !rx.await.unwrap_or(false) {
+                return None;
+            }
+
+            let value = self.target_field.value_as_int().max(0) as u32;
+            let target = match self.unit_dropdown.selected() {
+                0 => SeekTarget::Index(value.saturating_mul(frame_size)),
+                _ => SeekTarget::Vi(value),
+            };
+            self.start.set(None);
+            self.obj().set_seeking(true);
+            Some(target)
+        }
+
+        /// Shows how far the seek has progressed. `position` is measured in the same unit
+        /// as `target`.
+        pub(super) fn set_progress(&self, position: u32, target: SeekTarget) {
+            let start = self.start.get().unwrap_or(position);
+            self.start.set(Some(start));
+
+            let total = target.value().saturating_sub(start);
+            let done = position.saturating_sub(start).min(total);
+            self.progress_bar.set_fraction(match total {
+                0 => 1.0,
+                total => done as f64 / total as f64,
+            });
+            self.progress_bar.set_text(Some(&match target {
+                SeekTarget::Index(_) => {
+                    tr!("seek_dialog" => "Input {} of {}", position, target.value())
+                }
+                SeekTarget::Vi(_) => tr!("seek_dialog" => "VI {} of {}", position, target.value()),
+            }));
+        }
+
+        /// Returns true if the dialog was closed while seeking.
+        pub(super) fn cancelled(&self) -> bool {
+            !self.obj().is_visible()
+        }
+    }
+
+    #[gtk::template_callbacks]
+    impl SeekDialog {
+        #[template_callback]
+        fn not(&self, input: bool) -> bool {
+            !input
+        }
+
+        #[template_callback]
+        fn seek_clicked(&self, _: &gtk::Button) {
+            if let Some(tx) = self.response_tx.take() {
+                let _ = tx.send(true);
+            }
+        }
+
+        #[template_callback]
+        fn cancel_clicked(&self, _: &gtk::Button) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SeekDialog {
+        const NAME: &'static str = "M64PRS_SeekDialog";
+        type Type = super::SeekDialog;
+        type ParentType = gtk::Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for SeekDialog {
+        fn dispose(&self) {
+            self.dispose_template();
+        }
+    }
+    impl WidgetImpl for SeekDialog {}
+    impl WindowImpl for SeekDialog {
+        fn close_request(&self) -> glib::Propagation {
+            if let Some(tx) = self.response_tx.take() {
+                let _ = tx.send(false);
+            }
+            glib::Propagation::Proceed
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Dialog asking for a position to seek the movie to, showing progress while seeking.
+    pub struct SeekDialog(ObjectSubclass<inner::SeekDialog>)
+    @extends
+        gtk::Window,
+        gtk::Widget,
+    @implements
+        gtk::Accessible,
+        gtk::Buildable,
+        gtk::ConstraintTarget,
+        gtk::Native,
+        gtk::Root,
+        gtk::ShortcutManager;
+}
+
+impl SeekDialog {
+    pub(super) fn new() -> Self {
+        glib::Object::new()
+    }
+}
@@ -141,4 +141,7 @@ impl SettingsDialog {
     }
 }
 
-pub use pages::init_config;
+pub use pages::{
+    init_config, CHECKSUM_INTERVAL_KEY, END_BEHAVIOR_KEY, GREENZONE_INTERVAL_KEY,
+    GREENZONE_MAX_MB_KEY, VCR_SECTION_KEY,
+};
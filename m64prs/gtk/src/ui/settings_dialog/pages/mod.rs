@@ -1,4 +1,5 @@
 mod emu;
+mod movie;
 mod plugins;
 mod shortcuts;
 
@@ -6,18 +7,26 @@ use glib::types::StaticTypeExt;
 use m64prs_core::Core;
 
 pub(super) use emu::EmuPage;
+pub(super) use movie::MoviePage;
 pub(super) use plugins::PluginsPage;
 pub(super) use shortcuts::ShortcutsPage;
 
+pub use movie::{
+    CHECKSUM_INTERVAL_KEY, END_BEHAVIOR_KEY, GREENZONE_INTERVAL_KEY, GREENZONE_MAX_MB_KEY,
+    VCR_SECTION_KEY,
+};
+
 /// Ensures all page classes are initialized.
 pub(super) fn ensure_types() {
     EmuPage::ensure_type();
+    MoviePage::ensure_type();
     PluginsPage::ensure_type();
     ShortcutsPage::ensure_type();
 }
 
 /// Performs setup on the initial configuration values.
 pub fn init_config(core: &mut Core) {
+    movie::init_config(core);
     plugins::init_config(core);
     shortcuts::init_config(core);
 }
@@ -0,0 +1,148 @@
+use std::ffi::CStr;
+
+use m64prs_core::Core;
+
+use crate::ui::settings_dialog::SettingsPage;
+
+/// Config section holding movie settings.
+pub const VCR_SECTION_KEY: &CStr = c"M64PRS-VCR";
+/// Number of VIs between RAM checksums when recording a movie. 0 disables checksums.
+pub const CHECKSUM_INTERVAL_KEY: &CStr = c"ChecksumInterval";
+/// Number of input frames between greenzone savestates. 0 disables the greenzone.
+pub const GREENZONE_INTERVAL_KEY: &CStr = c"GreenzoneInterval";
+/// Memory limit for greenzone savestates, in MiB.
+pub const GREENZONE_MAX_MB_KEY: &CStr = c"GreenzoneMaxMB";
+/// What happens when playback reaches the end of a movie: 0 stops the movie, 1 pauses,
+/// 2 continues with live input and 3 continues recording.
+pub const END_BEHAVIOR_KEY: &CStr = c"EndBehavior";
+
+mod inner {
+    use std::cell::Cell;
+
+    use gtk::{prelude::*, subclass::prelude::*};
+
+    use crate::ui::{
+        core::CoreReadyState,
+        settings_dialog::{settings_page::SettingsPageImpl, SettingsPage},
+    };
+
+    use super::{
+        CHECKSUM_INTERVAL_KEY, END_BEHAVIOR_KEY, GREENZONE_INTERVAL_KEY, GREENZONE_MAX_MB_KEY,
+        VCR_SECTION_KEY,
+    };
+
+    #[derive(Default, gtk::CompositeTemplate, glib::Properties)]
+    #[template(file = "movie.ui")]
+    #[properties(wrapper_type = super::MoviePage)]
+    pub struct MoviePage {
+        #[property(get, set, default = 0)]
+        checksum_interval: Cell<u32>,
+        #[property(get, set, default = 60)]
+        greenzone_interval: Cell<u32>,
+        #[property(get, set, default = 256)]
+        greenzone_max_mb: Cell<u32>,
+        #[property(get, set, default = 0)]
+        end_behavior: Cell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MoviePage {
+        const NAME: &'static str = "M64PRS_SettingsMoviePage";
+        type Type = super::MoviePage;
+        type ParentType = gtk::Box;
+        type Interfaces = (SettingsPage,);
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for MoviePage {}
+    impl WidgetImpl for MoviePage {}
+    impl BoxImpl for MoviePage {}
+
+    impl SettingsPageImpl for MoviePage {
+        async fn load_page(&self, state: &mut CoreReadyState) {
+            let sect = state
+                .cfg_open_mut(VCR_SECTION_KEY)
+                .expect("Failed to open config section");
+            let this = self.obj();
+
+            this.set_checksum_interval(
+                sect.get_cast_or(0, CHECKSUM_INTERVAL_KEY).unwrap().max(0) as u32
+            );
+            this.set_greenzone_interval(
+                sect.get_cast_or(60, GREENZONE_INTERVAL_KEY).unwrap().max(0) as u32,
+            );
+            this.set_greenzone_max_mb(
+                sect.get_cast_or(256, GREENZONE_MAX_MB_KEY).unwrap().max(0) as u32
+            );
+            this.set_end_behavior(sect.get_cast_or(0, END_BEHAVIOR_KEY).unwrap().clamp(0, 3) as u32);
+        }
+
+        async fn save_page(&self, state: &mut CoreReadyState) {
+            let mut sect = state
+                .cfg_open_mut(VCR_SECTION_KEY)
+                .expect("Failed to open config section");
+            let this = self.obj();
+
+            sect.set(CHECKSUM_INTERVAL_KEY, this.checksum_interval() as i32)
+                .unwrap();
+            sect.set(GREENZONE_INTERVAL_KEY, this.greenzone_interval() as i32)
+                .unwrap();
+            sect.set(GREENZONE_MAX_MB_KEY, this.greenzone_max_mb() as i32)
+                .unwrap();
+            sect.set(END_BEHAVIOR_KEY, this.end_behavior() as i32)
+                .unwrap();
+
+            sect.save().unwrap();
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct MoviePage(ObjectSubclass<inner::MoviePage>)
+        @extends
+            gtk::Box,
+            gtk::Widget,
+        @implements
+            gtk::Accessible,
+            gtk::Buildable,
+            gtk::ConstraintTarget,
+            SettingsPage;
+}
+
+pub(super) fn init_config(core: &mut Core) {
+    let mut sect = core
+        .cfg_open_mut(VCR_SECTION_KEY)
+        .expect("Failed to open config section");
+    sect.set_default(
+        CHECKSUM_INTERVAL_KEY,
+        0,
+        c"Number of VIs between RAM checksums when recording a movie (0 to disable)",
+    )
+    .unwrap();
+    sect.set_default(
+        GREENZONE_INTERVAL_KEY,
+        60,
+        c"Number of input frames between greenzone savestates (0 to disable)",
+    )
+    .unwrap();
+    sect.set_default(
+        GREENZONE_MAX_MB_KEY,
+        256,
+        c"Memory limit for greenzone savestates, in MiB",
+    )
+    .unwrap();
+    sect.set_default(
+        END_BEHAVIOR_KEY,
+        0,
+        c"At the end of a movie: 0 = stop, 1 = pause, 2 = continue with live input, 3 = continue recording",
+    )
+    .unwrap();
+}
@@ -30,13 +30,17 @@ mod inner {
     };
 
     pub(super) const CFG_SECTION_KEY: &CStr = c"M64PRS-Shortcuts";
-    pub(super) static ACTION_TABLE: LazyLock<[(String, &'static str); 15]> = LazyLock::new(|| {
+    pub(super) static ACTION_TABLE: LazyLock<[(String, &'static str); 25]> = LazyLock::new(|| {
         [
             (tr!("main_act" => "Open ROM"), "app.file.open_rom"),
             (tr!("main_act" => "Close ROM"), "app.file.close_rom"),
             (tr!("main_act" => "Settings"), "app.file.settings"),
             (tr!("main_act" => "Pause/Resume"), "app.emu.toggle_pause"),
             (tr!("main_act" => "Frame Advance"), "app.emu.frame_advance"),
+            (
+                tr!("main_act" => "Advance to Next Input Poll"),
+                "app.emu.advance_to_poll",
+            ),
             (tr!("main_act" => "Reset ROM"), "app.emu.reset_rom"),
             (tr!("main_act" => "Save State"), "app.emu.save_slot"),
             (tr!("main_act" => "Load State"), "app.emu.load_slot"),
@@ -50,6 +54,15 @@ mod inner {
                 tr!("main_act" => "Read-only Mode"),
                 "app.vcr.toggle_read_only",
             ),
+            (tr!("main_act" => "Undo Input Change"), "app.vcr.undo"),
+            (tr!("main_act" => "Redo Input Change"), "app.vcr.redo"),
+            (tr!("main_act" => "Rewind One Frame"), "app.vcr.rewind"),
+            (tr!("main_act" => "Seek to Frame..."), "app.vcr.seek"),
+            (tr!("main_act" => "Branches..."), "app.vcr.branches"),
+            (tr!("main_act" => "Input Editor"), "app.vcr.input_editor"),
+            (tr!("main_act" => "RAM Watch"), "app.tools.ram_watch"),
+            (tr!("main_act" => "RAM Search"), "app.tools.ram_search"),
+            (tr!("main_act" => "Cheats"), "app.tools.cheats"),
         ]
     });
 
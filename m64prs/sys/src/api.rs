@@ -3,7 +3,15 @@
 
 use decan::{non_null, SymbolGroup};
 
-use crate::{ext::ptr_M64PRS_UseFrontendHandle, types::*};
+use crate::{
+    debug::{
+        ptr_DebugMemGetPointer, ptr_DebugMemRead16, ptr_DebugMemRead32, ptr_DebugMemRead64,
+        ptr_DebugMemRead8, ptr_DebugMemWrite16, ptr_DebugMemWrite32, ptr_DebugMemWrite64,
+        ptr_DebugMemWrite8, ptr_DebugVirtualToPhysical,
+    },
+    ext::ptr_M64PRS_UseFrontendHandle,
+    types::*,
+};
 
 #[derive(SymbolGroup)]
 pub struct FullCoreApi {
@@ -13,6 +21,10 @@ pub struct FullCoreApi {
     pub config: CoreConfigApi,
     #[subgroup]
     pub tas: CoreTasApi,
+    #[subgroup]
+    pub debug: CoreDebugApi,
+    #[subgroup]
+    pub cheat: CoreCheatApi,
 }
 
 #[derive(SymbolGroup)]
@@ -49,6 +61,8 @@ pub struct CoreConfigApi {
     // =================
     #[symbol = "ConfigGetSharedDataFilepath"]
     pub shared_data_filepath: non_null!(ptr_ConfigGetSharedDataFilepath),
+    #[symbol = "ConfigGetUserDataPath"]
+    pub user_data_path: non_null!(ptr_ConfigGetUserDataPath),
 
     // DISCOVERY
     // =================
@@ -116,6 +130,38 @@ pub struct CoreTasApi {
     pub set_savestate_handler: non_null!(ptr_CoreTAS_SetSavestateHandler),
 }
 
+#[derive(SymbolGroup)]
+pub struct CoreCheatApi {
+    #[symbol = "CoreAddCheat"]
+    pub add_cheat: non_null!(ptr_CoreAddCheat),
+    #[symbol = "CoreCheatEnabled"]
+    pub cheat_enabled: non_null!(ptr_CoreCheatEnabled),
+}
+
+#[derive(SymbolGroup)]
+pub struct CoreDebugApi {
+    #[symbol = "DebugMemGetPointer"]
+    pub mem_get_pointer: non_null!(ptr_DebugMemGetPointer),
+    #[symbol = "DebugVirtualToPhysical"]
+    pub virtual_to_physical: non_null!(ptr_DebugVirtualToPhysical),
+    #[symbol = "DebugMemRead64"]
+    pub mem_read_64: non_null!(ptr_DebugMemRead64),
+    #[symbol = "DebugMemRead32"]
+    pub mem_read_32: non_null!(ptr_DebugMemRead32),
+    #[symbol = "DebugMemRead16"]
+    pub mem_read_16: non_null!(ptr_DebugMemRead16),
+    #[symbol = "DebugMemRead8"]
+    pub mem_read_8: non_null!(ptr_DebugMemRead8),
+    #[symbol = "DebugMemWrite64"]
+    pub mem_write_64: non_null!(ptr_DebugMemWrite64),
+    #[symbol = "DebugMemWrite32"]
+    pub mem_write_32: non_null!(ptr_DebugMemWrite32),
+    #[symbol = "DebugMemWrite16"]
+    pub mem_write_16: non_null!(ptr_DebugMemWrite16),
+    #[symbol = "DebugMemWrite8"]
+    pub mem_write_8: non_null!(ptr_DebugMemWrite8),
+}
+
 #[derive(SymbolGroup)]
 pub struct BasePluginApi {
     #[symbol = "PluginGetVersion"]
@@ -0,0 +1,44 @@
+//! Parts of the debugger API needed to inspect emulated memory.
+//!
+//! These are written by hand, as the debugger types are left out of the generated bindings.
+
+use std::ffi::{c_uchar, c_uint, c_ulonglong, c_ushort, c_void};
+
+/// Memory regions that can be accessed through `DebugMemGetPointer`.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DbgMemPtrType {
+    Rdram = 1,
+    PiReg,
+    SiReg,
+    ViReg,
+    RiReg,
+    AiReg,
+}
+
+// FUNCTION PROTOTYPES
+// ========================
+
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemGetPointer =
+    Option<unsafe extern "C" fn(mem_ptr_type: DbgMemPtrType) -> *mut c_void>;
+#[allow(non_camel_case_types)]
+pub type ptr_DebugVirtualToPhysical = Option<unsafe extern "C" fn(address: c_uint) -> c_uint>;
+
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemRead64 = Option<unsafe extern "C" fn(address: c_uint) -> c_ulonglong>;
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemRead32 = Option<unsafe extern "C" fn(address: c_uint) -> c_uint>;
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemRead16 = Option<unsafe extern "C" fn(address: c_uint) -> c_ushort>;
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemRead8 = Option<unsafe extern "C" fn(address: c_uint) -> c_uchar>;
+
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemWrite64 = Option<unsafe extern "C" fn(address: c_uint, value: c_ulonglong)>;
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemWrite32 = Option<unsafe extern "C" fn(address: c_uint, value: c_uint)>;
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemWrite16 = Option<unsafe extern "C" fn(address: c_uint, value: c_ushort)>;
+#[allow(non_camel_case_types)]
+pub type ptr_DebugMemWrite8 = Option<unsafe extern "C" fn(address: c_uint, value: c_uchar)>;
@@ -3,6 +3,7 @@
 
 pub mod api;
 pub mod common;
+pub mod debug;
 pub mod ext;
 pub mod key;
 mod types;
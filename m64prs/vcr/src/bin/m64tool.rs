@@ -0,0 +1,689 @@
+//! Command-line toolkit for inspecting and editing .m64 movies.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use m64prs_sys::Buttons;
+use m64prs_vcr::{
+    bundle::MovieBundle,
+    movie::{ControllerFlags, ExtendedFlags, ExtendedInfo, M64File, M64Header, StartType},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Inspect and edit Mupen64 .m64 movies")]
+struct Args {
+    /// Repair truncated or over-long movies instead of rejecting them.
+    #[arg(long, global = true)]
+    lenient: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print a movie's header.
+    Dump {
+        movie: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+    /// Change header fields.
+    Edit {
+        movie: PathBuf,
+        /// Set a field, using the names from `dump` (e.g. `author=me`, `extended.wii_vc=true`).
+        #[arg(short, long = "set", value_name = "KEY=VALUE")]
+        sets: Vec<String>,
+        /// Replace the header with one previously produced by `dump`.
+        #[arg(long, value_name = "FILE")]
+        from: Option<PathBuf>,
+        /// Write to this file instead of modifying the movie in place.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Remove all input frames starting at a frame. The VI count is scaled down with the
+    /// number of frames, which is exact for movies without lag frames.
+    Truncate {
+        movie: PathBuf,
+        frame: u32,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Copy a range of frames from another movie.
+    Splice {
+        movie: PathBuf,
+        source: PathBuf,
+        /// First frame to copy from the source movie.
+        #[arg(long)]
+        from: u32,
+        /// Frame after the last frame to copy from the source movie.
+        #[arg(long)]
+        to: u32,
+        /// Frame to copy to. Defaults to the same frame as `--from`.
+        #[arg(long)]
+        at: Option<u32>,
+        /// Insert the frames instead of overwriting existing ones.
+        #[arg(long)]
+        insert: bool,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Append movies onto the first one.
+    Concat {
+        #[arg(required = true, num_args = 2..)]
+        movies: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Compare two movies frame by frame.
+    Diff {
+        left: PathBuf,
+        right: PathBuf,
+        /// Stop after this many differing frames.
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Add the Mupen64-rr-lua extended header fields, or remove them. Like the other
+    /// commands, this only supports version 3 movies.
+    SetExtended {
+        movie: PathBuf,
+        /// Remove the extended fields instead of adding them.
+        #[arg(long)]
+        remove: bool,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Convert a BizHawk .bk2 movie to an .m64 file.
+    ImportBk2 {
+        bk2: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Convert a movie to a BizHawk .bk2 movie.
+    ExportBk2 {
+        movie: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Insert lag frames from the lag log of this .bk2, such as the one the movie was
+        /// imported from.
+        #[arg(long)]
+        lag_from: Option<PathBuf>,
+    },
+    /// Pack a movie and its savestate or save data into a bundle.
+    Bundle {
+        movie: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Unpack a bundle into a movie, placing its savestate or save data next to it.
+    Unbundle {
+        bundle: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Convert a movie to the text format.
+    ToText {
+        movie: PathBuf,
+        /// Write to this file instead of standard output.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Convert a movie from the text format.
+    FromText {
+        text: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Toml,
+}
+
+/// Serializable form of [`M64Header`].
+#[derive(Debug, Serialize, Deserialize)]
+struct HeaderInfo {
+    uid: u32,
+    length_vis: u32,
+    /// Derived from the input data; ignored when editing.
+    length_samples: u32,
+    rerecord_count: u64,
+    vis_per_second: u8,
+    num_controllers: u8,
+    start_type: StartTypeName,
+    controller_flags: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extended: Option<ExtendedHeaderInfo>,
+    rom_name: String,
+    rom_crc: u32,
+    rom_cc: u16,
+    graphics_plugin: String,
+    audio_plugin: String,
+    input_plugin: String,
+    rsp_plugin: String,
+    author: String,
+    description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExtendedHeaderInfo {
+    version: u8,
+    wii_vc: bool,
+    #[serde(default)]
+    modified_memory: bool,
+    authorship_tag: String,
+    bruteforce_extra_data: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StartTypeName {
+    Snapshot,
+    Reset,
+    Eeprom,
+}
+
+impl HeaderInfo {
+    fn from_header(header: &M64Header) -> Result<Self, Box<dyn Error>> {
+        let start_type = match header.start_flags {
+            StartType::FROM_SNAPSHOT => StartTypeName::Snapshot,
+            StartType::FROM_RESET => StartTypeName::Reset,
+            StartType::FROM_EEPROM => StartTypeName::Eeprom,
+            StartType(other) => return Err(format!("invalid start flags {:#06x}", other).into()),
+        };
+
+        Ok(Self {
+            uid: header.uid,
+            length_vis: header.length_vis,
+            length_samples: header.length_samples,
+            rerecord_count: header.rerecords(),
+            vis_per_second: header.vis_per_second,
+            num_controllers: header.num_controllers,
+            start_type,
+            controller_flags: header.controller_flags.bits(),
+            extended: header.extended().map(|ext| ExtendedHeaderInfo {
+                version: ext.version,
+                wii_vc: ext.flags.contains(ExtendedFlags::WII_VC),
+                modified_memory: header.modified_memory(),
+                authorship_tag: ext
+                    .authorship_tag
+                    .iter()
+                    .take_while(|&&c| c != 0)
+                    .map(|&c| c as char)
+                    .collect(),
+                bruteforce_extra_data: ext.bruteforce_extra_data,
+            }),
+            rom_name: header.rom_name.try_read()?.to_owned(),
+            rom_crc: header.rom_crc,
+            rom_cc: header.rom_cc,
+            graphics_plugin: header.graphics_plugin.try_read()?.to_owned(),
+            audio_plugin: header.audio_plugin.try_read()?.to_owned(),
+            input_plugin: header.input_plugin.try_read()?.to_owned(),
+            rsp_plugin: header.rsp_plugin.try_read()?.to_owned(),
+            author: header.author.try_read()?.to_owned(),
+            description: header.description.try_read()?.to_owned(),
+        })
+    }
+
+    fn apply_to(&self, header: &mut M64Header) -> Result<(), Box<dyn Error>> {
+        header.uid = self.uid;
+        header.length_vis = self.length_vis;
+        header.vis_per_second = self.vis_per_second;
+        header.num_controllers = self.num_controllers;
+        header.start_flags = match self.start_type {
+            StartTypeName::Snapshot => StartType::FROM_SNAPSHOT,
+            StartTypeName::Reset => StartType::FROM_RESET,
+            StartTypeName::Eeprom => StartType::FROM_EEPROM,
+        };
+        header.controller_flags = ControllerFlags::from_bits_retain(self.controller_flags);
+
+        header.set_extended(match &self.extended {
+            Some(ext) => {
+                if !ext.authorship_tag.is_ascii() || ext.authorship_tag.len() > 4 {
+                    return Err("authorship tag must be at most 4 ASCII characters".into());
+                }
+                let mut authorship_tag = [0u8; 4];
+                authorship_tag[..ext.authorship_tag.len()]
+                    .copy_from_slice(ext.authorship_tag.as_bytes());
+
+                let mut flags = ExtendedFlags::empty();
+                flags.set(ExtendedFlags::WII_VC, ext.wii_vc);
+
+                Some(ExtendedInfo {
+                    version: ext.version,
+                    flags,
+                    authorship_tag,
+                    bruteforce_extra_data: ext.bruteforce_extra_data,
+                })
+            }
+            None => None,
+        });
+        header.set_rerecords(self.rerecord_count);
+        match &self.extended {
+            Some(ext) if ext.modified_memory => {
+                if !header.set_modified_memory() {
+                    return Err("modified_memory needs an empty or m64prs authorship tag".into());
+                }
+            }
+            _ => header.clear_modified_memory(),
+        }
+
+        header.rom_name.try_write(&*self.rom_name)?;
+        header.rom_crc = self.rom_crc;
+        header.rom_cc = self.rom_cc;
+        header.graphics_plugin.try_write(&*self.graphics_plugin)?;
+        header.audio_plugin.try_write(&*self.audio_plugin)?;
+        header.input_plugin.try_write(&*self.input_plugin)?;
+        header.rsp_plugin.try_write(&*self.rsp_plugin)?;
+        header.author.try_write(&*self.author)?;
+        header.description.try_write(&*self.description)?;
+
+        Ok(())
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(args) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<ExitCode, Box<dyn Error>> {
+    let lenient = args.lenient;
+
+    match args.command {
+        Command::Dump { movie, format } => {
+            let movie = read_movie(&movie, lenient)?;
+            let info = HeaderInfo::from_header(&movie.header)?;
+            let text = match format {
+                Format::Json => serde_json::to_string_pretty(&info)?,
+                Format::Toml => toml::to_string_pretty(&info)?,
+            };
+            println!("{}", text);
+        }
+        Command::Edit {
+            movie: path,
+            sets,
+            from,
+            output,
+        } => {
+            let mut movie = read_movie(&path, lenient)?;
+
+            let mut info = match from {
+                Some(from) => {
+                    let text = std::fs::read_to_string(&from)?;
+                    match from.extension().and_then(|ext| ext.to_str()) {
+                        Some("toml") => toml::from_str(&text)?,
+                        _ => serde_json::from_str(&text)?,
+                    }
+                }
+                None => HeaderInfo::from_header(&movie.header)?,
+            };
+            if !sets.is_empty() {
+                let mut value = serde_json::to_value(&info)?;
+                for set in &sets {
+                    apply_set(&mut value, set)?;
+                }
+                info = serde_json::from_value(value)?;
+            }
+            info.apply_to(&mut movie.header)?;
+
+            write_movie(output.as_deref().unwrap_or(&path), movie)?;
+        }
+        Command::Truncate {
+            movie: path,
+            frame,
+            output,
+        } => {
+            let mut movie = read_movie(&path, lenient)?;
+            truncate(&mut movie, frame)?;
+            write_movie(output.as_deref().unwrap_or(&path), movie)?;
+        }
+        Command::Splice {
+            movie: path,
+            source,
+            from,
+            to,
+            at,
+            insert,
+            output,
+        } => {
+            let mut movie = read_movie(&path, lenient)?;
+            let source = read_movie(&source, lenient)?;
+            let frame_size = frame_size(&movie.header)?;
+            if frame_size != self::frame_size(&source.header)? {
+                return Err("movies have a different number of controllers".into());
+            }
+            if from > to {
+                return Err("--from must not be after --to".into());
+            }
+
+            let src_range = (from as usize * frame_size)..(to as usize * frame_size);
+            let frames = source
+                .inputs
+                .get(src_range)
+                .ok_or("source movie is too short")?;
+
+            let dst_start = at.unwrap_or(from) as usize * frame_size;
+            if dst_start > movie.inputs.len() {
+                return Err("destination frame is past the end of the movie".into());
+            }
+            if insert {
+                movie
+                    .inputs
+                    .splice(dst_start..dst_start, frames.iter().copied());
+            } else {
+                let dst_end = (dst_start + frames.len()).min(movie.inputs.len());
+                movie
+                    .inputs
+                    .splice(dst_start..dst_end, frames.iter().copied());
+            }
+
+            write_movie(output.as_deref().unwrap_or(&path), movie)?;
+        }
+        Command::Concat { movies, output } => {
+            let mut paths = movies.iter();
+            let mut result = read_movie(paths.next().unwrap(), lenient)?;
+
+            for path in paths {
+                let next = read_movie(path, lenient)?;
+                if next.header.controller_flags.num_present()
+                    != result.header.controller_flags.num_present()
+                {
+                    return Err(format!(
+                        "{} has a different number of controllers",
+                        path.display()
+                    )
+                    .into());
+                }
+                result.header.length_vis = result
+                    .header
+                    .length_vis
+                    .saturating_add(next.header.length_vis);
+                result
+                    .header
+                    .set_rerecords(result.header.rerecords() + next.header.rerecords());
+                result.inputs.extend_from_slice(&next.inputs);
+            }
+
+            write_movie(&output, result)?;
+        }
+        Command::Diff { left, right, limit } => {
+            let left = read_movie(&left, lenient)?;
+            let right = read_movie(&right, lenient)?;
+            let frame_size = frame_size(&left.header)?;
+            if frame_size != self::frame_size(&right.header)? {
+                return Err("movies have a different number of controllers".into());
+            }
+
+            let left_frames: Vec<_> = left.inputs.chunks(frame_size).collect();
+            let right_frames: Vec<_> = right.inputs.chunks(frame_size).collect();
+
+            let mut differences = 0usize;
+            for (frame, (l, r)) in left_frames.iter().zip(&right_frames).enumerate() {
+                if l == r {
+                    continue;
+                }
+                differences += 1;
+                if differences <= limit {
+                    println!("frame {}:", frame);
+                    println!("  < {}", format_frame(l));
+                    println!("  > {}", format_frame(r));
+                }
+            }
+            if differences > limit {
+                println!("... and {} more", differences - limit);
+            }
+            if left_frames.len() != right_frames.len() {
+                println!(
+                    "lengths differ: {} frames vs {} frames",
+                    left_frames.len(),
+                    right_frames.len()
+                );
+            }
+
+            if differences > 0 || left_frames.len() != right_frames.len() {
+                return Ok(ExitCode::from(1));
+            }
+        }
+        Command::SetExtended {
+            movie: path,
+            remove,
+            output,
+        } => {
+            let mut movie = read_movie(&path, lenient)?;
+            if remove && movie.header.rerecords() > u32::MAX as u64 {
+                eprintln!("warning: rerecord count does not fit without the extended fields");
+            }
+            set_extended(&mut movie.header, !remove);
+            write_movie(output.as_deref().unwrap_or(&path), movie)?;
+        }
+        Command::ImportBk2 { bk2, output } => {
+            let movie = M64File::read_bk2(BufReader::new(File::open(&bk2)?))?;
+            write_movie(&output, movie)?;
+        }
+        Command::ExportBk2 {
+            movie,
+            output,
+            lag_from,
+        } => {
+            let movie = read_movie(&movie, lenient)?;
+            let lag_log = match lag_from {
+                Some(path) => M64File::read_bk2_with_lag(BufReader::new(File::open(path)?))?.1,
+                None => None,
+            };
+            movie.write_bk2_with_lag(BufWriter::new(File::create(&output)?), lag_log.as_deref())?;
+        }
+        Command::Bundle { movie, output } => {
+            let bundle = MovieBundle::from_movie_path(&movie)?;
+            let mut writer = BufWriter::new(File::create(&output)?);
+            bundle.write_into(&mut writer)?;
+            writer.flush()?;
+        }
+        Command::Unbundle { bundle, output } => {
+            let bundle = MovieBundle::read_from(BufReader::new(File::open(&bundle)?))?;
+            bundle.unpack(&output)?;
+        }
+        Command::ToText { movie, output } => {
+            let movie = read_movie(&movie, lenient)?;
+            match output {
+                Some(output) => {
+                    let mut writer = BufWriter::new(File::create(&output)?);
+                    movie.write_text(&mut writer)?;
+                    writer.flush()?;
+                }
+                None => movie.write_text(&mut io::stdout().lock())?,
+            }
+        }
+        Command::FromText { text, output } => {
+            let movie = M64File::read_text(BufReader::new(File::open(&text)?))?;
+            write_movie(&output, movie)?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn read_movie(path: &Path, lenient: bool) -> Result<M64File, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    if lenient {
+        let (movie, repairs) = M64File::read_from_lenient(reader)?;
+        for repair in repairs {
+            eprintln!("{}: repaired: {}", path.display(), repair);
+        }
+        Ok(movie)
+    } else {
+        Ok(M64File::read_from(reader)?)
+    }
+}
+
+fn write_movie(path: &Path, mut movie: M64File) -> io::Result<()> {
+    movie.header.length_samples = movie.inputs.len().try_into().unwrap();
+
+    // The movie has already been read into memory, so this is safe for in-place edits.
+    let mut writer = BufWriter::new(File::create(path)?);
+    movie.write_into(&mut writer)?;
+    writer.flush()
+}
+
+/// Removes all input frames starting at `frame`, scaling the VI count down to match.
+fn truncate(movie: &mut M64File, frame: u32) -> Result<(), Box<dyn Error>> {
+    let frame_size = frame_size(&movie.header)?;
+    let old_len = movie.inputs.len();
+
+    let len = (frame as usize).saturating_mul(frame_size);
+    if len > old_len {
+        return Err(format!("movie only has {} frames", old_len / frame_size).into());
+    }
+    movie.inputs.truncate(len);
+
+    // The VIs taken by each frame aren't stored, so assume they're spread evenly.
+    if old_len > 0 {
+        let length_vis = movie.header.length_vis as u64 * len as u64 / old_len as u64;
+        movie.header.length_vis = length_vis as u32;
+    }
+    Ok(())
+}
+
+/// Adds the extended header fields if they're missing, or removes them.
+fn set_extended(header: &mut M64Header, extended: bool) {
+    match extended {
+        true if header.extended().is_none() => header.set_extended(Some(ExtendedInfo::default())),
+        true => (),
+        false => header.set_extended(None),
+    }
+}
+
+fn frame_size(header: &M64Header) -> Result<usize, Box<dyn Error>> {
+    match header.controller_flags.num_present() {
+        0 => Err("movie has no controllers".into()),
+        n => Ok(n as usize),
+    }
+}
+
+/// Applies a `key=value` assignment to a JSON object. Nested keys are separated with `.`.
+/// Values are parsed as JSON if possible, and are otherwise treated as strings.
+fn apply_set(root: &mut serde_json::Value, set: &str) -> Result<(), Box<dyn Error>> {
+    let (key, value) = set
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{}`", set))?;
+    let value =
+        serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_owned()));
+
+    let mut target = root;
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        let object = match target {
+            serde_json::Value::Null => {
+                // Allow creating optional sections, e.g. `extended.version=1`.
+                *target = serde_json::Value::Object(Default::default());
+                target.as_object_mut().unwrap()
+            }
+            serde_json::Value::Object(object) => object,
+            _ => return Err(format!("`{}` is not a section", key).into()),
+        };
+        if parts.peek().is_none() {
+            object.insert(part.to_owned(), value);
+            return Ok(());
+        }
+        target = object
+            .entry(part.to_owned())
+            .or_insert(serde_json::Value::Null);
+    }
+    unreachable!("split always yields at least one part")
+}
+
+fn format_frame(frame: &[Buttons]) -> String {
+    frame
+        .iter()
+        .map(|sample| {
+            let names: Vec<_> = sample
+                .button_bits
+                .iter_names()
+                .map(|(name, _)| name)
+                .collect();
+            format!(
+                "[{}] x={} y={}",
+                names.join(" "),
+                sample.x_axis,
+                sample.y_axis
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+mod test {
+    use m64prs_sys::{ButtonFlags, Buttons};
+    use m64prs_vcr::movie::{ControllerFlags, ExtendedInfo, M64File, M64Header};
+
+    use super::{set_extended, truncate};
+
+    fn two_player_movie(frames: u16, length_vis: u32) -> M64File {
+        let mut header = M64Header::default();
+        header.controller_flags = ControllerFlags::P1_PRESENT | ControllerFlags::P2_PRESENT;
+        header.length_vis = length_vis;
+        let inputs = (0..frames * 2)
+            .map(|i| Buttons {
+                button_bits: ButtonFlags::from_bits_retain(i),
+                x_axis: 0,
+                y_axis: 0,
+            })
+            .collect();
+        M64File { header, inputs }
+    }
+
+    #[test]
+    fn truncate_scales_vi_count() {
+        let mut movie = two_player_movie(10, 20);
+        truncate(&mut movie, 4).unwrap();
+        assert_eq!(movie.inputs, two_player_movie(4, 0).inputs);
+        assert_eq!(movie.header.length_vis, 8);
+
+        truncate(&mut movie, 0).unwrap();
+        assert!(movie.inputs.is_empty());
+        assert_eq!(movie.header.length_vis, 0);
+    }
+
+    #[test]
+    fn truncate_rejects_frame_past_end() {
+        let mut movie = two_player_movie(3, 3);
+        assert!(truncate(&mut movie, 4).is_err());
+        assert_eq!(movie.inputs.len(), 6);
+        assert_eq!(movie.header.length_vis, 3);
+    }
+
+    #[test]
+    fn set_extended_keeps_existing_fields() {
+        let mut header = M64Header::default();
+        set_extended(&mut header, true);
+        assert_eq!(header.extended(), Some(ExtendedInfo::default()));
+
+        let info = ExtendedInfo {
+            authorship_tag: *b"TEST",
+            ..Default::default()
+        };
+        header.set_extended(Some(info));
+        set_extended(&mut header, true);
+        assert_eq!(header.extended(), Some(info));
+
+        header.set_rerecords(5);
+        set_extended(&mut header, false);
+        assert_eq!(header.extended(), None);
+        assert_eq!(header.rerecords(), 5);
+    }
+}
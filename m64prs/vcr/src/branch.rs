@@ -0,0 +1,280 @@
+//! Named branches of a movie, used to keep alternative versions of the same run.
+//!
+//! Each branch holds a copy of the movie's inputs, the position it was taken at, and a
+//! savestate for that position. Loading a branch restores its savestate, which brings its
+//! inputs along with it.
+//!
+//! Branches for an .m64 movie are stored next to it (`<movie>.branches`), as a `M64B` magic
+//! followed by the format version and the branch count. Each branch is stored as its name
+//! (length-prefixed UTF-8), sample index, VI count, inputs (count-prefixed) and savestate
+//! (length-prefixed). All integers are little-endian `u32`s.
+
+use std::{
+    ffi::OsString,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use m64prs_sys::Buttons;
+
+use crate::io_util::{invalid_data, read_u32};
+
+const MAGIC: [u8; 4] = *b"M64B";
+const VERSION: u32 = 1;
+
+/// An alternative version of a movie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// Name shown to the user.
+    pub name: String,
+    /// Index of the next input sample when the branch was taken.
+    pub index: u32,
+    /// VI count when the branch was taken.
+    pub vi_count: u32,
+    /// The movie's inputs when the branch was taken.
+    pub inputs: Vec<Buttons>,
+    /// Savestate taken at the branch's position.
+    pub savestate: Vec<u8>,
+}
+
+/// Differences between a branch and another set of inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BranchComparison {
+    /// Index of the first sample that differs, or `None` if the inputs are identical.
+    pub first_difference: Option<u32>,
+    /// Number of samples present in both that differ.
+    pub changed_samples: u32,
+    /// Number of samples in the branch.
+    pub branch_length: u32,
+    /// Number of samples in the inputs compared against.
+    pub other_length: u32,
+}
+
+impl Branch {
+    /// Compares the branch's inputs against `inputs`.
+    pub fn compare(&self, inputs: &[Buttons]) -> BranchComparison {
+        let mut first_difference = None;
+        let mut changed_samples = 0u32;
+        for (index, (a, b)) in self.inputs.iter().zip(inputs).enumerate() {
+            if a != b {
+                first_difference.get_or_insert(index as u32);
+                changed_samples += 1;
+            }
+        }
+        let common_len = self.inputs.len().min(inputs.len());
+        if self.inputs.len() != inputs.len() {
+            first_difference.get_or_insert(common_len as u32);
+        }
+
+        BranchComparison {
+            first_difference,
+            changed_samples,
+            branch_length: self.inputs.len() as u32,
+            other_length: inputs.len() as u32,
+        }
+    }
+}
+
+/// The branches of a movie.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BranchSet {
+    branches: Vec<Branch>,
+}
+
+impl BranchSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the path of the branch file for the movie at `movie_path`.
+    pub fn path_for(movie_path: &Path) -> PathBuf {
+        let mut path = OsString::from(movie_path);
+        path.push(".branches");
+        path.into()
+    }
+
+    /// Returns the number of branches.
+    pub fn len(&self) -> usize {
+        self.branches.len()
+    }
+
+    /// Returns true if there are no branches.
+    pub fn is_empty(&self) -> bool {
+        self.branches.is_empty()
+    }
+
+    /// Returns the branch at position `index`.
+    pub fn get(&self, index: usize) -> Option<&Branch> {
+        self.branches.get(index)
+    }
+
+    /// Iterates over the branches, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &Branch> {
+        self.branches.iter()
+    }
+
+    /// Adds a branch, returning its position.
+    pub fn push(&mut self, branch: Branch) -> usize {
+        self.branches.push(branch);
+        self.branches.len() - 1
+    }
+
+    /// Replaces the branch at position `index`, returning the old one.
+    pub fn replace(&mut self, index: usize, branch: Branch) -> Option<Branch> {
+        self.branches
+            .get_mut(index)
+            .map(|slot| std::mem::replace(slot, branch))
+    }
+
+    /// Removes the branch at position `index`.
+    pub fn remove(&mut self, index: usize) -> Option<Branch> {
+        (index < self.branches.len()).then(|| self.branches.remove(index))
+    }
+
+    /// Reads a branch file.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not a branch file"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data("unsupported branch file version"));
+        }
+
+        let count = read_u32(&mut reader)?;
+        let branches = (0..count)
+            .map(|_| {
+                let name = String::from_utf8(read_bytes(&mut reader)?)
+                    .map_err(|_| invalid_data("branch name is not valid UTF-8"))?;
+                let index = read_u32(&mut reader)?;
+                let vi_count = read_u32(&mut reader)?;
+                let input_count = read_u32(&mut reader)?;
+                let inputs = (0..input_count)
+                    .map(|_| read_u32(&mut reader).map(Buttons::from))
+                    .collect::<io::Result<_>>()?;
+                let savestate = read_bytes(&mut reader)?;
+                Ok(Branch {
+                    name,
+                    index,
+                    vi_count,
+                    inputs,
+                    savestate,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self { branches })
+    }
+
+    /// Writes the branch file.
+    pub fn write_into<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        writer.write_all(&(self.branches.len() as u32).to_le_bytes())?;
+        for branch in &self.branches {
+            write_bytes(writer, branch.name.as_bytes())?;
+            writer.write_all(&branch.index.to_le_bytes())?;
+            writer.write_all(&branch.vi_count.to_le_bytes())?;
+            writer.write_all(&(branch.inputs.len() as u32).to_le_bytes())?;
+            for sample in &branch.inputs {
+                writer.write_all(&u32::from(*sample).to_le_bytes())?;
+            }
+            write_bytes(writer, &branch.savestate)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_u32(reader)? as u64;
+    let mut data = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+fn write_bytes<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.write_all(data)
+}
+
+#[cfg(test)]
+mod test {
+    use m64prs_sys::{ButtonFlags, Buttons};
+
+    use super::{Branch, BranchSet};
+
+    fn sample(bits: u16) -> Buttons {
+        Buttons {
+            button_bits: ButtonFlags::from_bits_retain(bits),
+            x_axis: 0,
+            y_axis: 0,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut set = BranchSet::new();
+        set.push(Branch {
+            name: "first ✓".to_owned(),
+            index: 2,
+            vi_count: 5,
+            inputs: vec![sample(1), sample(2), sample(3)],
+            savestate: vec![0xAB; 17],
+        });
+        set.push(Branch {
+            name: String::new(),
+            index: 0,
+            vi_count: 0,
+            inputs: Vec::new(),
+            savestate: Vec::new(),
+        });
+
+        let mut data = Vec::new();
+        set.write_into(&mut data).unwrap();
+        let parsed = BranchSet::read_from(&data[..]).unwrap();
+
+        assert_eq!(parsed, set);
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let mut set = BranchSet::new();
+        set.push(Branch {
+            name: "branch".to_owned(),
+            index: 0,
+            vi_count: 0,
+            inputs: vec![sample(1)],
+            savestate: vec![1, 2, 3, 4],
+        });
+        let mut data = Vec::new();
+        set.write_into(&mut data).unwrap();
+        data.pop();
+
+        assert!(BranchSet::read_from(&data[..]).is_err());
+    }
+
+    #[test]
+    fn compare() {
+        let branch = Branch {
+            name: "branch".to_owned(),
+            index: 0,
+            vi_count: 0,
+            inputs: vec![sample(1), sample(2), sample(3)],
+            savestate: Vec::new(),
+        };
+        let comparison = branch.compare(&[sample(1), sample(4), sample(3), sample(5)]);
+
+        assert_eq!(comparison.first_difference, Some(1));
+        assert_eq!(comparison.changed_samples, 1);
+        assert_eq!(comparison.branch_length, 3);
+        assert_eq!(comparison.other_length, 4);
+        assert_eq!(branch.compare(&branch.inputs).first_difference, None);
+    }
+}
@@ -0,0 +1,314 @@
+//! Single-file movie bundles (`.m64z`).
+//!
+//! A bundle is a zip archive holding the movie as `movie.m64`, together with the files needed
+//! to start it: the start savestate as `start.st` and save memory dumps as `save.eep`,
+//! `save.sra`, `save.fla` and `save.mpk`. RAM checksums, if recorded, are stored as
+//! `checksums.bin`, and branches as `branches.bin`. `metadata.json` records the bundle format version and the program that
+//! wrote it.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    io::{self, Read, Seek, Write},
+    path::Path,
+};
+
+use m64prs_core::rom::SaveRamPaths;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zip::{result::ZipError, write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    branch::BranchSet,
+    checksum::ChecksumLog,
+    movie::{error::MovieError, M64File, M64Header, MovieRepair, StartType},
+};
+
+/// File extension used for movie bundles.
+pub const BUNDLE_EXTENSION: &str = "m64z";
+
+const MOVIE_FILE: &str = "movie.m64";
+const SAVESTATE_FILE: &str = "start.st";
+const CHECKSUMS_FILE: &str = "checksums.bin";
+const BRANCHES_FILE: &str = "branches.bin";
+const METADATA_FILE: &str = "metadata.json";
+const SAVE_RAM_PREFIX: &str = "save";
+
+/// Errors that can occur while reading a movie bundle.
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error("bundle does not contain a movie")]
+    MissingMovie,
+    #[error("unsupported bundle version {0}")]
+    UnsupportedVersion(u32),
+    #[error("invalid movie in bundle")]
+    Movie(#[from] MovieError),
+    #[error("invalid bundle metadata")]
+    Metadata(#[from] serde_json::Error),
+    #[error("failed to read bundle archive")]
+    Zip(#[from] ZipError),
+    #[error("I/O error while reading bundle")]
+    Io(#[from] io::Error),
+}
+
+/// Information about how a bundle was written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleMetadata {
+    /// Version of the bundle format.
+    pub version: u32,
+    /// Name and version of the program that wrote the bundle.
+    pub generator: String,
+}
+
+impl Default for BundleMetadata {
+    fn default() -> Self {
+        Self {
+            version: MovieBundle::VERSION,
+            generator: concat!("m64prs-vcr ", env!("CARGO_PKG_VERSION")).to_owned(),
+        }
+    }
+}
+
+/// Files stored in a bundle alongside the movie.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleFiles {
+    /// The savestate the movie starts from.
+    pub savestate: Option<Vec<u8>>,
+    /// Save memory dumps, in the same order as [`SaveRamPaths::EXTENSIONS`].
+    pub save_ram: [Option<Vec<u8>>; 4],
+    /// RAM checksums recorded with the movie.
+    pub checksums: Option<ChecksumLog>,
+    /// Branches of the movie.
+    pub branches: Option<BranchSet>,
+}
+
+/// A movie packaged together with the files needed to play it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovieBundle {
+    pub movie: M64File,
+    pub files: BundleFiles,
+    pub metadata: BundleMetadata,
+}
+
+impl MovieBundle {
+    /// The latest bundle format version.
+    pub const VERSION: u32 = 1;
+
+    /// Creates a bundle holding only a movie.
+    pub fn new(movie: M64File) -> Self {
+        Self {
+            movie,
+            files: BundleFiles::default(),
+            metadata: BundleMetadata::default(),
+        }
+    }
+
+    /// Checks if a path refers to a movie bundle, based on its extension.
+    pub fn is_bundle_path(path: &Path) -> bool {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(BUNDLE_EXTENSION))
+    }
+
+    /// Reads a bundle, rejecting any movie that doesn't match its header.
+    pub fn read_from<R: Read + Seek>(reader: R) -> Result<Self, BundleError> {
+        Self::parse(reader, false).map(|(bundle, _)| bundle)
+    }
+
+    /// Reads a bundle, repairing the movie if needed.
+    /// See [`M64File::read_from_lenient`] for details.
+    pub fn read_from_lenient<R: Read + Seek>(
+        reader: R,
+    ) -> Result<(Self, Vec<MovieRepair>), BundleError> {
+        Self::parse(reader, true)
+    }
+
+    /// Reads only the movie header from a bundle.
+    pub fn read_header<R: Read + Seek>(reader: R) -> Result<M64Header, BundleError> {
+        let mut archive = ZipArchive::new(reader)?;
+        let mut file = archive.by_name(MOVIE_FILE).map_err(missing_movie)?;
+        Ok(M64Header::read(&mut file)?)
+    }
+
+    fn parse<R: Read + Seek>(
+        reader: R,
+        lenient: bool,
+    ) -> Result<(Self, Vec<MovieRepair>), BundleError> {
+        let mut archive = ZipArchive::new(reader)?;
+
+        let metadata: BundleMetadata = match read_entry(&mut archive, METADATA_FILE)? {
+            Some(data) => serde_json::from_slice(&data)?,
+            None => BundleMetadata::default(),
+        };
+        if metadata.version > Self::VERSION {
+            return Err(BundleError::UnsupportedVersion(metadata.version));
+        }
+
+        let movie_data = read_entry(&mut archive, MOVIE_FILE)?.ok_or(BundleError::MissingMovie)?;
+        let (movie, repairs) = match lenient {
+            true => M64File::read_from_lenient(&movie_data[..])?,
+            false => (M64File::read_from(&movie_data[..])?, Vec::new()),
+        };
+
+        let mut files = BundleFiles {
+            savestate: read_entry(&mut archive, SAVESTATE_FILE)?,
+            ..Default::default()
+        };
+        for (dump, ext) in files.save_ram.iter_mut().zip(SaveRamPaths::EXTENSIONS) {
+            *dump = read_entry(&mut archive, &format!("{}.{}", SAVE_RAM_PREFIX, ext))?;
+        }
+        if let Some(data) = read_entry(&mut archive, CHECKSUMS_FILE)? {
+            files.checksums = Some(ChecksumLog::read_from(&data[..])?);
+        }
+        if let Some(data) = read_entry(&mut archive, BRANCHES_FILE)? {
+            files.branches = Some(BranchSet::read_from(&data[..])?);
+        }
+
+        Ok((
+            Self {
+                movie,
+                files,
+                metadata,
+            },
+            repairs,
+        ))
+    }
+
+    /// Writes the bundle to a file.
+    pub fn write_into<W: Write + Seek>(self, writer: &mut W) -> io::Result<()> {
+        let Self {
+            movie,
+            files,
+            metadata,
+        } = self;
+        let mut archive = ZipWriter::new(writer);
+        let options = SimpleFileOptions::default();
+
+        archive.start_file(METADATA_FILE, options)?;
+        serde_json::to_writer_pretty(&mut archive, &metadata)?;
+
+        archive.start_file(MOVIE_FILE, options)?;
+        movie.write_into(&mut archive)?;
+
+        if let Some(savestate) = &files.savestate {
+            // Savestates are already compressed.
+            archive.start_file(
+                SAVESTATE_FILE,
+                options.compression_method(zip::CompressionMethod::Stored),
+            )?;
+            archive.write_all(savestate)?;
+        }
+        for (dump, ext) in files.save_ram.iter().zip(SaveRamPaths::EXTENSIONS) {
+            if let Some(dump) = dump {
+                archive.start_file(format!("{}.{}", SAVE_RAM_PREFIX, ext), options)?;
+                archive.write_all(dump)?;
+            }
+        }
+        if let Some(checksums) = &files.checksums {
+            archive.start_file(CHECKSUMS_FILE, options)?;
+            checksums.write_into(&mut archive)?;
+        }
+        if let Some(branches) = &files.branches {
+            // Branches are mostly savestates, which are already compressed.
+            archive.start_file(
+                BRANCHES_FILE,
+                options.compression_method(zip::CompressionMethod::Stored),
+            )?;
+            branches.write_into(&mut archive)?;
+        }
+
+        archive.finish()?;
+        Ok(())
+    }
+
+    /// Packs an .m64 file and the files it starts from into a bundle.
+    pub fn from_movie_path(movie_path: &Path) -> Result<Self, BundleError> {
+        let movie = M64File::read_from(io::BufReader::new(fs::File::open(movie_path)?))?;
+        let mut files = BundleFiles::default();
+        match movie.header.start_flags {
+            StartType::FROM_SNAPSHOT => {
+                files.savestate = Some(fs::read(crate::find_savestate(movie_path)?)?);
+            }
+            StartType::FROM_EEPROM => {
+                for (dump, ext) in files.save_ram.iter_mut().zip(SaveRamPaths::EXTENSIONS) {
+                    let path = crate::save_ram_dump_path(movie_path, ext)?;
+                    if path.is_file() {
+                        *dump = Some(fs::read(path)?);
+                    }
+                }
+            }
+            _ => (),
+        }
+        let checksums_path = ChecksumLog::path_for(movie_path);
+        if checksums_path.is_file() {
+            files.checksums = Some(ChecksumLog::read_from(io::BufReader::new(fs::File::open(
+                checksums_path,
+            )?))?);
+        }
+        let branches_path = BranchSet::path_for(movie_path);
+        if branches_path.is_file() {
+            files.branches = Some(BranchSet::read_from(io::BufReader::new(fs::File::open(
+                branches_path,
+            )?))?);
+        }
+
+        Ok(Self {
+            movie,
+            files,
+            metadata: BundleMetadata::default(),
+        })
+    }
+
+    /// Unpacks the bundle into an .m64 file, with the other files stored next to it.
+    /// This is the reverse of [`MovieBundle::from_movie_path`].
+    pub fn unpack(self, movie_path: &Path) -> io::Result<()> {
+        let Self { movie, files, .. } = self;
+
+        let mut writer = io::BufWriter::new(fs::File::create(movie_path)?);
+        movie.write_into(&mut writer)?;
+        writer.flush()?;
+
+        if let Some(savestate) = &files.savestate {
+            fs::write(movie_path.with_extension("st"), savestate)?;
+        }
+        for (dump, ext) in files.save_ram.iter().zip(SaveRamPaths::EXTENSIONS) {
+            if let Some(dump) = dump {
+                fs::write(crate::save_ram_dump_path(movie_path, ext)?, dump)?;
+            }
+        }
+        if let Some(checksums) = &files.checksums {
+            let mut writer =
+                io::BufWriter::new(fs::File::create(ChecksumLog::path_for(movie_path))?);
+            checksums.write_into(&mut writer)?;
+            writer.flush()?;
+        }
+        if let Some(branches) = &files.branches {
+            let mut writer = io::BufWriter::new(fs::File::create(BranchSet::path_for(movie_path))?);
+            branches.write_into(&mut writer)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a file from the archive, returning `None` if it doesn't exist.
+fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<Vec<u8>>, BundleError> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(Some(data))
+}
+
+fn missing_movie(err: ZipError) -> BundleError {
+    match err {
+        ZipError::FileNotFound => BundleError::MissingMovie,
+        err => err.into(),
+    }
+}
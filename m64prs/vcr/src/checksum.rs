@@ -0,0 +1,220 @@
+//! RAM checksums recorded alongside a movie, used to find where playback desyncs.
+//!
+//! While recording, a CRC32 of RDRAM (or selected regions of it) is taken every few VIs.
+//! On playback, the same checksums are taken again, and the first mismatch marks the point
+//! where emulation diverged from the recording.
+//!
+//! Checksums for an .m64 movie are stored next to it (`<movie>.checksums`), as a `M64C` magic
+//! followed by little-endian `u32`s: the format version, the interval, the region count,
+//! each region's start and end, the entry count, and each entry's VI count and checksum.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    io::{self, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::io_util::{invalid_data, read_u32};
+
+const MAGIC: [u8; 4] = *b"M64C";
+const VERSION: u32 = 1;
+
+/// Controls how often checksums are taken, and which memory they cover.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChecksumSettings {
+    /// Number of VIs between checksums. 0 disables checksums.
+    pub interval: u32,
+    /// Byte ranges of RDRAM to checksum. If empty, all of RDRAM is used.
+    pub regions: Vec<Range<u32>>,
+}
+
+/// The first point where playback diverged from the recorded checksums.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Desync {
+    /// VI count at which the mismatch was found.
+    pub vi: u32,
+    /// Index of the next input sample at the time of the mismatch.
+    pub index: u32,
+    /// Checksum stored in the movie.
+    pub expected: u32,
+    /// Checksum of the current emulator state.
+    pub actual: u32,
+}
+
+/// Checksums of emulated memory, keyed by VI count.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChecksumLog {
+    settings: ChecksumSettings,
+    entries: BTreeMap<u32, u32>,
+}
+
+impl ChecksumLog {
+    /// Creates an empty log.
+    pub fn new(settings: ChecksumSettings) -> Self {
+        Self {
+            settings,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the path of the checksum file for the movie at `movie_path`.
+    pub fn path_for(movie_path: &Path) -> PathBuf {
+        let mut path = OsString::from(movie_path);
+        path.push(".checksums");
+        path.into()
+    }
+
+    /// Returns the settings this log was recorded with.
+    pub fn settings(&self) -> &ChecksumSettings {
+        &self.settings
+    }
+
+    /// Returns the number of checksums in the log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the log holds no checksums.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if a checksum should be recorded at VI `vi`.
+    pub fn is_due(&self, vi: u32) -> bool {
+        vi.checked_rem(self.settings.interval) == Some(0)
+    }
+
+    /// Returns the checksum recorded at VI `vi`, if any.
+    pub fn get(&self, vi: u32) -> Option<u32> {
+        self.entries.get(&vi).copied()
+    }
+
+    /// Computes the checksum of `rdram` over the configured regions.
+    pub fn compute(&self, rdram: &[u8]) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        if self.settings.regions.is_empty() {
+            hasher.update(rdram);
+        }
+        for region in &self.settings.regions {
+            let end = (region.end as usize).min(rdram.len());
+            let start = (region.start as usize).min(end);
+            hasher.update(&rdram[start..end]);
+        }
+        hasher.finalize()
+    }
+
+    /// Records a checksum at VI `vi`. Checksums after `vi` are discarded, since they
+    /// belong to the part of the movie being overwritten.
+    pub fn record(&mut self, vi: u32, checksum: u32) {
+        self.truncate(vi);
+        self.entries.insert(vi, checksum);
+    }
+
+    /// Discards all checksums at or after VI `vi`.
+    pub fn truncate(&mut self, vi: u32) {
+        self.entries.split_off(&vi);
+    }
+
+    /// Reads a checksum log.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not a checksum file"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data("unsupported checksum file version"));
+        }
+
+        let interval = read_u32(&mut reader)?;
+        let region_count = read_u32(&mut reader)?;
+        let regions = (0..region_count)
+            .map(|_| Ok(read_u32(&mut reader)?..read_u32(&mut reader)?))
+            .collect::<io::Result<_>>()?;
+
+        let entry_count = read_u32(&mut reader)?;
+        let entries = (0..entry_count)
+            .map(|_| Ok((read_u32(&mut reader)?, read_u32(&mut reader)?)))
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            settings: ChecksumSettings { interval, regions },
+            entries,
+        })
+    }
+
+    /// Writes the checksum log.
+    pub fn write_into<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&self.settings.interval.to_le_bytes())?;
+
+        writer.write_all(&(self.settings.regions.len() as u32).to_le_bytes())?;
+        for region in &self.settings.regions {
+            writer.write_all(&region.start.to_le_bytes())?;
+            writer.write_all(&region.end.to_le_bytes())?;
+        }
+
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for (vi, checksum) in &self.entries {
+            writer.write_all(&vi.to_le_bytes())?;
+            writer.write_all(&checksum.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChecksumLog, ChecksumSettings};
+
+    #[test]
+    fn round_trip() {
+        let mut log = ChecksumLog::new(ChecksumSettings {
+            interval: 30,
+            regions: vec![0x100..0x200, 0x33B400..0x33B500],
+        });
+        log.record(0, 0xDEADBEEF);
+        log.record(30, 0x12345678);
+        log.record(60, 0);
+
+        let mut data = Vec::new();
+        log.write_into(&mut data).unwrap();
+        let parsed = ChecksumLog::read_from(&data[..]).unwrap();
+
+        assert_eq!(parsed, log);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        assert!(ChecksumLog::read_from(&b"M64X\x01\0\0\0"[..]).is_err());
+    }
+
+    #[test]
+    fn record_discards_later_checksums() {
+        let mut log = ChecksumLog::new(ChecksumSettings {
+            interval: 10,
+            regions: Vec::new(),
+        });
+        log.record(10, 1);
+        log.record(20, 2);
+        log.record(10, 3);
+
+        assert_eq!(log.get(10), Some(3));
+        assert_eq!(log.get(20), None);
+        assert_eq!(log.len(), 1);
+    }
+
+    #[test]
+    fn compute_clamps_regions() {
+        let rdram = [1u8, 2, 3, 4];
+        let log = ChecksumLog::new(ChecksumSettings {
+            interval: 1,
+            regions: vec![0..1, 2..100],
+        });
+        assert_eq!(log.compute(&rdram), crc32fast::hash(&[1, 3, 4]));
+    }
+}
@@ -0,0 +1,189 @@
+//! Checks that a movie matches the ROM and plugins it is played back with.
+//!
+//! Fields left empty (or zero) in a movie's header are not checked, since movies converted
+//! from other formats or recorded by older versions may not fill them in.
+
+use std::fmt::{self, Display};
+
+use crate::movie::M64Header;
+
+/// Country codes of PAL ROMs, which run at 50 VIs per second.
+const PAL_COUNTRY_CODES: [u8; 8] = [b'D', b'F', b'I', b'P', b'S', b'U', b'X', b'Y'];
+
+/// The ROM and plugins currently in use.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environment {
+    /// Internal name of the ROM, from its header.
+    pub rom_name: String,
+    /// The ROM's first CRC, from its header.
+    pub rom_crc: u32,
+    /// The ROM's country code, from its header.
+    pub rom_cc: u16,
+    pub graphics_plugin: String,
+    pub audio_plugin: String,
+    pub input_plugin: String,
+    pub rsp_plugin: String,
+}
+
+/// One of the four plugins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginKind {
+    Graphics,
+    Audio,
+    Input,
+    Rsp,
+}
+
+/// A difference between a movie's header and the current [`Environment`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    RomName {
+        movie: String,
+        current: String,
+    },
+    RomCrc {
+        movie: u32,
+        current: u32,
+    },
+    RomCountry {
+        movie: u16,
+        current: u16,
+    },
+    Plugin {
+        kind: PluginKind,
+        movie: String,
+        current: String,
+    },
+}
+
+impl Display for PluginKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PluginKind::Graphics => "graphics",
+            PluginKind::Audio => "audio",
+            PluginKind::Input => "input",
+            PluginKind::Rsp => "RSP",
+        })
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::RomName { movie, current } => write!(
+                f,
+                "movie was recorded on ROM \"{}\", but \"{}\" is loaded",
+                movie, current
+            ),
+            Mismatch::RomCrc { movie, current } => write!(
+                f,
+                "movie's ROM CRC is {:08X}, but the loaded ROM's is {:08X}",
+                movie, current
+            ),
+            Mismatch::RomCountry { movie, current } => write!(
+                f,
+                "movie's ROM country code is {:#06X}, but the loaded ROM's is {:#06X}",
+                movie, current
+            ),
+            Mismatch::Plugin {
+                kind,
+                movie,
+                current,
+            } => write!(
+                f,
+                "movie was recorded with {} plugin \"{}\", but \"{}\" is in use",
+                kind, movie, current
+            ),
+        }
+    }
+}
+
+impl Environment {
+    /// Returns the number of VIs per second for a ROM with the given country code.
+    pub fn vis_per_second(country_code: u16) -> u8 {
+        match PAL_COUNTRY_CODES.contains(&((country_code & 0xFF) as u8)) {
+            true => 50,
+            false => 60,
+        }
+    }
+
+    /// Fills in the ROM and plugin fields of a new movie's header, along with the VI rate
+    /// for the ROM's region. Names that don't fit the header are clipped, and names that
+    /// aren't ASCII are left empty.
+    pub fn apply_to(&self, header: &mut M64Header) {
+        header.rom_crc = self.rom_crc;
+        header.rom_cc = self.rom_cc;
+        header.vis_per_second = Self::vis_per_second(self.rom_cc);
+
+        let _ = header.rom_name.write_clipped(&self.rom_name);
+        let _ = header.graphics_plugin.write_clipped(&self.graphics_plugin);
+        let _ = header.audio_plugin.write_clipped(&self.audio_plugin);
+        let _ = header.input_plugin.write_clipped(&self.input_plugin);
+        let _ = header.rsp_plugin.write_clipped(&self.rsp_plugin);
+    }
+
+    /// Compares a movie's header against this environment.
+    pub fn check(&self, header: &M64Header) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+
+        if let Ok(movie) = header.rom_name.try_read() {
+            let movie = movie.trim_end();
+            if !movie.is_empty() && movie != self.rom_name.trim_end() {
+                mismatches.push(Mismatch::RomName {
+                    movie: movie.to_owned(),
+                    current: self.rom_name.clone(),
+                });
+            }
+        }
+        if header.rom_crc != 0 && header.rom_crc != self.rom_crc {
+            mismatches.push(Mismatch::RomCrc {
+                movie: header.rom_crc,
+                current: self.rom_crc,
+            });
+        }
+        if header.rom_cc != 0 && header.rom_cc != self.rom_cc {
+            mismatches.push(Mismatch::RomCountry {
+                movie: header.rom_cc,
+                current: self.rom_cc,
+            });
+        }
+
+        let plugins = [
+            (
+                PluginKind::Graphics,
+                header.graphics_plugin.try_read(),
+                &self.graphics_plugin,
+            ),
+            (
+                PluginKind::Audio,
+                header.audio_plugin.try_read(),
+                &self.audio_plugin,
+            ),
+            (
+                PluginKind::Input,
+                header.input_plugin.try_read(),
+                &self.input_plugin,
+            ),
+            (
+                PluginKind::Rsp,
+                header.rsp_plugin.try_read(),
+                &self.rsp_plugin,
+            ),
+        ];
+        for (kind, movie, current) in plugins {
+            let Ok(movie) = movie else {
+                continue;
+            };
+            // Long names are clipped to fit the header.
+            if !movie.is_empty() && !current.starts_with(movie) {
+                mismatches.push(Mismatch::Plugin {
+                    kind,
+                    movie: movie.to_owned(),
+                    current: current.clone(),
+                });
+            }
+        }
+
+        mismatches
+    }
+}
@@ -15,10 +15,48 @@ pub mod v1 {
     pub const VERSION_CODE: u32 = 1;
 }
 
+pub mod v2 {
+    use m64prs_sys::Buttons;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct MovieFreeze {
+        pub uid: u32,
+        pub index: u32,
+        pub vi_count: u32,
+        /// Rerecord count of the movie at the time the state was saved.
+        pub rerecord_count: u32,
+        pub inputs: Vec<Buttons>,
+    }
+
+    pub const VERSION_CODE: u32 = 2;
+}
+
+pub mod v3 {
+    use m64prs_sys::Buttons;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct MovieFreeze {
+        pub uid: u32,
+        pub index: u32,
+        pub vi_count: u32,
+        /// Rerecord count of the movie at the time the state was saved.
+        pub rerecord_count: u32,
+        /// Number of lag frames since the start of the movie.
+        pub lag_count: u32,
+        pub inputs: Vec<Buttons>,
+    }
+
+    pub const VERSION_CODE: u32 = 3;
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum MovieFreeze {
     V1(v1::MovieFreeze),
+    V2(v2::MovieFreeze),
+    V3(v3::MovieFreeze),
 }
 
 impl From<v1::MovieFreeze> for MovieFreeze {
@@ -26,3 +64,15 @@ impl From<v1::MovieFreeze> for MovieFreeze {
         Self::V1(value)
     }
 }
+
+impl From<v2::MovieFreeze> for MovieFreeze {
+    fn from(value: v2::MovieFreeze) -> Self {
+        Self::V2(value)
+    }
+}
+
+impl From<v3::MovieFreeze> for MovieFreeze {
+    fn from(value: v3::MovieFreeze) -> Self {
+        Self::V3(value)
+    }
+}
@@ -0,0 +1,189 @@
+//! Savestates captured automatically while a movie runs, used to seek backwards.
+//!
+//! Captures are keyed by the movie's sample index at the time they were taken. A capture
+//! only stays valid while the inputs before its index are unchanged, so editing or
+//! rerecording the movie discards every capture after the edit.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Controls how often captures are taken, and how much memory they may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GreenzoneSettings {
+    /// Number of input frames between captures. 0 disables the greenzone.
+    pub interval: u32,
+    /// Maximum total size of all captures, in bytes.
+    pub max_bytes: usize,
+}
+
+/// A position in a movie to seek to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekTarget {
+    /// Sample index of the next input to read.
+    Index(u32),
+    /// Number of VIs since the start of the movie.
+    Vi(u32),
+}
+
+impl SeekTarget {
+    /// Returns the target's sample index or VI count.
+    pub fn value(self) -> u32 {
+        match self {
+            SeekTarget::Index(value) | SeekTarget::Vi(value) => value,
+        }
+    }
+}
+
+impl Default for GreenzoneSettings {
+    fn default() -> Self {
+        Self {
+            interval: 0,
+            max_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Ring of savestates taken at regular points in a movie.
+#[derive(Debug, Default)]
+pub struct Greenzone {
+    settings: GreenzoneSettings,
+    captures: BTreeMap<u32, Capture>,
+    total_bytes: usize,
+    capturing: bool,
+    saved_at: Option<(u32, u32)>,
+}
+
+#[derive(Debug)]
+struct Capture {
+    vi_count: u32,
+    data: Arc<[u8]>,
+}
+
+impl Greenzone {
+    /// Creates an empty greenzone.
+    pub fn new(settings: GreenzoneSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the greenzone's settings.
+    pub fn settings(&self) -> GreenzoneSettings {
+        self.settings
+    }
+
+    /// Returns true if the greenzone is enabled.
+    pub fn enabled(&self) -> bool {
+        self.settings.interval != 0
+    }
+
+    /// Returns the number of captures held.
+    pub fn len(&self) -> usize {
+        self.captures.len()
+    }
+
+    /// Returns true if there are no captures.
+    pub fn is_empty(&self) -> bool {
+        self.captures.is_empty()
+    }
+
+    /// Returns the total size of all captures, in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Returns true if a capture exists at sample index `index`.
+    pub fn contains(&self, index: u32) -> bool {
+        self.captures.contains_key(&index)
+    }
+
+    /// Returns true if a capture is in progress.
+    pub fn capturing(&self) -> bool {
+        self.capturing
+    }
+
+    /// Returns the latest capture at or before `target`, along with its sample index.
+    pub fn nearest(&self, target: SeekTarget) -> Option<(u32, Arc<[u8]>)> {
+        let capture = match target {
+            SeekTarget::Index(index) => self.captures.range(..=index).next_back(),
+            // VI counts increase along with sample indices.
+            SeekTarget::Vi(vi) => self
+                .captures
+                .iter()
+                .rev()
+                .find(|(_, capture)| capture.vi_count <= vi),
+        };
+        capture.map(|(index, capture)| (*index, Arc::clone(&capture.data)))
+    }
+
+    /// Starts a capture. Savestates saved until [`Greenzone::finish_capture`] should be
+    /// reported with [`Greenzone::note_save`].
+    /// # Return value
+    /// False if a capture is already in progress.
+    pub fn begin_capture(&mut self) -> bool {
+        if self.capturing {
+            return false;
+        }
+        self.capturing = true;
+        self.saved_at = None;
+        true
+    }
+
+    /// Records the sample index and VI count at which a savestate was saved. Since the
+    /// emulator keeps running while a capture is requested, this is the only reliable
+    /// source for them.
+    pub fn note_save(&mut self, index: u32, vi_count: u32) {
+        if self.capturing {
+            self.saved_at = Some((index, vi_count));
+        }
+    }
+
+    /// Finishes a capture started with [`Greenzone::begin_capture`]. Pass `None` if the
+    /// savestate couldn't be saved.
+    pub fn finish_capture(&mut self, data: Option<Vec<u8>>) {
+        self.capturing = false;
+        if let (Some((index, vi_count)), Some(data)) = (self.saved_at.take(), data) {
+            self.insert(
+                index,
+                Capture {
+                    vi_count,
+                    data: data.into(),
+                },
+            );
+        }
+    }
+
+    /// Discards all captures after sample index `index`, because the inputs from
+    /// `index` onwards changed.
+    pub fn invalidate(&mut self, index: u32) {
+        let Some(after) = index.checked_add(1) else {
+            return;
+        };
+        for (_, capture) in self.captures.split_off(&after) {
+            self.total_bytes -= capture.data.len();
+        }
+        // A capture in progress may have been taken after the change.
+        if self.saved_at.is_some_and(|(saved, _)| saved > index) {
+            self.saved_at = None;
+        }
+    }
+
+    /// Discards all captures.
+    pub fn clear(&mut self) {
+        self.captures.clear();
+        self.total_bytes = 0;
+        self.saved_at = None;
+    }
+
+    fn insert(&mut self, index: u32, capture: Capture) {
+        self.total_bytes += capture.data.len();
+        if let Some(old) = self.captures.insert(index, capture) {
+            self.total_bytes -= old.data.len();
+        }
+        // Drop the oldest captures first; seeking that far back is the least likely.
+        while self.total_bytes > self.settings.max_bytes && self.captures.len() > 1 {
+            let (_, capture) = self.captures.pop_first().unwrap();
+            self.total_bytes -= capture.data.len();
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Undo history for changes to a movie's inputs.
+//!
+//! Each entry stores the splice that reverts a change, rather than the change itself. Applying
+//! an entry gives back the splice that reverts *it*, so the same representation serves for
+//! both undo and redo.
+
+use std::collections::VecDeque;
+
+use m64prs_sys::Buttons;
+
+/// Maximum number of changes kept in the undo history.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// A splice of the movie's inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Index of the first sample replaced.
+    pub start: u32,
+    /// Number of samples replaced, or `None` to replace every sample after `start`.
+    pub len: Option<u32>,
+    /// The samples to put in their place.
+    pub samples: Vec<Buttons>,
+}
+
+impl Change {
+    /// Applies the change to `inputs`.
+    /// # Return value
+    /// The change that reverts this one.
+    pub fn apply(self, inputs: &mut Vec<Buttons>) -> Change {
+        let start = (self.start as usize).min(inputs.len());
+        let end = match self.len {
+            Some(len) => start.saturating_add(len as usize).min(inputs.len()),
+            None => inputs.len(),
+        };
+        let removed: Vec<Buttons> = inputs
+            .splice(start..end, self.samples.iter().copied())
+            .collect();
+        Change {
+            start: start as u32,
+            len: self.len.map(|_| self.samples.len() as u32),
+            samples: removed,
+        }
+    }
+}
+
+/// Undo and redo stacks for a movie.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+}
+
+impl History {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if there is a change to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns true if there is a change to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Records a new change, given the change that reverts it. This clears the redo stack.
+    pub fn record(&mut self, revert: Change) {
+        self.redo.clear();
+        self.push_undo(revert);
+    }
+
+    /// Records that every sample from `start` on was replaced. `old_tail` holds the samples
+    /// that were there before.
+    pub fn record_tail(&mut self, start: u32, old_tail: Vec<Buttons>) {
+        self.record(Change {
+            start,
+            len: None,
+            samples: old_tail,
+        });
+    }
+
+    /// Clears the history.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<Change> {
+        self.undo.pop_back()
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<Change> {
+        self.redo.pop()
+    }
+
+    pub(crate) fn push_undo(&mut self, change: Change) {
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(change);
+    }
+
+    pub(crate) fn push_redo(&mut self, change: Change) {
+        self.redo.push(change);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use m64prs_sys::{ButtonFlags, Buttons};
+
+    use super::{Change, History, HISTORY_LIMIT};
+
+    fn samples(bits: &[u16]) -> Vec<Buttons> {
+        bits.iter()
+            .map(|&bits| Buttons {
+                button_bits: ButtonFlags::from_bits_retain(bits),
+                x_axis: 0,
+                y_axis: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn apply_returns_revert() {
+        let original = samples(&[1, 2, 3, 4]);
+        let changes = [
+            // Replace in place.
+            Change {
+                start: 1,
+                len: Some(2),
+                samples: samples(&[5, 6]),
+            },
+            // Insert.
+            Change {
+                start: 2,
+                len: Some(0),
+                samples: samples(&[7, 8, 9]),
+            },
+            // Replace the tail.
+            Change {
+                start: 3,
+                len: None,
+                samples: samples(&[10]),
+            },
+        ];
+
+        for change in changes {
+            let mut inputs = original.clone();
+            let revert = change.clone().apply(&mut inputs);
+            let redo = revert.apply(&mut inputs);
+            assert_eq!(inputs, original);
+            redo.apply(&mut inputs);
+
+            let mut expected = original.clone();
+            change.apply(&mut expected);
+            assert_eq!(inputs, expected);
+        }
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let mut inputs = samples(&[1, 2, 3]);
+        let mut history = History::new();
+        history.record(
+            Change {
+                start: 1,
+                len: Some(1),
+                samples: samples(&[4, 5]),
+            }
+            .apply(&mut inputs),
+        );
+        let edited = inputs.clone();
+
+        let revert = history.pop_undo().unwrap().apply(&mut inputs);
+        history.push_redo(revert);
+        assert_eq!(inputs, samples(&[1, 2, 3]));
+        assert!(history.can_redo());
+
+        let revert = history.pop_redo().unwrap().apply(&mut inputs);
+        history.push_undo(revert);
+        assert_eq!(inputs, edited);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn limit_drops_oldest() {
+        let mut history = History::new();
+        for start in 0..(HISTORY_LIMIT as u32 + 1) {
+            history.record(Change {
+                start,
+                len: Some(0),
+                samples: Vec::new(),
+            });
+        }
+        let mut oldest = None;
+        while let Some(change) = history.pop_undo() {
+            oldest = Some(change.start);
+        }
+        assert_eq!(oldest, Some(1));
+    }
+}
@@ -0,0 +1,15 @@
+//! Helpers shared by the binary file formats stored next to movies.
+
+use std::io::{self, Read};
+
+/// Reads a little-endian `u32`.
+pub(crate) fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Creates the error returned for malformed files.
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,278 @@
+//! Append-only journal of changes made to a movie while it is being recorded.
+//!
+//! The journal lives next to the movie (`<movie>.journal`) and is made of framed records:
+//! a one-byte tag, a little-endian `u32` payload length, the payload, and a CRC32 of everything
+//! before it. A crash can only ever damage the last record, which is discarded on recovery.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Seek, Write},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use m64prs_sys::Buttons;
+
+use crate::movie::{M64File, M64Header};
+
+const TAG_HEADER: u8 = 0;
+const TAG_TRUNCATE: u8 = 1;
+const TAG_SAMPLES: u8 = 2;
+const TAG_COUNTERS: u8 = 3;
+
+const SAMPLE_SIZE: usize = mem::size_of::<Buttons>();
+
+/// Writer for a movie's journal.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Journal {
+    /// Returns the path of the journal for the movie at `movie_path`.
+    pub fn path_for(movie_path: &Path) -> PathBuf {
+        let mut path = OsString::from(movie_path);
+        path.push(".journal");
+        path.into()
+    }
+
+    /// Creates a new journal for the movie at `movie_path`, starting from the given state.
+    /// Any existing journal is overwritten.
+    pub fn create(movie_path: &Path, header: &M64Header, inputs: &[Buttons]) -> io::Result<Self> {
+        let path = Self::path_for(movie_path);
+        let file = File::create(&path)?;
+        let mut journal = Self {
+            path,
+            writer: BufWriter::new(file),
+        };
+        journal.write_base(header, inputs)?;
+        Ok(journal)
+    }
+
+    /// Discards all records and restarts the journal from the given state. This should be
+    /// called once the movie has been saved, to keep the journal small.
+    pub fn checkpoint(&mut self, header: &M64Header, inputs: &[Buttons]) -> io::Result<()> {
+        self.writer.flush()?;
+        let file = self.writer.get_mut();
+        file.set_len(0)?;
+        file.rewind()?;
+        self.write_base(header, inputs)
+    }
+
+    /// Records that the movie was truncated to `len` samples.
+    pub fn truncate(&mut self, len: u32) -> io::Result<()> {
+        self.write_record(TAG_TRUNCATE, &len.to_le_bytes())
+    }
+
+    /// Records that samples starting at `start` were overwritten, extending the movie if needed.
+    pub fn write_samples(&mut self, start: u32, samples: &[Buttons]) -> io::Result<()> {
+        let mut payload = Vec::with_capacity(4 + samples.len() * SAMPLE_SIZE);
+        payload.extend_from_slice(&start.to_le_bytes());
+        for sample in samples {
+            payload.extend_from_slice(&u32::from(*sample).to_le_bytes());
+        }
+        self.write_record(TAG_SAMPLES, &payload)
+    }
+
+    /// Records the current VI count and rerecord count.
+    pub fn write_counters(&mut self, length_vis: u32, rerecords: u64) -> io::Result<()> {
+        let mut payload = [0u8; 12];
+        payload[0..4].copy_from_slice(&length_vis.to_le_bytes());
+        payload[4..12].copy_from_slice(&rerecords.to_le_bytes());
+        self.write_record(TAG_COUNTERS, &payload)
+    }
+
+    /// Hands all buffered records to the OS. Records that have been flushed
+    /// survive the emulator crashing.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Deletes the journal. This should be done once the movie has been closed and saved.
+    pub fn remove(self) -> io::Result<()> {
+        let Self { path, writer } = self;
+        drop(writer);
+        fs::remove_file(path)
+    }
+
+    /// Reconstructs a movie from the journal for the movie at `movie_path`, if one exists.
+    /// Records after the first damaged one are ignored.
+    pub fn recover(movie_path: &Path) -> io::Result<Option<M64File>> {
+        let mut data = Vec::new();
+        match File::open(Self::path_for(movie_path)) {
+            Ok(mut file) => file.read_to_end(&mut data)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let mut header: Option<M64Header> = None;
+        let mut inputs: Vec<Buttons> = Vec::new();
+
+        let mut rest = &data[..];
+        while let Some((tag, payload, next)) = split_record(rest) {
+            rest = next;
+            match (tag, payload.len()) {
+                (TAG_HEADER, len) if len == mem::size_of::<M64Header>() => {
+                    header = Some(M64Header::from_bytes(payload.try_into().unwrap()));
+                    inputs.clear();
+                }
+                (TAG_TRUNCATE, 4) => {
+                    let len = u32::from_le_bytes(payload.try_into().unwrap());
+                    inputs.truncate(len as usize);
+                }
+                (TAG_SAMPLES, len) if len >= 4 && (len - 4) % SAMPLE_SIZE == 0 => {
+                    let start = u32::from_le_bytes(payload[0..4].try_into().unwrap()) as usize;
+                    let samples = payload[4..]
+                        .chunks_exact(SAMPLE_SIZE)
+                        .map(|chunk| Buttons::from(u32::from_le_bytes(chunk.try_into().unwrap())));
+                    if inputs.len() < start {
+                        inputs.resize(start, Buttons::BLANK);
+                    }
+                    for (i, sample) in samples.enumerate() {
+                        match inputs.get_mut(start + i) {
+                            Some(slot) => *slot = sample,
+                            None => inputs.push(sample),
+                        }
+                    }
+                }
+                (TAG_COUNTERS, 12) => {
+                    if let Some(header) = header.as_mut() {
+                        header.length_vis = u32::from_le_bytes(payload[0..4].try_into().unwrap());
+                        header
+                            .set_rerecords(u64::from_le_bytes(payload[4..12].try_into().unwrap()));
+                    }
+                }
+                _ => {
+                    log::warn!("ignoring malformed journal record (tag {})", tag);
+                }
+            }
+        }
+        if !rest.is_empty() {
+            log::warn!("discarding {} bytes of incomplete journal data", rest.len());
+        }
+
+        Ok(header.map(|mut header| {
+            header.length_samples = inputs.len().try_into().unwrap();
+            M64File { header, inputs }
+        }))
+    }
+
+    fn write_base(&mut self, header: &M64Header, inputs: &[Buttons]) -> io::Result<()> {
+        self.write_record(TAG_HEADER, &header.clone().into_bytes())?;
+        if !inputs.is_empty() {
+            self.write_samples(0, inputs)?;
+        }
+        self.flush()
+    }
+
+    fn write_record(&mut self, tag: u8, payload: &[u8]) -> io::Result<()> {
+        let len: u32 = payload
+            .len()
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "journal record too long"))?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&[tag]);
+        hasher.update(&len.to_le_bytes());
+        hasher.update(payload);
+
+        self.writer.write_all(&[tag])?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(payload)?;
+        self.writer.write_all(&hasher.finalize().to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// Splits the first record off of `data`, returning its tag, payload, and the remaining data.
+/// Returns `None` if the record is incomplete or its checksum doesn't match.
+fn split_record(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let len = u32::from_le_bytes(rest.get(0..4)?.try_into().unwrap()) as usize;
+    let payload = rest.get(4..(4 + len))?;
+    let crc = u32::from_le_bytes(rest.get((4 + len)..(8 + len))?.try_into().unwrap());
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&data[..(5 + len)]);
+    if hasher.finalize() != crc {
+        return None;
+    }
+
+    Some((tag, payload, &rest[(8 + len)..]))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::PathBuf, process};
+
+    use m64prs_sys::{ButtonFlags, Buttons};
+
+    use super::Journal;
+    use crate::movie::M64Header;
+
+    fn sample(bits: u16) -> Buttons {
+        Buttons {
+            button_bits: ButtonFlags::from_bits_retain(bits),
+            x_axis: 0,
+            y_axis: 0,
+        }
+    }
+
+    fn movie_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("m64prs-journal-{}-{}.m64", process::id(), name))
+    }
+
+    #[test]
+    fn recover_replays_records() {
+        let path = movie_path("replay");
+        let mut header = M64Header::default();
+        header.uid = 42;
+
+        let mut journal = Journal::create(&path, &header, &[sample(1), sample(2)]).unwrap();
+        journal.write_samples(2, &[sample(3), sample(4)]).unwrap();
+        journal.truncate(3).unwrap();
+        journal.write_samples(1, &[sample(5)]).unwrap();
+        journal.write_counters(100, 7).unwrap();
+        journal.flush().unwrap();
+
+        let movie = Journal::recover(&path).unwrap().unwrap();
+        journal.remove().unwrap();
+
+        assert_eq!(movie.inputs, [sample(1), sample(5), sample(3)]);
+        assert_eq!(movie.header.uid, 42);
+        assert_eq!(movie.header.length_vis, 100);
+        assert_eq!(movie.header.length_samples, 3);
+        assert_eq!(movie.header.rerecords(), 7);
+    }
+
+    #[test]
+    fn recover_discards_truncated_tail() {
+        let path = movie_path("tail");
+        let mut journal = Journal::create(&path, &M64Header::default(), &[sample(1)]).unwrap();
+        journal.write_samples(1, &[sample(2)]).unwrap();
+        journal.write_samples(2, &[sample(3)]).unwrap();
+        journal.flush().unwrap();
+
+        // Cut the last record short, as if the emulator crashed while writing it.
+        let journal_path = Journal::path_for(&path);
+        let len = fs::metadata(&journal_path).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&journal_path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+
+        let movie = Journal::recover(&path).unwrap().unwrap();
+        journal.remove().unwrap();
+
+        assert_eq!(movie.inputs, [sample(1), sample(2)]);
+    }
+
+    #[test]
+    fn recover_without_journal() {
+        assert_eq!(Journal::recover(&movie_path("missing")).unwrap(), None);
+    }
+}
@@ -1,16 +1,33 @@
 use std::{
     error::Error,
     ffi::{c_int, OsStr},
-    fs, io,
-    path::PathBuf,
+    fs,
+    io::{self, Seek, Write},
+    mem,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
+use branch::{Branch, BranchComparison, BranchSet};
+use bundle::{BundleFiles, MovieBundle};
+use checksum::{ChecksumLog, Desync};
 use freeze::MovieFreeze;
-use m64prs_core::{error::M64PError, save::SavestateFormat, Core};
+use greenzone::{Greenzone, GreenzoneSettings, SeekTarget};
+use history::{Change, History};
+use journal::Journal;
+use m64prs_core::{error::M64PError, rom::SaveRamPaths, save::SavestateFormat, Core};
 use m64prs_sys::{Buttons, EmuState};
-use movie::{M64File, M64Header, StartType};
+use movie::{M64File, M64Header, ResetKind, StartType};
 
+pub mod branch;
+pub mod bundle;
+pub mod checksum;
+pub mod compat;
 pub mod freeze;
+pub mod greenzone;
+pub mod history;
+mod io_util;
+pub mod journal;
 pub mod movie;
 
 /// Struct implementing movie recording state.
@@ -24,12 +41,163 @@ pub struct VcrState {
     vi_count: u32,
     read_only: bool,
     first_poll: bool,
+    polled_ports: u8,
+    polled_this_vi: bool,
+    lag_count: u32,
+    session: SessionCounters,
+    journal: Option<Journal>,
+    /// True if journaling was started. The journal is only written in read-write mode.
+    journaling: bool,
+    bundle: Option<BundleFiles>,
+    checksums: Option<ChecksumLog>,
+    desync: Option<Desync>,
+    greenzone: Greenzone,
+    branches: BranchSet,
+    /// Incremented whenever the inputs change.
+    revision: u64,
+    history: History,
+    /// Reset requested while recording, to be stored at the start of the next frame.
+    pending_reset: Option<ResetKind>,
+    /// Reset read or recorded at the start of the current frame, to be performed by the core.
+    reset_due: Option<ResetKind>,
+    end_behavior: EndBehavior,
+    /// True once playback has reached the end of the movie.
+    ended: bool,
+    /// End of the movie reached during the current frame, to be handled by the frontend.
+    end_due: Option<EndBehavior>,
+    /// Emulation needs to be restarted for the movie's save memory to be loaded.
+    restart_due: bool,
+    /// Save memory files replaced by the movie's, to be put back when the movie is closed.
+    replaced_save_ram: Vec<PathBuf>,
+    /// True if memory was modified outside of the game while recording, even if the header
+    /// can't store it.
+    modified_memory: bool,
+}
+
+/// A movie exported from a [`VcrState`], in the format it was opened in.
+#[derive(Debug, Clone)]
+pub enum ExportedMovie {
+    M64 {
+        movie: M64File,
+        checksums: Option<ChecksumLog>,
+        branches: BranchSet,
+    },
+    Bundle(MovieBundle),
+}
+
+impl ExportedMovie {
+    /// Writes the movie to a file. For .m64 movies, this does not include the files
+    /// stored next to the movie; use [`ExportedMovie::save`] to write those as well.
+    pub fn write_into<W: Write + Seek>(self, writer: &mut W) -> io::Result<()> {
+        match self {
+            ExportedMovie::M64 { movie, .. } => movie.write_into(writer),
+            ExportedMovie::Bundle(bundle) => bundle.write_into(writer),
+        }
+    }
+
+    /// Saves the movie to `path`, along with any files stored next to it.
+    pub fn save(self, path: &Path) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        match self {
+            ExportedMovie::M64 {
+                movie,
+                checksums,
+                branches,
+            } => {
+                movie.write_into(&mut writer)?;
+                save_sidecar(
+                    &ChecksumLog::path_for(path),
+                    checksums.as_ref(),
+                    |log, writer| log.write_into(writer),
+                )?;
+                let branches = (!branches.is_empty()).then_some(&branches);
+                save_sidecar(&BranchSet::path_for(path), branches, |set, writer| {
+                    set.write_into(writer)
+                })?;
+            }
+            ExportedMovie::Bundle(bundle) => bundle.write_into(&mut writer)?,
+        }
+        writer.flush()
+    }
+}
+
+/// Writes a file stored next to a movie, or removes it if there is nothing to store, so
+/// that one from an earlier recording isn't left behind.
+fn save_sidecar<T, F>(path: &Path, value: Option<&T>, write: F) -> io::Result<()>
+where
+    F: FnOnce(&T, &mut io::BufWriter<fs::File>) -> io::Result<()>,
+{
+    match value {
+        Some(value) => {
+            let mut writer = io::BufWriter::new(fs::File::create(path)?);
+            write(value, &mut writer)?;
+            writer.flush()
+        }
+        None => match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// What happens when playback reaches the end of a movie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EndBehavior {
+    /// Stop the movie.
+    #[default]
+    Stop,
+    /// Pause the emulator, then continue with live input.
+    Pause,
+    /// Keep the movie open, passing live input through without recording it.
+    PlayLive,
+    /// Switch to read-write mode and keep recording.
+    Record,
+}
+
+/// Statistics collected over the lifetime of a [`VcrState`], as opposed to
+/// those stored in the movie itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Number of rerecords made since the movie was opened.
+    pub rerecords: u32,
+    /// Number of VIs emulated while the movie was active.
+    pub vi_frames: u64,
+    /// Number of input samples recorded or played back while the movie was active.
+    pub input_frames: u64,
+    /// Number of VIs emulated without the game polling for input.
+    pub lag_frames: u64,
+    /// Time elapsed since the movie was opened.
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+struct SessionCounters {
+    rerecords: u32,
+    vi_frames: u64,
+    input_frames: u64,
+    lag_frames: u64,
+    start_time: Instant,
+}
+
+impl SessionCounters {
+    fn new() -> Self {
+        Self {
+            rerecords: 0,
+            vi_frames: 0,
+            input_frames: 0,
+            lag_frames: 0,
+            start_time: Instant::now(),
+        }
+    }
 }
 
 impl VcrState {
-    /// Initialize VCR for a new recording.
+    /// Initialize VCR for a new recording. If `path` has the bundle extension, the movie
+    /// is recorded as a [`MovieBundle`].
     pub fn new<P: Into<PathBuf>>(path: P, header: M64Header, read_only: bool) -> Self {
         let path = path.into();
+        let bundle = MovieBundle::is_bundle_path(&path).then(BundleFiles::default);
+        let modified_memory = header.modified_memory();
         Self {
             path,
             header,
@@ -38,6 +206,27 @@ impl VcrState {
             vi_count: 0,
             read_only,
             first_poll: false,
+            polled_ports: 0,
+            polled_this_vi: false,
+            lag_count: 0,
+            session: SessionCounters::new(),
+            journal: None,
+            journaling: false,
+            bundle,
+            checksums: None,
+            desync: None,
+            greenzone: Greenzone::default(),
+            branches: BranchSet::new(),
+            revision: 0,
+            history: History::new(),
+            pending_reset: None,
+            reset_due: None,
+            end_behavior: EndBehavior::default(),
+            ended: false,
+            end_due: None,
+            restart_due: false,
+            replaced_save_ram: Vec::new(),
+            modified_memory,
         }
     }
 
@@ -45,6 +234,7 @@ impl VcrState {
     pub fn with_m64<P: Into<PathBuf>>(path: P, file: M64File, read_only: bool) -> Self {
         let path = path.into();
         let M64File { header, inputs } = file;
+        let modified_memory = header.modified_memory();
         Self {
             path,
             header,
@@ -53,81 +243,219 @@ impl VcrState {
             vi_count: 0,
             read_only,
             first_poll: false,
+            polled_ports: 0,
+            polled_this_vi: false,
+            lag_count: 0,
+            session: SessionCounters::new(),
+            journal: None,
+            journaling: false,
+            bundle: None,
+            checksums: None,
+            desync: None,
+            greenzone: Greenzone::default(),
+            branches: BranchSet::new(),
+            revision: 0,
+            history: History::new(),
+            pending_reset: None,
+            reset_due: None,
+            end_behavior: EndBehavior::default(),
+            ended: false,
+            end_due: None,
+            restart_due: false,
+            replaced_save_ram: Vec::new(),
+            modified_memory,
+        }
+    }
+
+    /// Initialize VCR with an existing movie bundle.
+    pub fn with_bundle<P: Into<PathBuf>>(path: P, bundle: MovieBundle, read_only: bool) -> Self {
+        let MovieBundle {
+            movie, mut files, ..
+        } = bundle;
+        Self {
+            checksums: files.checksums.take(),
+            branches: files.branches.take().unwrap_or_default(),
+            bundle: Some(files),
+            ..Self::with_m64(path, movie, read_only)
         }
     }
 
-    /// Export the current VCR state to an .m64 file.
-    pub fn export(&self) -> (PathBuf, M64File) {
+    /// Export the current VCR state, in the same format it was loaded from.
+    pub fn export(&self) -> (PathBuf, ExportedMovie) {
+        let header = self.export_header();
+        let inputs = self.inputs.clone();
+        let movie = M64File { header, inputs };
+
+        let exported = match &self.bundle {
+            Some(files) => ExportedMovie::Bundle(MovieBundle {
+                files: BundleFiles {
+                    checksums: self.checksums.clone(),
+                    branches: (!self.branches.is_empty()).then(|| self.branches.clone()),
+                    ..files.clone()
+                },
+                ..MovieBundle::new(movie)
+            }),
+            None => ExportedMovie::M64 {
+                movie,
+                checksums: self.checksums.clone(),
+                branches: self.branches.clone(),
+            },
+        };
+        (self.path.clone(), exported)
+    }
+
+    /// Returns true if this movie is stored as a [`MovieBundle`].
+    pub fn is_bundle(&self) -> bool {
+        self.bundle.is_some()
+    }
+
+    fn export_header(&self) -> M64Header {
         let mut header = self.header.clone();
 
         header.length_samples = self.inputs.len().try_into().unwrap();
         header.length_vis = self.vi_count;
 
-        let inputs = self.inputs.clone();
+        header
+    }
+
+    /// Starts journaling changes to the movie, so that it can be recovered using
+    /// [`Journal::recover`] if the emulator crashes before the movie is saved. Read-only
+    /// playback can't change the movie, so the journal is only created once in read-write
+    /// mode.
+    pub fn start_journal(&mut self) -> io::Result<()> {
+        self.journaling = true;
+        self.open_journal()
+    }
+
+    /// Creates the journal if journaling was started and the movie can be changed.
+    fn open_journal(&mut self) -> io::Result<()> {
+        if !self.journaling || self.read_only || self.journal.is_some() {
+            return Ok(());
+        }
+        self.journal = Some(Journal::create(
+            &self.path,
+            &self.export_header(),
+            &self.inputs,
+        )?);
+        Ok(())
+    }
 
-        (self.path.clone(), M64File { header, inputs })
+    /// Restarts the journal from the current state. Call this after the movie is saved.
+    pub fn checkpoint_journal(&mut self) -> io::Result<()> {
+        let header = self.export_header();
+        match self.journal.as_mut() {
+            Some(journal) => journal.checkpoint(&header, &self.inputs),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops journaling and deletes the journal. Call this after the movie is saved and closed.
+    pub fn remove_journal(&mut self) -> io::Result<()> {
+        self.journaling = false;
+        match self.journal.take() {
+            Some(journal) => journal.remove(),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs an operation on the journal, if there is one. If any step of the operation fails,
+    /// journaling is stopped, since the journal can no longer be trusted. This takes the
+    /// journal rather than `self`, so that the operation can borrow the inputs.
+    fn with_journal<F: FnOnce(&mut Journal) -> io::Result<()>>(
+        journal: &mut Option<Journal>,
+        f: F,
+    ) {
+        if let Some(writer) = journal.as_mut() {
+            if let Err(err) = f(writer) {
+                log::error!("movie journal write failed, journaling disabled: {}", err);
+                *journal = None;
+            }
+        }
     }
 
     /// Resets all counters to frame 0 and sets up the core to restart playback.
     /// If `new` is set, creates any necessary auxilliary files instead of loading them.
+    /// Check [`VcrState::take_restart`] afterwards.
     pub async fn reset(&mut self, core: &Core, new: bool) -> Result<(), Box<dyn Error>> {
         self.vi_count = 0;
         self.index = 0;
+        self.polled_ports = 0;
+        self.polled_this_vi = false;
+        self.lag_count = 0;
+        self.desync = None;
+        self.pending_reset = None;
+        self.reset_due = None;
+        self.ended = false;
+        self.restart_due = false;
+        if new {
+            if let Some(checksums) = self.checksums.as_mut() {
+                checksums.truncate(0);
+            }
+            self.greenzone.clear();
+        }
 
         match self.header.start_flags {
             StartType::FROM_RESET => {
                 core.reset(true)?;
                 self.first_poll = true;
             }
-            StartType::FROM_SNAPSHOT => match new {
-                false => {
-                    self.first_poll = true;
-                    let file_stem = self
-                        .path
-                        .file_name()
-                        .and_then(OsStr::to_str)
-                        .and_then(|name_str| name_str.find('.').map(|pos| &name_str[..pos]))
-                        .ok_or(io::Error::new(
-                            io::ErrorKind::InvalidInput,
-                            ".m64 filenames should not start with `.`",
-                        ))?;
-
-                    let st_path = fs::read_dir(self.path.parent().unwrap())?
-                        .filter_map(|entry| {
-                            let entry = entry.unwrap();
-                            if !entry.file_type().is_ok_and(|ty| ty.is_file()) {
-                                return None;
-                            }
-
-                            if !(Some(&*entry.file_name())
-                                .and_then(OsStr::to_str)
-                                .and_then(|name_str| name_str.find('.').map(|pos| &name_str[..pos]))
-                                .is_some_and(|stem| stem == file_stem))
-                            {
-                                return None;
-                            }
-
-                            Some(entry.path())
-                        })
-                        .next()
-                        .ok_or_else(|| {
+            StartType::FROM_SNAPSHOT => {
+                self.first_poll = true;
+                match (new, self.bundle.as_mut()) {
+                    (false, Some(files)) => {
+                        let savestate = files.savestate.as_deref().ok_or_else(|| {
                             io::Error::new(
                                 io::ErrorKind::NotFound,
-                                "No .st file found for .m64 movie",
+                                "Movie bundle does not contain a savestate",
                             )
                         })?;
-
-                    core.load_file(st_path).await?;
+                        core.load_data(savestate).await?;
+                    }
+                    (true, Some(files)) => {
+                        files.savestate = Some(core.save_data(SavestateFormat::Mupen64Plus).await?);
+                    }
+                    (false, None) => {
+                        core.load_file(find_savestate(&self.path)?).await?;
+                    }
+                    (true, None) => {
+                        let st_path = self.path.with_extension("st");
+                        core.save_file(st_path, SavestateFormat::Mupen64Plus)
+                            .await?;
+                    }
                 }
-                true => {
-                    self.first_poll = true;
-                    let st_path = self.path.with_extension("st");
-                    core.save_file(st_path, SavestateFormat::Mupen64Plus)
-                        .await?;
+            }
+            StartType::FROM_EEPROM if new => {
+                let save_paths = core.save_ram_paths()?;
+
+                for (i, (core_path, ext)) in
+                    save_paths.iter().zip(SaveRamPaths::EXTENSIONS).enumerate()
+                {
+                    let dump = match core_path.is_file() {
+                        true => Some(fs::read(core_path)?),
+                        false => None,
+                    };
+                    if let Some(files) = self.bundle.as_mut() {
+                        files.save_ram[i] = dump;
+                        continue;
+                    }
+
+                    // Snapshot the current save memory alongside the movie. Stale dumps
+                    // from an earlier recording would otherwise be picked up on playback.
+                    let dump_path = save_ram_dump_path(&self.path, ext)?;
+                    match dump {
+                        Some(data) => fs::write(&dump_path, data)?,
+                        None if dump_path.is_file() => fs::remove_file(&dump_path)?,
+                        None => (),
+                    }
                 }
-            },
+
+                core.reset(true)?;
+                self.first_poll = true;
+            }
             StartType::FROM_EEPROM => {
-                unimplemented!()
+                // The core only reads save memory when emulation starts.
+                self.restart_due = true;
+                self.first_poll = true;
             }
             _ => panic!("invalid start flags"),
         }
@@ -136,44 +464,236 @@ impl VcrState {
         Ok(())
     }
 
+    /// Returns true if emulation has to be restarted for the movie to start. This is the
+    /// case for movies starting from save memory, since the core only reads save memory
+    /// when emulation starts. Call [`VcrState::load_save_ram`] while emulation is stopped.
+    pub fn take_restart(&mut self) -> bool {
+        mem::take(&mut self.restart_due)
+    }
+
+    /// Replaces the ROM's save memory with the movie's. The user's own save memory is backed
+    /// up the first time, and put back by [`VcrState::restore_save_ram`].
+    pub fn load_save_ram(&mut self, core: &Core) -> Result<(), Box<dyn Error>> {
+        let save_paths = core.save_ram_paths()?;
+
+        for (i, (core_path, ext)) in save_paths.iter().zip(SaveRamPaths::EXTENSIONS).enumerate() {
+            // Later restarts must not overwrite the backup with the movie's save memory.
+            let backup_path = save_ram_backup_path(core_path);
+            if !self.replaced_save_ram.contains(core_path) {
+                // A backup left by a session that didn't restore it is the user's own.
+                if core_path.is_file() && !backup_path.is_file() {
+                    fs::copy(core_path, &backup_path)?;
+                }
+                self.replaced_save_ram.push(core_path.clone());
+            }
+
+            let dump = match self.bundle.as_ref() {
+                Some(files) => files.save_ram[i].clone(),
+                None => {
+                    let dump_path = save_ram_dump_path(&self.path, ext)?;
+                    match dump_path.is_file() {
+                        true => Some(fs::read(dump_path)?),
+                        false => None,
+                    }
+                }
+            };
+            match dump {
+                Some(data) => fs::write(core_path, data)?,
+                None if core_path.is_file() => fs::remove_file(core_path)?,
+                None => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the ROM's save memory was replaced by the movie's.
+    pub fn replaced_save_ram(&self) -> bool {
+        !self.replaced_save_ram.is_empty()
+    }
+
+    /// Puts back the save memory replaced by [`VcrState::load_save_ram`]. Call this when the
+    /// movie is closed, while emulation is stopped.
+    pub fn restore_save_ram(&mut self) -> io::Result<()> {
+        for core_path in mem::take(&mut self.replaced_save_ram) {
+            let backup_path = save_ram_backup_path(&core_path);
+            if backup_path.is_file() {
+                fs::rename(backup_path, core_path)?;
+            } else if core_path.is_file() {
+                fs::remove_file(core_path)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Implementation of [`InputHandler::filter_inputs`][m64prs_core::tas_callbacks::InputHandler::filter_inputs].  
     /// This method will either play back inputs (read/write mode), or overwrite inputs, depending on the read-only mode.
+    ///
+    /// Each input frame holds one sample per present controller, in port order, and every
+    /// poll uses its own sample. A frame ends once every present port was polled. Polling a
+    /// port again before that starts a new frame, so ports may be polled in any order, and
+    /// ports that aren't polled in a frame hold their previous value.
     /// # Return value
     /// Two things:
     /// - `Buttons`: the filtered input value
-    /// - `bool`: if true, the VCR state has run out of frames.
-    pub fn filter_inputs(&mut self, port: c_int, input: Buttons) -> (Buttons, bool) {
+    /// - `bool`: if true, the VCR state has run out of frames and the movie should be closed.
+    ///   This only happens with [`EndBehavior::Stop`], which [`VcrState::take_end`] also
+    ///   returns once.
+    pub fn filter_inputs(&mut self, port: c_int, mut input: Buttons) -> (Buttons, bool) {
+        self.polled_this_vi = true;
+
         // don't overwrite inputs we don't care about
         if !self.header.controller_flags.port_present(port) {
             return (input, false);
         }
 
-        // this works for singleplayer .m64s, not sure about multiplayer
-        if self.first_poll {
+        let port_bit = 1u8 << port;
+        if self.polled_ports & port_bit != 0 {
+            self.polled_ports = 0;
+            self.first_poll = false;
+        }
+        let frame_start = self.polled_ports == 0;
+        self.polled_ports |= port_bit;
+
+        // the first frame after a reset is not part of the movie
+        let skip = self.first_poll;
+        if self.polled_ports == self.header.controller_flags.present_mask() {
+            // every present port was polled, so the next poll starts a new frame
+            self.polled_ports = 0;
             self.first_poll = false;
+        }
+        if skip {
             return (input, false);
         }
 
-        let index_usize: usize = self.index.try_into().unwrap();
-        if self.read_only {
-            if index_usize < self.inputs.len() {
-                let result = self.inputs[index_usize];
-                self.index += 1;
-                (result, false)
-            } else {
-                (input, true)
+        let frame_size = self.header.controller_flags.num_present();
+        let rank = self.header.controller_flags.port_rank(port);
+
+        if frame_start {
+            // index always points to the frame after the current one.
+            let base: usize = self.index.try_into().unwrap();
+            let frame_size_usize: usize = frame_size.try_into().unwrap();
+
+            if self.read_only && base + frame_size_usize > self.inputs.len() {
+                if !self.ended {
+                    self.ended = true;
+                    self.end_due = Some(self.end_behavior);
+                }
+                match self.end_behavior {
+                    EndBehavior::Stop => return (input, true),
+                    EndBehavior::Pause | EndBehavior::PlayLive => return (input, false),
+                    // The frame is starting, so there is nothing to reset.
+                    EndBehavior::Record => self.apply_read_only(false),
+                }
             }
-        } else {
-            if index_usize < self.inputs.len() {
-                self.inputs.truncate(index_usize);
+
+            if self.read_only {
+                // the movie may have been extended since it ended
+                self.ended = false;
+                self.reset_due = ResetKind::from_sample(self.inputs[base]);
+            } else {
+                let old_len = self.inputs.len();
+                if old_len > base {
+                    // Recording over existing inputs drops everything after this frame.
+                    self.history
+                        .record_tail(self.index, self.inputs[base..].to_vec());
+                }
+                // start the new frame by holding the previous frame's inputs
+                self.inputs.resize(base, Buttons::BLANK);
+                if base >= frame_size_usize {
+                    self.inputs
+                        .extend_from_within((base - frame_size_usize)..base);
+                } else {
+                    self.inputs.resize(base + frame_size_usize, Buttons::BLANK);
+                }
+                // A held frame must not repeat the previous frame's reset.
+                for sample in &mut self.inputs[base..] {
+                    sample.button_bits.remove(ResetKind::FLAGS);
+                }
+                self.reset_due = self.pending_reset.take();
+                if let Some(reset) = self.reset_due {
+                    self.inputs[base].button_bits.insert(reset.flag());
+                }
+
+                self.greenzone.invalidate(self.index);
+
+                let (vi_count, rerecords) = (self.vi_count, self.header.rerecords());
+                let new_frame = &self.inputs[base..];
+                let index = self.index;
+                Self::with_journal(&mut self.journal, |journal| {
+                    if old_len > base {
+                        journal.truncate(index)?;
+                    }
+                    journal.write_samples(index, new_frame)?;
+                    journal.write_counters(vi_count, rerecords)?;
+                    journal.flush()
+                });
             }
             // TODO: account for the (2**32 - 1)th frame being the last
-            self.inputs.push(input);
-            self.index += 1;
+            self.index += frame_size;
+            self.session.input_frames += 1;
+        }
+
+        // Other ports polled in the frame where the movie ended get live input as well.
+        if self.read_only && self.ended {
+            return (input, false);
+        }
+
+        let slot: usize = (self.index - frame_size + rank).try_into().unwrap();
+        input.button_bits.remove(ResetKind::FLAGS);
+        if self.read_only {
+            let mut sample = self.inputs[slot];
+            sample.button_bits.remove(ResetKind::FLAGS);
+            (sample, false)
+        } else {
+            // keep the reset stored at the start of the frame
+            let mut sample = input;
+            sample.button_bits |= self.inputs[slot].button_bits & ResetKind::FLAGS;
+            self.inputs[slot] = sample;
+            self.revision = self.revision.wrapping_add(1);
+            Self::with_journal(&mut self.journal, |journal| {
+                journal.write_samples(slot as u32, &[sample])
+            });
             (input, false)
         }
     }
 
+    /// Requests a reset while recording. The reset is stored at the start of the next input
+    /// frame, and is performed once [`VcrState::take_reset`] returns it, so that recording
+    /// and playback reset at the same point.
+    /// # Return value
+    /// False in read-only mode, where resets only come from the movie.
+    pub fn request_reset(&mut self, reset: ResetKind) -> bool {
+        if self.read_only {
+            return false;
+        }
+        self.pending_reset = Some(reset);
+        true
+    }
+
+    /// Sets what happens when playback reaches the end of the movie.
+    pub fn set_end_behavior(&mut self, behavior: EndBehavior) {
+        self.end_behavior = behavior;
+    }
+
+    /// Returns what happens when playback reaches the end of the movie.
+    pub fn end_behavior(&self) -> EndBehavior {
+        self.end_behavior
+    }
+
+    /// Returns the end-of-movie behaviour to carry out if playback reached the end of the
+    /// movie during the last call to [`VcrState::filter_inputs`]. This is only returned
+    /// once each time the end is reached. With [`EndBehavior::Record`], the VCR state has
+    /// already switched to read-write mode.
+    pub fn take_end(&mut self) -> Option<EndBehavior> {
+        self.end_due.take()
+    }
+
+    /// Returns the reset stored at the start of the current frame, if it still needs to be
+    /// performed. This should be checked after each call to [`VcrState::filter_inputs`].
+    pub fn take_reset(&mut self) -> Option<ResetKind> {
+        self.reset_due.take()
+    }
+
     /// Implementation of [`InputHandler::poll_present`][m64prs_core::tas_callbacks::InputHandler::poll_present].  
     /// This method will return true for any port where input is being recorded.
     pub fn poll_present(&self, port: c_int) -> bool {
@@ -181,24 +701,127 @@ impl VcrState {
     }
 
     /// Implementation of [`m64prs_core::tas_callbacks::FrameHandler`]. This method
-    /// simply increments the VI count.
-    pub fn tick_vi(&mut self) {
-        if self.read_only {
-            if usize::try_from(self.index).unwrap() < self.inputs.len() {
-                self.vi_count = self.vi_count.saturating_add(1);
-            }
-        } else {
+    /// increments the VI count, and counts the VI as a lag frame if no input was polled
+    /// since the previous VI.
+    /// # Return value
+    /// True if the VI was a lag frame.
+    pub fn tick_vi(&mut self) -> bool {
+        let lagged = !mem::take(&mut self.polled_this_vi);
+        self.session.vi_frames += 1;
+        if lagged {
+            self.session.lag_frames += 1;
+        }
+
+        let in_movie = !self.read_only || usize::try_from(self.index).unwrap() < self.inputs.len();
+        if in_movie {
             self.vi_count = self.vi_count.saturating_add(1);
+            if lagged {
+                self.lag_count = self.lag_count.saturating_add(1);
+            }
+        }
+        if !self.read_only {
             self.header.length_vis = self.header.length_vis.max(self.vi_count);
         }
+        lagged
+    }
+
+    /// Sets the RAM checksums to record or check against. When recording, pass an empty
+    /// [`ChecksumLog`] to start recording checksums.
+    pub fn set_checksums(&mut self, checksums: Option<ChecksumLog>) {
+        self.checksums = checksums;
+        self.desync = None;
+    }
+
+    /// Returns the movie's RAM checksums, if any.
+    pub fn checksums(&self) -> Option<&ChecksumLog> {
+        self.checksums.as_ref()
+    }
+
+    /// Returns true if [`VcrState::check_memory`] should be called for the current VI.
+    /// Call this after [`VcrState::tick_vi`], so that reading memory can be skipped otherwise.
+    pub fn checksum_due(&self) -> bool {
+        let Some(checksums) = &self.checksums else {
+            return false;
+        };
+        if self.read_only {
+            self.desync.is_none()
+                && usize::try_from(self.index).unwrap() < self.inputs.len()
+                && checksums.get(self.vi_count).is_some()
+        } else {
+            checksums.is_due(self.vi_count)
+        }
+    }
+
+    /// Checksums emulated memory at the current VI. In read-write mode, the checksum is
+    /// recorded into the movie. In read-only mode, it is compared against the recorded one.
+    /// # Return value
+    /// The first mismatch found since the movie was reset or a state was loaded.
+    pub fn check_memory(&mut self, rdram: &[u8]) -> Option<Desync> {
+        let checksums = self.checksums.as_mut()?;
+        let actual = checksums.compute(rdram);
+        if !self.read_only {
+            checksums.record(self.vi_count, actual);
+            return None;
+        }
+
+        let expected = checksums.get(self.vi_count)?;
+        if expected == actual || self.desync.is_some() {
+            return None;
+        }
+        let desync = Desync {
+            vi: self.vi_count,
+            index: self.index,
+            expected,
+            actual,
+        };
+        log::warn!(
+            "movie desynced at VI {} (expected checksum {:08x}, got {:08x})",
+            desync.vi,
+            desync.expected,
+            desync.actual
+        );
+        self.desync = Some(desync);
+        Some(desync)
+    }
+
+    /// Returns the first desync found during playback, if any.
+    pub fn desync(&self) -> Option<Desync> {
+        self.desync
+    }
+
+    /// Replaces the greenzone with an empty one using the given settings.
+    pub fn set_greenzone_settings(&mut self, settings: GreenzoneSettings) {
+        self.greenzone = Greenzone::new(settings);
+    }
+
+    /// Returns the movie's greenzone.
+    pub fn greenzone(&self) -> &Greenzone {
+        &self.greenzone
+    }
+
+    /// Returns the movie's greenzone, for recording captures.
+    pub fn greenzone_mut(&mut self) -> &mut Greenzone {
+        &mut self.greenzone
+    }
+
+    /// Returns true if a greenzone capture should be taken at the current position.
+    pub fn greenzone_due(&self) -> bool {
+        let interval = self.greenzone.settings().interval;
+        if self.index == 0 || self.greenzone.capturing() {
+            return false;
+        }
+        let frame = self.index / self.frame_size().max(1);
+        frame.checked_rem(interval) == Some(0) && !self.greenzone.contains(self.index)
     }
 
     /// Emits a [`freeze::MovieFreeze`] suitable for serializing into a savestate.
     pub fn freeze(&self) -> MovieFreeze {
-        freeze::v1::MovieFreeze {
+        freeze::v3::MovieFreeze {
             uid: self.header.uid,
-            index: self.index.try_into().unwrap(),
-            vi_count: self.vi_count.try_into().unwrap(),
+            index: self.index,
+            vi_count: self.vi_count,
+            rerecord_count: self.header.rerecords().try_into().unwrap_or(u32::MAX),
+            lag_count: self.lag_count,
             inputs: self.inputs.clone(),
         }
         .into()
@@ -208,26 +831,525 @@ impl VcrState {
         self.read_only
     }
 
+    /// Switches between read-only and read-write mode. The next poll starts a new frame, so
+    /// that ports polled for the rest of the current frame don't use a frame from the other
+    /// mode.
     pub fn set_read_only(&mut self, value: bool) {
+        if self.read_only != value {
+            self.polled_ports = 0;
+            self.ended = false;
+        }
+        self.apply_read_only(value);
+    }
+
+    fn apply_read_only(&mut self, value: bool) {
         self.read_only = value;
+        if let Err(err) = self.open_journal() {
+            log::error!("failed to create movie journal: {}", err);
+        }
     }
 
-    ///
-    pub fn load_freeze(&mut self, freeze: MovieFreeze) -> Result<(), M64PError> {
-        match freeze {
-            MovieFreeze::V1(freeze) => {
-                if freeze.uid != self.header.uid {
-                    return Err(M64PError::InputInvalid);
-                }
+    /// Notes that memory was modified outside of the game. While recording, this flags the
+    /// movie as unverifiable. The flag is only stored in the header if the movie has no
+    /// authorship tag or m64prs's own.
+    /// # Return value
+    /// True if this is the first modification noted while recording.
+    pub fn note_memory_modified(&mut self) -> bool {
+        if self.read_only || self.modified_memory {
+            return false;
+        }
+        self.modified_memory = true;
+        self.header.set_modified_memory();
+        true
+    }
+
+    /// Returns true if the movie was recorded with memory modified outside of the game.
+    pub fn modified_memory(&self) -> bool {
+        self.modified_memory
+    }
+
+    /// Returns the movie's current rerecord count.
+    pub fn rerecord_count(&self) -> u64 {
+        self.header.rerecords()
+    }
+
+    /// Returns the index of the next input sample.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the number of input samples in each input frame.
+    pub fn frame_size(&self) -> u32 {
+        self.header.controller_flags.num_present()
+    }
+
+    /// Returns the number of VIs elapsed since the start of the movie.
+    pub fn vi_count(&self) -> u32 {
+        self.vi_count
+    }
 
-                self.index = freeze.index.try_into().unwrap();
-                self.vi_count = freeze.vi_count.try_into().unwrap();
-                self.inputs = freeze.inputs;
+    /// Returns the current position, measured in the same unit as `target`.
+    pub fn seek_position(&self, target: SeekTarget) -> u32 {
+        match target {
+            SeekTarget::Index(_) => self.index,
+            SeekTarget::Vi(_) => self.vi_count,
+        }
+    }
+
+    /// Returns the movie's inputs.
+    pub fn inputs(&self) -> &[Buttons] {
+        &self.inputs
+    }
+
+    /// Returns a counter that changes whenever the inputs change. This can be used to
+    /// tell if a copy of the inputs is out of date.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Replaces `len` samples starting at `start` with `samples`, like [`Vec::splice`].
+    /// Edits at or after the current position take effect as the movie plays; edits before
+    /// it take effect once the movie is replayed from an earlier point.
+    /// # Return value
+    /// The samples that were removed.
+    pub fn splice_inputs(&mut self, start: u32, len: u32, samples: &[Buttons]) -> Vec<Buttons> {
+        let revert = self.apply_change(Change {
+            start,
+            len: Some(len),
+            samples: samples.to_vec(),
+        });
+        let removed = revert.samples.clone();
+        self.history.record(revert);
+        removed
+    }
+
+    /// Returns the movie's undo history.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Reverts the last change to the inputs.
+    /// # Return value
+    /// False if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(change) = self.history.pop_undo() else {
+            return false;
+        };
+        let revert = self.apply_change(change);
+        self.history.push_redo(revert);
+        true
+    }
+
+    /// Reapplies the last change reverted by [`VcrState::undo`].
+    /// # Return value
+    /// False if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(change) = self.history.pop_redo() else {
+            return false;
+        };
+        let revert = self.apply_change(change);
+        self.history.push_undo(revert);
+        true
+    }
+
+    /// Applies `change` to the inputs, keeping the greenzone and journal up to date.
+    fn apply_change(&mut self, change: Change) -> Change {
+        let inserted = change.samples.len();
+        let revert = change.apply(&mut self.inputs);
+        let start = revert.start as usize;
+        self.revision = self.revision.wrapping_add(1);
+        self.greenzone.invalidate(revert.start);
 
-                Ok(())
+        // If the length changed, everything after the edit moved.
+        let length_changed = revert.samples.len() != inserted;
+        let written = match length_changed {
+            true => &self.inputs[start..],
+            false => &self.inputs[start..(start + inserted)],
+        };
+        Self::with_journal(&mut self.journal, |journal| {
+            if length_changed {
+                journal.truncate(start as u32)?;
             }
+            journal.write_samples(start as u32, written)?;
+            journal.flush()
+        });
+        revert
+    }
+
+    /// Replaces the movie's branches.
+    pub fn set_branches(&mut self, branches: BranchSet) {
+        self.branches = branches;
+    }
+
+    /// Returns the movie's branches.
+    pub fn branches(&self) -> &BranchSet {
+        &self.branches
+    }
+
+    /// Creates a branch from the current inputs and position. `savestate` should be
+    /// taken at the current position.
+    /// # Return value
+    /// The new branch's position in [`VcrState::branches`].
+    pub fn create_branch(&mut self, name: String, savestate: Vec<u8>) -> usize {
+        let branch = self.snapshot_branch(name, savestate);
+        self.branches.push(branch)
+    }
+
+    /// Overwrites the branch at position `index` with the current inputs and position,
+    /// keeping its name.
+    pub fn update_branch(&mut self, index: usize, savestate: Vec<u8>) -> Option<Branch> {
+        let name = self.branches.get(index)?.name.clone();
+        let branch = self.snapshot_branch(name, savestate);
+        self.branches.replace(index, branch)
+    }
+
+    /// Removes the branch at position `index`.
+    pub fn remove_branch(&mut self, index: usize) -> Option<Branch> {
+        self.branches.remove(index)
+    }
+
+    /// Compares the branch at position `index` against the current inputs.
+    pub fn compare_branch(&self, index: usize) -> Option<BranchComparison> {
+        Some(self.branches.get(index)?.compare(&self.inputs))
+    }
+
+    /// Makes the branch at position `index` the main timeline. The current timeline takes
+    /// its place in the branch list, under the same name. `savestate` should be taken at the
+    /// current position.
+    /// # Return value
+    /// The promoted branch's savestate. Loading it switches the movie to the branch's inputs.
+    pub fn promote_branch(&mut self, index: usize, savestate: Vec<u8>) -> Option<Vec<u8>> {
+        self.update_branch(index, savestate)
+            .map(|promoted| promoted.savestate)
+    }
+
+    fn snapshot_branch(&self, name: String, savestate: Vec<u8>) -> Branch {
+        Branch {
+            name,
+            index: self.index,
+            vi_count: self.vi_count,
+            inputs: self.inputs.clone(),
+            savestate,
+        }
+    }
+
+    /// Returns true if the movie has reached or passed `target`.
+    pub fn reached(&self, target: SeekTarget) -> bool {
+        self.seek_position(target) >= target.value()
+    }
+
+    /// Returns the number of lag frames since the start of the movie.
+    pub fn lag_count(&self) -> u32 {
+        self.lag_count
+    }
+
+    /// Returns the number of input samples currently in the movie.
+    pub fn length_samples(&self) -> u32 {
+        self.inputs.len().try_into().unwrap()
+    }
+
+    /// Returns statistics for the current session.
+    pub fn session_stats(&self) -> SessionStats {
+        SessionStats {
+            rerecords: self.session.rerecords,
+            vi_frames: self.session.vi_frames,
+            input_frames: self.session.input_frames,
+            lag_frames: self.session.lag_frames,
+            elapsed: self.session.start_time.elapsed(),
+        }
+    }
+
+    /// Loads a [`freeze::MovieFreeze`] from a savestate. In read-write mode, this counts as a rerecord.
+    pub fn load_freeze(&mut self, freeze: MovieFreeze) -> Result<(), M64PError> {
+        let (uid, index, vi_count, rerecords, lag_count, inputs) = match freeze {
+            MovieFreeze::V1(freeze) => (
+                freeze.uid,
+                freeze.index,
+                freeze.vi_count,
+                None,
+                None,
+                freeze.inputs,
+            ),
+            MovieFreeze::V2(freeze) => (
+                freeze.uid,
+                freeze.index,
+                freeze.vi_count,
+                Some(freeze.rerecord_count),
+                None,
+                freeze.inputs,
+            ),
+            MovieFreeze::V3(freeze) => (
+                freeze.uid,
+                freeze.index,
+                freeze.vi_count,
+                Some(freeze.rerecord_count),
+                Some(freeze.lag_count),
+                freeze.inputs,
+            ),
             #[allow(unreachable_patterns)]
-            _ => Err(M64PError::Incompatible),
+            _ => return Err(M64PError::Incompatible),
+        };
+
+        if uid != self.header.uid {
+            return Err(M64PError::InputInvalid);
+        }
+
+        // Only journal the inputs that actually changed.
+        let common_len = self
+            .inputs
+            .iter()
+            .zip(&inputs)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if common_len < self.inputs.len().max(inputs.len()) {
+            self.greenzone.invalidate(common_len as u32);
+            self.history
+                .record_tail(common_len as u32, self.inputs[common_len..].to_vec());
+        }
+
+        self.index = index;
+        self.vi_count = vi_count;
+        // Older states don't track lag, so the count restarts from the state.
+        self.lag_count = lag_count.unwrap_or(0);
+        self.inputs = inputs;
+        self.revision = self.revision.wrapping_add(1);
+        if let Some(rerecords) = rerecords {
+            // The count stored in the state may be ahead of ours if the movie
+            // was reopened from an older save.
+            let rerecords = self.header.rerecords().max(rerecords.into());
+            self.header.set_rerecords(rerecords);
+        }
+
+        self.polled_ports = 0;
+        self.polled_this_vi = false;
+        self.desync = None;
+        self.pending_reset = None;
+        self.reset_due = None;
+        self.ended = false;
+
+        if !self.read_only {
+            self.header
+                .set_rerecords(self.header.rerecords().saturating_add(1));
+            self.session.rerecords = self.session.rerecords.saturating_add(1);
+            // Checksums past this point belong to the timeline being rerecorded.
+            if let Some(checksums) = self.checksums.as_mut() {
+                checksums.truncate(self.vi_count.saturating_add(1));
+            }
+        }
+
+        let (vi_count, rerecords) = (self.vi_count, self.header.rerecords());
+        let changed = &self.inputs[common_len..];
+        Self::with_journal(&mut self.journal, |journal| {
+            journal.truncate(common_len as u32)?;
+            journal.write_samples(common_len as u32, changed)?;
+            journal.write_counters(vi_count, rerecords)?;
+            journal.flush()
+        });
+
+        Ok(())
+    }
+}
+
+/// Returns the movie's filename up to the first `.`, used to locate auxilliary files.
+fn file_stem(movie_path: &Path) -> io::Result<&str> {
+    movie_path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .and_then(|name_str| name_str.find('.').map(|pos| &name_str[..pos]))
+        .ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            ".m64 filenames should not start with `.`",
+        ))
+}
+
+/// Locates the savestate that a movie starts from. This is `<movie>.st` as written when
+/// recording, or a `.st` or `.savestate` file sharing the movie's stem.
+pub(crate) fn find_savestate(movie_path: &Path) -> io::Result<PathBuf> {
+    let file_stem = file_stem(movie_path)?;
+    let movie_dir = movie_path.parent().unwrap();
+
+    [
+        movie_path.with_extension("st"),
+        movie_dir.join(format!("{}.st", file_stem)),
+        movie_dir.join(format!("{}.savestate", file_stem)),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No .st file found for .m64 movie"))
+}
+
+/// Returns the path of a movie's save memory dump with the given extension.
+pub(crate) fn save_ram_dump_path(movie_path: &Path, ext: &str) -> io::Result<PathBuf> {
+    let file_stem = file_stem(movie_path)?;
+    Ok(movie_path
+        .parent()
+        .unwrap()
+        .join(format!("{}.{}", file_stem, ext)))
+}
+
+/// Returns the path of the backup kept of a save memory file while a movie replaces it.
+fn save_ram_backup_path(core_path: &Path) -> PathBuf {
+    let mut backup_path = core_path.to_owned().into_os_string();
+    backup_path.push(".bak");
+    backup_path.into()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, process};
+
+    use m64prs_sys::{ButtonFlags, Buttons};
+
+    use crate::{
+        journal::Journal,
+        movie::{ControllerFlags, M64File, M64Header, StartType},
+        EndBehavior, VcrState,
+    };
+
+    fn sample(bits: u16) -> Buttons {
+        Buttons {
+            button_bits: ButtonFlags::from_bits_retain(bits),
+            x_axis: 0,
+            y_axis: 0,
+        }
+    }
+
+    fn two_player_header() -> M64Header {
+        let mut header = M64Header::default();
+        header.start_flags = StartType::FROM_RESET;
+        header.num_controllers = 2;
+        header.controller_flags = ControllerFlags::P1_PRESENT | ControllerFlags::P2_PRESENT;
+        header
+    }
+
+    fn two_player_state() -> VcrState {
+        VcrState::new("movie.m64", two_player_header(), false)
+    }
+
+    #[test]
+    fn skipped_port_holds_previous_sample() {
+        let mut vcr = two_player_state();
+
+        vcr.filter_inputs(0, sample(0x1));
+        vcr.filter_inputs(1, sample(0x2));
+        vcr.tick_vi();
+        // Port 0 is polled again before port 1, so port 1 is skipped.
+        vcr.filter_inputs(0, sample(0x4));
+        vcr.filter_inputs(0, sample(0x10));
+        vcr.tick_vi();
+        vcr.filter_inputs(1, sample(0x8));
+        vcr.tick_vi();
+
+        assert_eq!(
+            vcr.inputs(),
+            [0x1, 0x2, 0x4, 0x2, 0x10, 0x8].map(sample).as_slice()
+        );
+    }
+
+    #[test]
+    fn port_polled_twice_in_one_vi_uses_two_samples() {
+        let mut header = M64Header::default();
+        header.controller_flags = ControllerFlags::P1_PRESENT;
+        let mut vcr = VcrState::new("movie.m64", header.clone(), false);
+        vcr.filter_inputs(0, sample(0x1));
+        vcr.filter_inputs(0, sample(0x2));
+        vcr.tick_vi();
+        assert_eq!(vcr.inputs(), [0x1, 0x2].map(sample).as_slice());
+
+        let inputs = vcr.inputs().to_vec();
+        let mut vcr = VcrState::with_m64("movie.m64", M64File { header, inputs }, true);
+        assert_eq!(vcr.filter_inputs(0, sample(0)), (sample(0x1), false));
+        assert_eq!(vcr.filter_inputs(0, sample(0)), (sample(0x2), false));
+    }
+
+    #[test]
+    fn read_write_after_end_starts_new_frame() {
+        for (movie, expected) in [
+            (vec![], [0x4, 0x8].as_slice()),
+            (
+                vec![sample(0x1), sample(0x2)],
+                [0x1, 0x2, 0x4, 0x8].as_slice(),
+            ),
+        ] {
+            let file = M64File {
+                header: two_player_header(),
+                inputs: movie.clone(),
+            };
+            let mut vcr = VcrState::with_m64("movie.m64", file, true);
+            vcr.set_end_behavior(EndBehavior::PlayLive);
+            if !movie.is_empty() {
+                vcr.filter_inputs(0, sample(0));
+                vcr.filter_inputs(1, sample(0));
+                vcr.tick_vi();
+            }
+            vcr.filter_inputs(0, sample(0x4));
+            assert_eq!(vcr.take_end(), Some(EndBehavior::PlayLive));
+
+            // Switch to read-write in the middle of the frame where the movie ended.
+            vcr.set_read_only(false);
+            vcr.filter_inputs(1, sample(0x8));
+            vcr.filter_inputs(0, sample(0x4));
+            vcr.tick_vi();
+
+            assert_eq!(
+                vcr.inputs(),
+                expected.iter().copied().map(sample).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn empty_movie_passes_live_input_to_every_port() {
+        for behavior in [EndBehavior::Pause, EndBehavior::PlayLive] {
+            let mut vcr = two_player_state();
+            vcr.set_read_only(true);
+            vcr.set_end_behavior(behavior);
+
+            for _ in 0..2 {
+                assert_eq!(vcr.filter_inputs(0, sample(0x1)), (sample(0x1), false));
+                assert_eq!(vcr.filter_inputs(1, sample(0x2)), (sample(0x2), false));
+                vcr.tick_vi();
+            }
+            assert_eq!(vcr.take_end(), Some(behavior));
+            assert_eq!(vcr.take_end(), None);
+            assert!(vcr.inputs().is_empty());
         }
     }
+
+    #[test]
+    fn undo_then_redo_restores_inputs() {
+        let mut vcr = two_player_state();
+        for bits in [0x1, 0x2, 0x4, 0x8] {
+            vcr.filter_inputs(0, sample(bits));
+            vcr.filter_inputs(1, sample(bits << 4));
+            vcr.tick_vi();
+        }
+        let recorded = vcr.inputs().to_vec();
+
+        vcr.splice_inputs(2, 4, &[sample(0x100)]);
+        vcr.splice_inputs(0, 0, &[sample(0x200), sample(0x400)]);
+        let edited = vcr.inputs().to_vec();
+
+        assert!(vcr.undo());
+        assert!(vcr.undo());
+        assert_eq!(vcr.inputs(), recorded);
+        assert!(vcr.redo());
+        assert!(vcr.redo());
+        assert!(!vcr.redo());
+        assert_eq!(vcr.inputs(), edited);
+    }
+
+    #[test]
+    fn journal_only_in_read_write() {
+        let path = env::temp_dir().join(format!("m64prs-vcr-journal-{}.m64", process::id()));
+        let journal_path = Journal::path_for(&path);
+        let mut vcr = VcrState::new(path, M64Header::default(), true);
+
+        vcr.start_journal().unwrap();
+        assert!(!journal_path.exists());
+        vcr.set_read_only(false);
+        assert!(journal_path.exists());
+        vcr.remove_journal().unwrap();
+        assert!(!journal_path.exists());
+    }
 }
@@ -0,0 +1,587 @@
+//! Conversion between .m64 files and BizHawk .bk2 movies.
+//!
+//! A .bk2 file is a zip archive. The parts relevant to N64 movies are `Header.txt`, which holds
+//! `key value` pairs, and `Input Log.txt`, which holds one line per frame in BizHawk's mnemonic
+//! format. Its `LogKey` line names the controls, which are grouped per controller:
+//!
+//! ```text
+//! [Input]
+//! LogKey:#Reset|Power|#P1 X Axis|P1 Y Axis|P1 DPad U|...|P1 R|
+//! |..|   12,  -80,....S.........|
+//! [/Input]
+//! ```
+//!
+//! BizHawk logs every VI, while .m64 files only store frames where the game polled input.
+//! Movies saved by TAStudio also hold a `LagLog`, whose first line is a JSON object mapping
+//! each frame to whether it lagged. Lagged lines are dropped on import, and can be inserted
+//! again on export given a lag log. Without one, each .m64 input frame is mapped to one line.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::c_int,
+    fmt::Write as _,
+    io::{Read, Seek, Write},
+};
+
+use m64prs_sys::{ButtonFlags, Buttons};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use super::{error::Bk2Error, ControllerFlags, M64File, M64Header, StartType};
+
+const HEADER_FILE: &str = "Header.txt";
+const INPUT_LOG_FILE: &str = "Input Log.txt";
+const COMMENTS_FILE: &str = "Comments.txt";
+const LAG_LOG_FILE: &str = "LagLog";
+
+const PLATFORM: &str = "N64";
+const SYSTEM_KEYS: [&str; 2] = ["Reset", "Power"];
+
+/// BizHawk's digital stick directions, which push the stick all the way, with their mnemonics.
+/// These are logged before the buttons.
+const STICK_KEYS: [(&str, char, i8, i8); 4] = [
+    ("A Up", 'U', 0, 127),
+    ("A Down", 'D', 0, -128),
+    ("A Left", 'L', -128, 0),
+    ("A Right", 'R', 127, 0),
+];
+
+/// Digital controls, in the order BizHawk logs them, with their mnemonics.
+const BUTTON_KEYS: [(&str, char, ButtonFlags); 14] = [
+    ("DPad U", 'U', ButtonFlags::D_UP),
+    ("DPad D", 'D', ButtonFlags::D_DOWN),
+    ("DPad L", 'L', ButtonFlags::D_LEFT),
+    ("DPad R", 'R', ButtonFlags::D_RIGHT),
+    ("Start", 'S', ButtonFlags::START),
+    ("Z", 'Z', ButtonFlags::Z),
+    ("B", 'B', ButtonFlags::B),
+    ("A", 'A', ButtonFlags::A),
+    ("C Up", 'u', ButtonFlags::C_UP),
+    ("C Down", 'd', ButtonFlags::C_DOWN),
+    ("C Right", 'r', ButtonFlags::C_RIGHT),
+    ("C Left", 'l', ButtonFlags::C_LEFT),
+    ("L", 'L', ButtonFlags::L),
+    ("R", 'R', ButtonFlags::R),
+];
+
+/// A single control named in the `LogKey`.
+#[derive(Debug, Clone, Copy)]
+enum Control {
+    XAxis,
+    YAxis,
+    Button(ButtonFlags),
+    /// One of [`STICK_KEYS`].
+    StickDigital {
+        x: i8,
+        y: i8,
+    },
+    Other,
+}
+
+impl Control {
+    fn parse(name: &str) -> (Option<usize>, Control) {
+        let Some((port, control)) = name
+            .strip_prefix('P')
+            .and_then(|rest| rest.split_once(' '))
+            .and_then(|(port, control)| Some((port.parse::<usize>().ok()?, control)))
+            .filter(|&(port, _)| (1..=4).contains(&port))
+        else {
+            return (None, Control::Other);
+        };
+
+        let control = match control {
+            "X Axis" => Control::XAxis,
+            "Y Axis" => Control::YAxis,
+            other => STICK_KEYS
+                .iter()
+                .find(|(key, ..)| *key == other)
+                .map(|&(_, _, x, y)| Control::StickDigital { x, y })
+                .or_else(|| {
+                    BUTTON_KEYS
+                        .iter()
+                        .find(|(key, _, _)| *key == other)
+                        .map(|&(_, _, flag)| Control::Button(flag))
+                })
+                .unwrap_or(Control::Other),
+        };
+        (Some(port - 1), control)
+    }
+
+    fn is_axis(name: &str) -> bool {
+        name.ends_with(" Axis")
+    }
+}
+
+impl M64File {
+    /// Reads a BizHawk .bk2 movie recorded on the N64 core.
+    pub fn read_bk2<R: Read + Seek>(reader: R) -> Result<Self, Bk2Error> {
+        Self::read_bk2_with_lag(reader).map(|(movie, _)| movie)
+    }
+
+    /// Reads a BizHawk .bk2 movie recorded on the N64 core, along with its lag log if it has
+    /// one. Lagged lines are left out of the movie's inputs.
+    pub fn read_bk2_with_lag<R: Read + Seek>(
+        reader: R,
+    ) -> Result<(Self, Option<Vec<bool>>), Bk2Error> {
+        let mut archive = ZipArchive::new(reader)?;
+
+        let header_text = read_text(&mut archive, HEADER_FILE)?;
+        let fields: HashMap<String, &str> = header_text
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(key, value)| (key.to_ascii_lowercase(), value.trim()))
+            .collect();
+
+        match fields.get("platform") {
+            Some(&PLATFORM) => (),
+            Some(other) => return Err(Bk2Error::WrongPlatform(other.to_string())),
+            None => return Err(Bk2Error::WrongPlatform(String::new())),
+        }
+        if fields
+            .get("startsfromsavestate")
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            return Err(Bk2Error::UnsupportedStart(StartType::FROM_SNAPSHOT));
+        }
+        if fields
+            .get("startsfromsaveram")
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            return Err(Bk2Error::UnsupportedStart(StartType::FROM_EEPROM));
+        }
+
+        let mut header = M64Header {
+            start_flags: StartType::FROM_RESET,
+            ..Default::default()
+        };
+        if let Some(author) = fields.get("author") {
+            header.author.try_write(fit(author, 222))?;
+        }
+        if let Some(name) = fields.get("gamename") {
+            let name: String = name.chars().filter(char::is_ascii).collect();
+            header.rom_name.try_write(fit(&name, 32))?;
+        }
+        if let Some(count) = fields.get("rerecordcount").and_then(|v| v.parse().ok()) {
+            header.set_rerecords(count);
+        }
+        if fields
+            .get("pal")
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            header.vis_per_second = 50;
+        }
+        if archive.index_for_name(COMMENTS_FILE).is_some() {
+            let comments = read_text(&mut archive, COMMENTS_FILE)?;
+            let comments = comments
+                .lines()
+                .map(|line| line.strip_prefix("comment ").unwrap_or(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            header.description.try_write(fit(&comments, 256))?;
+        }
+
+        let lag_log = match archive.index_for_name(LAG_LOG_FILE) {
+            Some(_) => Some(parse_lag_log(&read_text(&mut archive, LAG_LOG_FILE)?)?),
+            None => None,
+        };
+
+        let input_log = read_text(&mut archive, INPUT_LOG_FILE)?;
+        let mut lines = input_log.lines().enumerate();
+
+        // Parse the LogKey into groups of controls.
+        let groups: Vec<Vec<(Option<usize>, Control, bool)>> = lines
+            .find_map(|(_, line)| line.strip_prefix("LogKey:"))
+            .ok_or(Bk2Error::MissingLogKey)?
+            .split('#')
+            .filter(|group| !group.is_empty())
+            .map(|group| {
+                group
+                    .split('|')
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        let (port, control) = Control::parse(name);
+                        (port, control, Control::is_axis(name))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut ports = [false; 4];
+        for &(port, _, _) in groups.iter().flatten() {
+            if let Some(port) = port {
+                ports[port] = true;
+            }
+        }
+        let mut flags = ControllerFlags::empty();
+        for (port, present) in ports.iter().enumerate() {
+            if *present {
+                flags |= ControllerFlags::from_bits_retain(1 << port);
+            }
+        }
+        if flags.is_empty() {
+            return Err(Bk2Error::NoControllers);
+        }
+        header.controller_flags = flags;
+        header.num_controllers = flags.num_present() as u8;
+
+        let mut inputs = Vec::new();
+        let mut num_lines = 0usize;
+        for (index, line) in lines {
+            let line = line.trim_end();
+            if line.starts_with('[') || line.is_empty() {
+                continue;
+            }
+            let frame_index = num_lines;
+            num_lines += 1;
+            // The game didn't poll for input on lagged lines.
+            if lag_log
+                .as_ref()
+                .is_some_and(|lag_log| lag_log.get(frame_index) == Some(&true))
+            {
+                continue;
+            }
+            let bad_line = || Bk2Error::BadInputLine { line: index + 1 };
+
+            let mut frame = [Buttons::BLANK; 4];
+            let values: Vec<&str> = line
+                .strip_prefix('|')
+                .and_then(|line| line.strip_suffix('|'))
+                .ok_or_else(bad_line)?
+                .split('|')
+                .collect();
+            if values.len() != groups.len() {
+                return Err(bad_line());
+            }
+
+            for (group, value) in groups.iter().zip(values) {
+                let num_axes = group.iter().filter(|(_, _, is_axis)| *is_axis).count();
+                let mut parts = value.splitn(num_axes + 1, ',');
+                let mut axes = parts
+                    .by_ref()
+                    .take(num_axes)
+                    .collect::<Vec<_>>()
+                    .into_iter();
+                let mut buttons = parts.next().unwrap_or("").chars();
+
+                for &(port, control, is_axis) in group {
+                    let (axis, pressed) = if is_axis {
+                        let axis: i32 = axes
+                            .next()
+                            .and_then(|value| value.trim().parse().ok())
+                            .ok_or_else(bad_line)?;
+                        (axis.clamp(i8::MIN as i32, i8::MAX as i32) as i8, false)
+                    } else {
+                        (0, buttons.next().ok_or_else(bad_line)? != '.')
+                    };
+                    let Some(sample) = port.map(|port| &mut frame[port]) else {
+                        continue;
+                    };
+                    match control {
+                        Control::XAxis => sample.x_axis = axis,
+                        Control::YAxis => sample.y_axis = axis,
+                        Control::Button(flag) => sample.button_bits.set(flag, pressed),
+                        Control::StickDigital { x, y } if pressed => {
+                            if x != 0 {
+                                sample.x_axis = x;
+                            }
+                            if y != 0 {
+                                sample.y_axis = y;
+                            }
+                        }
+                        Control::StickDigital { .. } | Control::Other => (),
+                    }
+                }
+            }
+
+            inputs.extend((0..4).filter(|&port| ports[port]).map(|port| frame[port]));
+        }
+
+        // Each line is one VI.
+        header.length_vis = num_lines.try_into().unwrap_or(u32::MAX);
+        header.length_samples = inputs.len().try_into().unwrap_or(u32::MAX);
+
+        Ok((Self { header, inputs }, lag_log))
+    }
+
+    /// Writes this movie as a BizHawk .bk2 movie for the N64 core. Only movies that start
+    /// from a reset can be converted.
+    pub fn write_bk2<W: Write + Seek>(&self, writer: W) -> Result<(), Bk2Error> {
+        self.write_bk2_with_lag(writer, None)
+    }
+
+    /// Writes this movie as a BizHawk .bk2 movie for the N64 core, inserting a line for each
+    /// VI marked as lagged in `lag_log`. The lines inserted repeat the previous frame's input.
+    /// Frames past the end of the lag log get one line each.
+    pub fn write_bk2_with_lag<W: Write + Seek>(
+        &self,
+        writer: W,
+        lag_log: Option<&[bool]>,
+    ) -> Result<(), Bk2Error> {
+        let header = &self.header;
+        if header.start_flags != StartType::FROM_RESET {
+            return Err(Bk2Error::UnsupportedStart(header.start_flags));
+        }
+        let ports: Vec<usize> = (0..4)
+            .filter(|&port| header.controller_flags.port_present(port as c_int))
+            .collect();
+        if ports.is_empty() {
+            return Err(Bk2Error::NoControllers);
+        }
+
+        let mut header_text = String::new();
+        writeln!(header_text, "MovieVersion BizHawk v2.0.0").unwrap();
+        writeln!(header_text, "Author {}", header.author.try_read()?).unwrap();
+        writeln!(header_text, "emuVersion m64prs").unwrap();
+        writeln!(header_text, "Platform {}", PLATFORM).unwrap();
+        writeln!(header_text, "GameName {}", header.rom_name.try_read()?).unwrap();
+        writeln!(header_text, "rerecordCount {}", header.rerecords()).unwrap();
+        writeln!(header_text, "Core Mupen64Plus").unwrap();
+        if header.vis_per_second == 50 {
+            writeln!(header_text, "PAL True").unwrap();
+        }
+
+        let mut input_log = String::from("[Input]\nLogKey:#");
+        for key in SYSTEM_KEYS {
+            write!(input_log, "{}|", key).unwrap();
+        }
+        for &port in &ports {
+            let player = port + 1;
+            write!(input_log, "#P{} X Axis|P{} Y Axis|", player, player).unwrap();
+            for (key, ..) in STICK_KEYS {
+                write!(input_log, "P{} {}|", player, key).unwrap();
+            }
+            for (key, _, _) in BUTTON_KEYS {
+                write!(input_log, "P{} {}|", player, key).unwrap();
+            }
+        }
+        input_log.push('\n');
+
+        // A partial last frame is padded with blank samples.
+        let mut frames = self.inputs.chunks(ports.len()).map(|frame| {
+            let mut frame = frame.to_vec();
+            frame.resize(ports.len(), Buttons::BLANK);
+            frame
+        });
+        let mut written_lag = Vec::new();
+        let mut previous = vec![Buttons::BLANK; ports.len()];
+        for &lagged in lag_log.unwrap_or_default() {
+            if !lagged {
+                let Some(frame) = frames.next() else {
+                    break;
+                };
+                previous = frame;
+            }
+            write_input_line(&mut input_log, &previous);
+            written_lag.push(lagged);
+        }
+        for frame in frames {
+            write_input_line(&mut input_log, &frame);
+            written_lag.push(false);
+        }
+        input_log.push_str("[/Input]\n");
+
+        let description = header.description.try_read()?;
+
+        let mut archive = ZipWriter::new(writer);
+        let options = SimpleFileOptions::default();
+        archive.start_file(HEADER_FILE, options)?;
+        archive.write_all(header_text.as_bytes())?;
+        archive.start_file(INPUT_LOG_FILE, options)?;
+        archive.write_all(input_log.as_bytes())?;
+        if !description.is_empty() {
+            archive.start_file(COMMENTS_FILE, options)?;
+            for line in description.lines() {
+                writeln!(archive, "comment {}", line)?;
+            }
+        }
+        if lag_log.is_some() {
+            archive.start_file(LAG_LOG_FILE, options)?;
+            writeln!(archive, "{}", write_lag_log(&written_lag))?;
+        }
+        archive.finish()?;
+
+        Ok(())
+    }
+}
+
+/// Writes one line of the input log, holding one sample per present controller.
+fn write_input_line(input_log: &mut String, frame: &[Buttons]) {
+    input_log.push('|');
+    input_log.extend(SYSTEM_KEYS.map(|_| '.'));
+    input_log.push('|');
+    for sample in frame {
+        write!(input_log, "{:5},{:5},", sample.x_axis, sample.y_axis).unwrap();
+        input_log.extend(STICK_KEYS.map(|_| '.'));
+        input_log.extend(BUTTON_KEYS.iter().map(|&(_, mnemonic, flag)| {
+            if sample.button_bits.contains(flag) {
+                mnemonic
+            } else {
+                '.'
+            }
+        }));
+        input_log.push('|');
+    }
+    input_log.push('\n');
+}
+
+/// Parses the first line of a lag log, a JSON object mapping frame numbers to whether the
+/// frame lagged. Frames missing from the object didn't lag.
+fn parse_lag_log(text: &str) -> Result<Vec<bool>, Bk2Error> {
+    let first_line = text.lines().next().unwrap_or_default();
+    let entries: BTreeMap<usize, bool> =
+        serde_json::from_str(first_line).map_err(|_| Bk2Error::BadLagLog)?;
+    let mut lag_log = vec![false; entries.keys().next_back().map_or(0, |&last| last + 1)];
+    for (frame, lagged) in entries {
+        lag_log[frame] = lagged;
+    }
+    Ok(lag_log)
+}
+
+/// Writes a lag log in the form read by [`parse_lag_log`].
+fn write_lag_log(lag_log: &[bool]) -> String {
+    let entries: BTreeMap<usize, bool> = lag_log.iter().copied().enumerate().collect();
+    serde_json::to_string(&entries).unwrap()
+}
+
+fn read_text<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &'static str,
+) -> Result<String, Bk2Error> {
+    let mut file = archive.by_name(name).map_err(|err| match err {
+        zip::result::ZipError::FileNotFound => Bk2Error::MissingFile(name),
+        err => err.into(),
+    })?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Truncates a string to at most `max_len` bytes, without splitting a character.
+fn fit(s: &str, max_len: usize) -> &str {
+    if s.len() <= max_len {
+        return s;
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use m64prs_sys::{ButtonFlags, Buttons};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use crate::movie::{ControllerFlags, M64File, M64Header, StartType};
+
+    fn sample(bits: u16, x_axis: i8, y_axis: i8) -> Buttons {
+        Buttons {
+            button_bits: ButtonFlags::from_bits_retain(bits),
+            x_axis,
+            y_axis,
+        }
+    }
+
+    fn two_player_movie() -> M64File {
+        let mut header = M64Header {
+            start_flags: StartType::FROM_RESET,
+            controller_flags: ControllerFlags::P1_PRESENT | ControllerFlags::P3_PRESENT,
+            num_controllers: 2,
+            ..Default::default()
+        };
+        header.author.try_write("Someone").unwrap();
+        header.rom_name.try_write("SUPER MARIO 64").unwrap();
+        header.set_rerecords(12);
+        let inputs = vec![
+            sample(ButtonFlags::A.bits(), 0, 0),
+            sample(ButtonFlags::START.bits(), -80, 12),
+            sample(ButtonFlags::C_UP.bits() | ButtonFlags::Z.bits(), 127, -128),
+            sample(0, 5, -5),
+        ];
+        header.length_samples = inputs.len() as u32;
+        header.length_vis = 2;
+        M64File { header, inputs }
+    }
+
+    fn export(movie: &M64File, lag_log: Option<&[bool]>) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        movie.write_bk2_with_lag(&mut data, lag_log).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn round_trip() {
+        let movie = two_player_movie();
+        let imported = M64File::read_bk2(Cursor::new(export(&movie, None))).unwrap();
+        let reimported = M64File::read_bk2(Cursor::new(export(&imported, None))).unwrap();
+
+        assert_eq!(imported.inputs, movie.inputs);
+        assert_eq!(
+            imported.header.controller_flags,
+            movie.header.controller_flags
+        );
+        assert_eq!(imported.header.rerecords(), 12);
+        assert_eq!(reimported, imported);
+    }
+
+    #[test]
+    fn round_trip_with_lag() {
+        let movie = two_player_movie();
+        let lag_log = [true, false, true, true, false];
+        let (imported, imported_lag) =
+            M64File::read_bk2_with_lag(Cursor::new(export(&movie, Some(&lag_log)))).unwrap();
+
+        assert_eq!(imported.inputs, movie.inputs);
+        assert_eq!(imported.header.length_vis, lag_log.len() as u32);
+        assert_eq!(imported_lag.as_deref(), Some(&lag_log[..]));
+
+        let (reimported, reimported_lag) =
+            M64File::read_bk2_with_lag(Cursor::new(export(&imported, imported_lag.as_deref())))
+                .unwrap();
+        assert_eq!(reimported, imported);
+        assert_eq!(reimported_lag, imported_lag);
+    }
+
+    #[test]
+    fn lag_lines_are_dropped() {
+        let mut data = Cursor::new(Vec::new());
+        let mut archive = ZipWriter::new(&mut data);
+        let options = SimpleFileOptions::default();
+        archive.start_file("Header.txt", options).unwrap();
+        archive.write_all(b"Platform N64\n").unwrap();
+        archive.start_file("Input Log.txt", options).unwrap();
+        archive
+            .write_all(
+                b"[Input]\n\
+                LogKey:#Reset|Power|#P1 X Axis|P1 Y Axis|P1 A Up|P1 DPad U|P1 A|\n\
+                |..|    1,    2,...|\n\
+                |..|    9,    9,..A|\n\
+                |..|    0,    0,U.A|\n\
+                [/Input]\n",
+            )
+            .unwrap();
+        archive.start_file("LagLog", options).unwrap();
+        archive.write_all(b"{\"1\":true}\n{}\n").unwrap();
+        archive.finish().unwrap();
+
+        let (movie, lag_log) = M64File::read_bk2_with_lag(Cursor::new(data.into_inner())).unwrap();
+        assert_eq!(lag_log.as_deref(), Some(&[false, true][..]));
+        assert_eq!(
+            movie.inputs,
+            [sample(0, 1, 2), sample(ButtonFlags::A.bits(), 0, 127)]
+        );
+        assert_eq!(movie.header.length_vis, 3);
+    }
+
+    #[test]
+    fn partial_frame_is_padded() {
+        let mut movie = two_player_movie();
+        movie.inputs.pop();
+        let imported = M64File::read_bk2(Cursor::new(export(&movie, None))).unwrap();
+
+        movie.inputs.push(Buttons::BLANK);
+        assert_eq!(imported.inputs, movie.inputs);
+    }
+}
@@ -1,7 +1,9 @@
-use std::str::Utf8Error;
+use std::{io, str::Utf8Error};
 
 use thiserror::Error;
 
+use super::StartType;
+
 #[derive(Debug, Error)]
 pub enum StringFieldError {
     #[error("UTF-8 validation of field failed")]
@@ -11,3 +13,66 @@ pub enum StringFieldError {
     #[error("Value is too long, max length is {max_len} bytes")]
     FieldTooLong { max_len: usize },
 }
+
+/// Errors that can occur while reading an .m64 file.
+#[derive(Debug, Error)]
+pub enum MovieError {
+    #[error("signature doesn't match ({0:x?})")]
+    BadMagic([u8; 4]),
+    #[error("unsupported .m64 version {0}")]
+    UnsupportedVersion(u32),
+    #[error("header says there are {expected} input samples, but only {found} are present")]
+    TruncatedInputs { expected: u32, found: u32 },
+    #[error("{0} bytes of unexpected data after the last input sample")]
+    TrailingData(usize),
+    #[error("invalid start flags ({0:#06x})")]
+    InvalidStartFlags(u16),
+    #[error("I/O error while reading movie")]
+    Io(#[from] io::Error),
+}
+
+impl MovieError {
+    /// Returns true if the error can be fixed by reading the movie in lenient mode.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            MovieError::TruncatedInputs { .. } | MovieError::TrailingData(_)
+        )
+    }
+}
+
+/// Errors that can occur while converting between .m64 and BizHawk .bk2 movies.
+#[derive(Debug, Error)]
+pub enum Bk2Error {
+    #[error("archive is missing {0}")]
+    MissingFile(&'static str),
+    #[error("movie is for platform {0:?}, not N64")]
+    WrongPlatform(String),
+    #[error("input log has no LogKey")]
+    MissingLogKey,
+    #[error("malformed input on line {line} of the input log")]
+    BadInputLine { line: usize },
+    #[error("movies with start type {0:?} can't be converted")]
+    UnsupportedStart(StartType),
+    #[error("movie has no controllers")]
+    NoControllers,
+    #[error("malformed lag log")]
+    BadLagLog,
+    #[error("invalid header field")]
+    Field(#[from] StringFieldError),
+    #[error("failed to read or write .bk2 archive")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("I/O error while converting movie")]
+    Io(#[from] io::Error),
+}
+
+/// Errors that can occur while reading a movie in the text format.
+#[derive(Debug, Error)]
+pub enum TextMovieError {
+    #[error("line {line}: {reason}")]
+    Syntax { line: usize, reason: String },
+    #[error("movie has no [inputs] section")]
+    MissingInputs,
+    #[error("I/O error while reading or writing text movie")]
+    Io(#[from] io::Error),
+}
@@ -1,16 +1,19 @@
+use error::MovieError;
 use futures::{AsyncRead, AsyncReadExt};
 use helpers::fix_buttons_order;
-use m64prs_sys::Buttons;
+use m64prs_sys::{ButtonFlags, Buttons};
 use std::{
     ffi::c_int,
-    fmt::Debug,
+    fmt::{Debug, Display},
     io::{self, Read, Write},
     mem::{self},
     pin::{pin, Pin},
 };
 
+mod bk2;
 pub mod error;
 mod helpers;
+mod text;
 
 pub use helpers::{AsciiField, StringField};
 
@@ -27,13 +30,15 @@ pub struct M64Header {
     pub uid: u32,
     /// Time needed to play the movie, in VIs (vertical interrupts).
     pub length_vis: u32,
-    /// Number of rerecords for this movie.
+    /// Number of rerecords for this movie. If the movie has an extended header, this
+    /// only holds the low 32 bits; use [`M64Header::rerecords`] to get the full count.
     pub rerecord_count: u32,
     /// Framerate of the console. Generally 60 on NTSC consoles and 50 on PAL.
     pub vis_per_second: u8,
     /// Number of controllers connected for this.
     pub num_controllers: u8,
-    _reserved1: u16,
+    extended_version: u8,
+    extended_flags: ExtendedFlags,
     /// Number of input frames in the movie.
     pub length_samples: u32,
     /// How the movie should be started.
@@ -41,7 +46,8 @@ pub struct M64Header {
     _reserved2: u16,
     /// Flags specifying which controllers and which attachments are connected.
     pub controller_flags: ControllerFlags,
-    _reserved3: [u8; 160],
+    extended_data: ExtendedData,
+    _reserved3: [u8; 128],
     /// Internal name of the ROM used to record this movie.
     pub rom_name: AsciiField<32>,
     /// ROM's CRC32, ripped from its header.
@@ -74,9 +80,12 @@ const _: () = {
     assert!(mem::offset_of!(M64Header, rerecord_count) == 0x010);
     assert!(mem::offset_of!(M64Header, vis_per_second) == 0x014);
     assert!(mem::offset_of!(M64Header, num_controllers) == 0x015);
+    assert!(mem::offset_of!(M64Header, extended_version) == 0x016);
+    assert!(mem::offset_of!(M64Header, extended_flags) == 0x017);
     assert!(mem::offset_of!(M64Header, length_samples) == 0x018);
     assert!(mem::offset_of!(M64Header, start_flags) == 0x01C);
     assert!(mem::offset_of!(M64Header, controller_flags) == 0x020);
+    assert!(mem::offset_of!(M64Header, extended_data) == 0x024);
     assert!(mem::offset_of!(M64Header, rom_name) == 0x0C4);
     assert!(mem::offset_of!(M64Header, rom_crc) == 0x0E4);
     assert!(mem::offset_of!(M64Header, rom_cc) == 0x0E8);
@@ -95,15 +104,17 @@ impl Default for M64Header {
             version: 3,
             uid: 0,
             length_vis: u32::MAX,
-            rerecord_count: u32::MAX,
+            rerecord_count: 0,
             vis_per_second: 60,
             num_controllers: 1,
-            _reserved1: 0,
+            extended_version: 0,
+            extended_flags: ExtendedFlags::empty(),
             length_samples: 0,
             start_flags: StartType::FROM_SNAPSHOT,
             _reserved2: 0,
             controller_flags: ControllerFlags::P1_PRESENT,
-            _reserved3: [0; 160],
+            extended_data: ExtendedData::default(),
+            _reserved3: [0; 128],
             rom_name: Default::default(),
             rom_crc: 0,
             rom_cc: 0,
@@ -119,14 +130,14 @@ impl Default for M64Header {
 }
 
 impl M64Header {
-    fn from_bytes(slice: [u8; 1024]) -> Self {
+    pub(crate) fn from_bytes(slice: [u8; 1024]) -> Self {
         // SAFETY: All fields, including padding, do not technically have invalid values.
         let mut result = unsafe { mem::transmute::<[u8; 1024], Self>(slice) };
 
         // Fix endianness of integer fields
         macro_rules! fix_field {
-            ($name:ident) => {
-                result.$name = result.$name.to_le()
+            ($($name:ident).+) => {
+                result.$($name).+ = result.$($name).+.to_le()
             };
             (newtype $name:ident) => {
                 result.$name.0 = result.$name.0.to_le()
@@ -143,17 +154,19 @@ impl M64Header {
         fix_field!(length_samples);
         fix_field!(newtype start_flags);
         fix_field!(bitflag controller_flags);
+        fix_field!(extended_data.bruteforce_extra_data);
+        fix_field!(extended_data.rerecord_count_high);
         fix_field!(rom_crc);
         fix_field!(rom_cc);
 
         result
     }
 
-    fn into_bytes(mut self) -> [u8; 1024] {
+    pub(crate) fn into_bytes(mut self) -> [u8; 1024] {
         // Un-fix endianness of integer fields.
         macro_rules! fix_field {
-            ($name:ident) => {
-                self.$name = self.$name.to_le()
+            ($($name:ident).+) => {
+                self.$($name).+ = self.$($name).+.to_le()
             };
             (newtype $name:ident) => {
                 self.$name.0 = self.$name.0.to_le()
@@ -170,6 +183,8 @@ impl M64Header {
         fix_field!(length_samples);
         fix_field!(newtype start_flags);
         fix_field!(bitflag controller_flags);
+        fix_field!(extended_data.bruteforce_extra_data);
+        fix_field!(extended_data.rerecord_count_high);
         fix_field!(rom_crc);
         fix_field!(rom_cc);
 
@@ -189,6 +204,98 @@ impl M64Header {
         reader.read_exact(&mut chunk).await?;
         Ok(Self::from_bytes(chunk))
     }
+
+    /// Returns the Mupen64-rr-lua extended header fields, if this movie has them.
+    pub fn extended(&self) -> Option<ExtendedInfo> {
+        (self.extended_version != 0).then_some(ExtendedInfo {
+            version: self.extended_version,
+            flags: self.extended_flags,
+            authorship_tag: self.extended_data.authorship_tag,
+            bruteforce_extra_data: self.extended_data.bruteforce_extra_data,
+        })
+    }
+
+    /// Sets or clears the Mupen64-rr-lua extended header fields.
+    ///
+    /// Clearing the extended fields clamps the rerecord count to 32 bits.
+    pub fn set_extended(&mut self, info: Option<ExtendedInfo>) {
+        let rerecords = self.rerecords();
+        match info {
+            Some(info) => {
+                self.extended_version = info.version.max(1);
+                self.extended_flags = info.flags;
+                self.extended_data.authorship_tag = info.authorship_tag;
+                self.extended_data.bruteforce_extra_data = info.bruteforce_extra_data;
+                // Other programs may use the unused data differently.
+                if info.authorship_tag != ExtendedInfo::M64PRS_TAG {
+                    self.extended_data.m64prs_flags = M64prsFlags::empty();
+                }
+            }
+            None => {
+                self.extended_version = 0;
+                self.extended_flags = ExtendedFlags::empty();
+                self.extended_data = ExtendedData::default();
+            }
+        }
+        self.set_rerecords(rerecords);
+    }
+
+    /// Returns the m64prs-specific flags, if the movie carries m64prs's authorship tag.
+    fn m64prs_flags(&self) -> Option<M64prsFlags> {
+        self.extended()
+            .filter(|ext| ext.authorship_tag == ExtendedInfo::M64PRS_TAG)
+            .map(|_| self.extended_data.m64prs_flags)
+    }
+
+    /// Returns true if the movie was recorded with memory modified outside of the game.
+    pub fn modified_memory(&self) -> bool {
+        self.m64prs_flags()
+            .is_some_and(|flags| flags.contains(M64prsFlags::MODIFIED_MEMORY))
+    }
+
+    /// Flags the movie as recorded with memory modified outside of the game, adding the
+    /// extended header fields and m64prs's authorship tag if needed.
+    ///
+    /// # Return value
+    /// False if the movie carries another program's authorship tag, in which case the flag
+    /// can't be stored.
+    pub fn set_modified_memory(&mut self) -> bool {
+        let mut info = self.extended().unwrap_or_default();
+        match info.authorship_tag {
+            ExtendedInfo::M64PRS_TAG => (),
+            [0, 0, 0, 0] => info.authorship_tag = ExtendedInfo::M64PRS_TAG,
+            _ => return false,
+        }
+        self.set_extended(Some(info));
+        self.extended_data.m64prs_flags |= M64prsFlags::MODIFIED_MEMORY;
+        true
+    }
+
+    /// Clears the flag set by [`M64Header::set_modified_memory`].
+    pub fn clear_modified_memory(&mut self) {
+        self.extended_data.m64prs_flags -= M64prsFlags::MODIFIED_MEMORY;
+    }
+
+    /// Returns the full rerecord count, including the high word stored in the extended header.
+    pub fn rerecords(&self) -> u64 {
+        match self.extended_version {
+            0 => self.rerecord_count as u64,
+            _ => {
+                ((self.extended_data.rerecord_count_high as u64) << 32) | self.rerecord_count as u64
+            }
+        }
+    }
+
+    /// Sets the full rerecord count. Without an extended header, the count saturates at [`u32::MAX`].
+    pub fn set_rerecords(&mut self, value: u64) {
+        match self.extended_version {
+            0 => self.rerecord_count = value.try_into().unwrap_or(u32::MAX),
+            _ => {
+                self.rerecord_count = value as u32;
+                self.extended_data.rerecord_count_high = (value >> 32) as u32;
+            }
+        }
+    }
 }
 
 impl Debug for M64Header {
@@ -201,9 +308,12 @@ impl Debug for M64Header {
             .field("rerecord_count", &self.rerecord_count)
             .field("vis_per_second", &self.vis_per_second)
             .field("num_controllers", &self.num_controllers)
+            .field("extended_version", &self.extended_version)
+            .field("extended_flags", &self.extended_flags)
             .field("length_samples", &self.length_samples)
             .field("start_flags", &self.start_flags)
             .field("controller_flags", &self.controller_flags)
+            .field("extended_data", &self.extended_data)
             .field("rom_name", &self.rom_name)
             .field("rom_crc", &self.rom_crc)
             .field("rom_cc", &self.rom_cc)
@@ -217,6 +327,44 @@ impl Debug for M64Header {
     }
 }
 
+/// A reset stored in a movie. As in Mupen64-rr, resets are stored in the reserved button bits
+/// of the first sample of the frame they happen on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResetKind {
+    Soft,
+    Hard,
+}
+
+impl ResetKind {
+    /// All button bits used to store resets.
+    pub const FLAGS: ButtonFlags = ButtonFlags::RESERVED1.union(ButtonFlags::RESERVED2);
+
+    /// Returns the button bit used to store this kind of reset.
+    pub fn flag(self) -> ButtonFlags {
+        match self {
+            ResetKind::Soft => ButtonFlags::RESERVED1,
+            ResetKind::Hard => ButtonFlags::RESERVED2,
+        }
+    }
+
+    /// Returns the reset stored in `sample`, if any. A hard reset takes priority if both
+    /// bits are set.
+    pub fn from_sample(sample: Buttons) -> Option<Self> {
+        if sample.button_bits.contains(ButtonFlags::RESERVED2) {
+            Some(ResetKind::Hard)
+        } else if sample.button_bits.contains(ButtonFlags::RESERVED1) {
+            Some(ResetKind::Soft)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true for a hard reset.
+    pub fn is_hard(self) -> bool {
+        self == ResetKind::Hard
+    }
+}
+
 /// Value indicating how the .m64 file is to be started.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct StartType(pub u16);
@@ -281,6 +429,88 @@ impl ControllerFlags {
 
         self.contains(Self::from_bits_retain((1 << 8) << port))
     }
+    /// Returns the number of ports with a controller connected. This is
+    /// the number of input samples stored per input frame.
+    pub fn num_present(self) -> u32 {
+        (self.bits() & 0xF).count_ones()
+    }
+    /// Returns the bits of the ports with a controller connected, one bit per port.
+    pub fn present_mask(self) -> u8 {
+        (self.bits() & 0xF) as u8
+    }
+    /// Returns the position of this port's sample within an input frame,
+    /// i.e. the number of present ports before it.
+    pub fn port_rank(self, port: c_int) -> u32 {
+        assert!((0..4).contains(&port));
+
+        (self.bits() & 0xF & ((1 << port) - 1)).count_ones()
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags stored in the extended header.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ExtendedFlags: u8 {
+        /// The movie was recorded with Wii Virtual Console timings.
+        const WII_VC = 1 << 0;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags specific to m64prs, stored in the unused extended data of movies carrying
+    /// m64prs's authorship tag.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    struct M64prsFlags: u8 {
+        /// Memory was modified outside of the game while recording (e.g. by frozen values
+        /// or cheats), so the movie can't be verified.
+        const MODIFIED_MEMORY = 1 << 0;
+    }
+}
+
+/// Extra data stored in the extended header, in place of some reserved bytes.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+struct ExtendedData {
+    authorship_tag: [u8; 4],
+    bruteforce_extra_data: u32,
+    rerecord_count_high: u32,
+    /// Only meaningful if `authorship_tag` is [`ExtendedInfo::M64PRS_TAG`].
+    m64prs_flags: M64prsFlags,
+    _unused: [u8; 19],
+}
+
+const _: () = assert!(mem::size_of::<ExtendedData>() == 32);
+
+/// Typed view of the extended header fields introduced by Mupen64-rr-lua.
+/// Movies without these fields have an extended version of 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtendedInfo {
+    /// Version of the extended format. Always nonzero.
+    pub version: u8,
+    /// Extended flags.
+    pub flags: ExtendedFlags,
+    /// Tag identifying the program (or person) that authored the movie.
+    pub authorship_tag: [u8; 4],
+    /// Additional data used by bruteforcing tools.
+    pub bruteforce_extra_data: u32,
+}
+
+impl ExtendedInfo {
+    /// The latest extended format version.
+    pub const CURRENT_VERSION: u8 = 1;
+    /// Authorship tag of movies recorded by m64prs.
+    pub const M64PRS_TAG: [u8; 4] = *b"m64p";
+}
+
+impl Default for ExtendedInfo {
+    fn default() -> Self {
+        Self {
+            version: Self::CURRENT_VERSION,
+            flags: ExtendedFlags::empty(),
+            authorship_tag: [0; 4],
+            bruteforce_extra_data: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -289,98 +519,144 @@ pub struct M64File {
     pub inputs: Vec<Buttons>,
 }
 
-impl M64File {
-    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
-        let header = {
-            // Try to read the header (exactly 1024 bytes)
-            let mut buffer = [0u8; mem::size_of::<M64Header>()];
-            reader.read_exact(&mut buffer)?;
-            // Check signature
-            if buffer[0..4] != M64_MAGIC {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!(".m64: signature doesn't match ({:x?})", &buffer[0..4]),
-                ));
-            }
-            // Parse the header
-            M64Header::from_bytes(buffer)
-        };
-        let inputs = {
-            // Compute buffer sizes
-            let buffer_size: usize = header.length_samples.try_into().unwrap();
-            let buffer_byte_size: usize =
-                buffer_size.checked_mul(mem::size_of::<Buttons>()).unwrap();
-
-            // Read remaining bytes, it should all be input data
-            let input_bytes = {
-                let mut buffer = Vec::<u8>::new();
-                reader.read_to_end(&mut buffer)?;
-                buffer.into_boxed_slice()
-            };
+/// A fix applied while reading a malformed .m64 file in lenient mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MovieRepair {
+    /// The header listed more input samples than the file contains. The
+    /// sample count was reduced to match.
+    TruncatedInputs { expected: u32, found: u32 },
+    /// The file contains more input samples than the header lists. The
+    /// extra samples were kept and the sample count was increased to match.
+    ExtraInputs { expected: u32, found: u32 },
+    /// The file ends with an incomplete input sample, which was dropped.
+    TrailingData(usize),
+}
 
-            // Ensure we have as many samples as the header says we do
-            if input_bytes.len() < buffer_byte_size {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    ".m64: not enough input frames",
-                ));
+impl Display for MovieRepair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovieRepair::TruncatedInputs { expected, found } => write!(
+                f,
+                "header listed {} input samples, but only {} were present",
+                expected, found
+            ),
+            MovieRepair::ExtraInputs { expected, found } => write!(
+                f,
+                "header listed {} input samples, but {} were present",
+                expected, found
+            ),
+            MovieRepair::TrailingData(len) => {
+                write!(f, "dropped {} bytes of incomplete input data", len)
             }
-            // Setup the input vector. Ideally it should not need to be zeroed.
-            let mut buffer = vec![Buttons::BLANK; buffer_size];
+        }
+    }
+}
 
-            // Copy the input bytes directly into the buttons and fix any endianness issues.
-            unsafe {
-                // SAFETY: Buttons has no invalid values.
-                let bytes = buffer.align_to_mut::<u8>().1;
-                bytes.copy_from_slice(&input_bytes[0..buffer_byte_size]);
-            }
-            fix_buttons_order(&mut buffer);
+impl M64File {
+    /// The only .m64 version currently supported.
+    pub const SUPPORTED_VERSION: u32 = 3;
+
+    /// Reads an .m64 file, rejecting any file that doesn't match its header.
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, MovieError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::parse(&data, false).map(|(file, _)| file)
+    }
 
-            buffer
-        };
+    /// Reads an .m64 file, repairing truncated or over-long input data if needed.
+    /// Returns the file, as well as a list of repairs that were applied.
+    pub fn read_from_lenient<R: Read>(
+        mut reader: R,
+    ) -> Result<(Self, Vec<MovieRepair>), MovieError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::parse(&data, true)
+    }
 
-        Ok(Self { header, inputs })
+    /// Asynchronous version of [`M64File::read_from`].
+    pub async fn read_from_async<R: AsyncRead>(reader: R) -> Result<Self, MovieError> {
+        let mut data = Vec::new();
+        pin!(reader).read_to_end(&mut data).await?;
+        Self::parse(&data, false).map(|(file, _)| file)
     }
 
-    pub async fn read_from_async<R: futures::AsyncRead>(mut reader: R) -> io::Result<Self> {
-        use futures::AsyncReadExt;
+    /// Asynchronous version of [`M64File::read_from_lenient`].
+    pub async fn read_from_async_lenient<R: AsyncRead>(
+        reader: R,
+    ) -> Result<(Self, Vec<MovieRepair>), MovieError> {
+        let mut data = Vec::new();
+        pin!(reader).read_to_end(&mut data).await?;
+        Self::parse(&data, true)
+    }
 
-        let mut reader = pin!(reader);
+    fn parse(data: &[u8], lenient: bool) -> Result<(Self, Vec<MovieRepair>), MovieError> {
+        const HEADER_SIZE: usize = mem::size_of::<M64Header>();
+        const SAMPLE_SIZE: usize = mem::size_of::<Buttons>();
 
-        let header = {
+        let mut repairs = Vec::new();
+
+        let mut header = {
             // Try to read the header (exactly 1024 bytes)
-            let mut buffer = [0u8; mem::size_of::<M64Header>()];
-            reader.read_exact(&mut buffer).await?;
+            let buffer: [u8; HEADER_SIZE] = data
+                .get(..HEADER_SIZE)
+                .and_then(|slice| slice.try_into().ok())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
             // Check signature
             if buffer[0..4] != M64_MAGIC {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!(".m64: signature doesn't match ({:x?})", &buffer[0..4]),
-                ));
+                return Err(MovieError::BadMagic(buffer[0..4].try_into().unwrap()));
             }
             // Parse the header
             M64Header::from_bytes(buffer)
         };
+
+        if header.version != Self::SUPPORTED_VERSION {
+            return Err(MovieError::UnsupportedVersion(header.version));
+        }
+        if ![
+            StartType::FROM_SNAPSHOT,
+            StartType::FROM_RESET,
+            StartType::FROM_EEPROM,
+        ]
+        .contains(&header.start_flags)
+        {
+            return Err(MovieError::InvalidStartFlags(header.start_flags.0));
+        }
+
         let inputs = {
-            // Compute buffer sizes
-            let buffer_size: usize = header.length_samples.try_into().unwrap();
-            let buffer_byte_size: usize =
-                buffer_size.checked_mul(mem::size_of::<Buttons>()).unwrap();
-
-            // Read remaining bytes, it should all be input data
-            let input_bytes = {
-                let mut buffer = Vec::<u8>::new();
-                reader.read_to_end(&mut buffer).await?;
-                buffer.into_boxed_slice()
-            };
+            // Everything after the header should be input data
+            let input_bytes = &data[HEADER_SIZE..];
+            let trailing = input_bytes.len() % SAMPLE_SIZE;
+            let expected = header.length_samples;
+            let found: u32 = (input_bytes.len() / SAMPLE_SIZE)
+                .try_into()
+                .unwrap_or(u32::MAX);
 
             // Ensure we have as many samples as the header says we do
-            if input_bytes.len() < buffer_byte_size {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    ".m64: not enough input frames",
-                ));
+            if found < expected {
+                if !lenient {
+                    return Err(MovieError::TruncatedInputs { expected, found });
+                }
+                repairs.push(MovieRepair::TruncatedInputs { expected, found });
+                header.length_samples = found;
+            } else if found > expected {
+                if !lenient {
+                    return Err(MovieError::TrailingData(
+                        input_bytes.len() - expected as usize * SAMPLE_SIZE,
+                    ));
+                }
+                repairs.push(MovieRepair::ExtraInputs { expected, found });
+                header.length_samples = found;
+            }
+            if trailing != 0 {
+                if !lenient {
+                    return Err(MovieError::TrailingData(trailing));
+                }
+                repairs.push(MovieRepair::TrailingData(trailing));
             }
+
+            let buffer_size: usize = header.length_samples as usize;
+            let buffer_byte_size = buffer_size * SAMPLE_SIZE;
+
             // Setup the input vector. Ideally it should not need to be zeroed.
             let mut buffer = vec![Buttons::BLANK; buffer_size];
 
@@ -395,7 +671,7 @@ impl M64File {
             buffer
         };
 
-        Ok(Self { header, inputs })
+        Ok((Self { header, inputs }, repairs))
     }
 
     pub fn write_into<W: Write>(self, writer: &mut W) -> io::Result<()> {
@@ -432,3 +708,37 @@ impl M64File {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{ExtendedFlags, ExtendedInfo, M64Header};
+
+    #[test]
+    fn modified_memory_claims_empty_tag() {
+        let mut header = M64Header::default();
+        assert!(header.set_modified_memory());
+
+        let header = M64Header::from_bytes(header.into_bytes());
+        let ext = header.extended().unwrap();
+        assert_eq!(ext.authorship_tag, ExtendedInfo::M64PRS_TAG);
+        assert_eq!(ext.flags, ExtendedFlags::empty());
+        assert!(header.modified_memory());
+    }
+
+    #[test]
+    fn modified_memory_keeps_foreign_tag() {
+        let info = ExtendedInfo {
+            flags: ExtendedFlags::WII_VC,
+            authorship_tag: *b"mupn",
+            ..Default::default()
+        };
+        let mut header = M64Header::default();
+        header.set_extended(Some(info));
+        let bytes = header.clone().into_bytes();
+
+        assert!(!header.set_modified_memory());
+        assert!(!header.modified_memory());
+        assert_eq!(header.extended(), Some(info));
+        assert_eq!(header.into_bytes(), bytes);
+    }
+}
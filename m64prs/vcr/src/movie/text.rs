@@ -0,0 +1,444 @@
+//! Line-oriented text form of .m64 files, intended for diffing and code review.
+//!
+//! The header is a block of `key = value` lines, followed by an `[inputs]` line and one line
+//! per input sample:
+//!
+//! ```text
+//! uid = 1700000000
+//! author = "Someone"
+//! ...
+//! [inputs]
+//! P1    0  127 ....SZ..........
+//! ```
+//!
+//! Each sample line holds the port, the stick's X and Y values, and one character per button
+//! (`.` when released). Header bytes that the typed fields can't represent, such as reserved
+//! fields, are written as `raw <offset> = <hex>` lines, so the conversion is lossless.
+
+use std::{
+    ffi::c_int,
+    io::{BufRead, Write},
+    mem,
+};
+
+use m64prs_sys::{ButtonFlags, Buttons};
+
+use super::{
+    error::TextMovieError, AsciiField, ControllerFlags, ExtendedFlags, M64File, M64Header,
+    StartType, StringField,
+};
+
+const INPUTS_MARKER: &str = "[inputs]";
+
+/// Button mnemonics, in bit order.
+const BUTTON_CHARS: [char; 16] = [
+    '>', '<', 'v', '^', 'S', 'Z', 'B', 'A', 'r', 'l', 'd', 'u', 'R', 'L', '1', '2',
+];
+
+impl M64File {
+    /// Writes this movie in the text format.
+    pub fn write_text<W: Write>(&self, writer: &mut W) -> Result<(), TextMovieError> {
+        let fields = header_fields(&self.header);
+
+        // Anything the fields don't capture is written as raw bytes.
+        let mut rebuilt = M64Header::default();
+        for (key, value) in &fields {
+            apply_field(&mut rebuilt, key, value).expect("generated fields should be valid");
+        }
+        // The sample count is derived from the inputs when reading.
+        rebuilt.length_samples = self.header.length_samples;
+        let original = self.header.clone().into_bytes();
+        let rebuilt = rebuilt.into_bytes();
+
+        for (key, value) in &fields {
+            writeln!(writer, "{} = {}", key, value)?;
+        }
+        let mut offset = 0;
+        while offset < original.len() {
+            if original[offset] == rebuilt[offset] {
+                offset += 1;
+                continue;
+            }
+            let start = offset;
+            while offset < original.len() && original[offset] != rebuilt[offset] {
+                offset += 1;
+            }
+            let hex: String = original[start..offset]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            writeln!(writer, "raw {:#05x} = {}", start, hex)?;
+        }
+
+        writeln!(writer, "{}", INPUTS_MARKER)?;
+        let ports = present_ports(self.header.controller_flags);
+        for (sample, port) in self.inputs.iter().zip(ports.iter().cycle()) {
+            let buttons: String = BUTTON_CHARS
+                .iter()
+                .enumerate()
+                .map(|(bit, &c)| {
+                    if sample.button_bits.bits() & (1 << bit) != 0 {
+                        c
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            writeln!(
+                writer,
+                "P{} {:4} {:4} {}",
+                port + 1,
+                sample.x_axis,
+                sample.y_axis,
+                buttons
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a movie from the text format.
+    pub fn read_text<R: BufRead>(reader: R) -> Result<Self, TextMovieError> {
+        let mut header = M64Header::default();
+        let mut raw_patches: Vec<(usize, Vec<u8>)> = Vec::new();
+        let mut inputs: Option<Vec<Buttons>> = None;
+        let mut ports = Vec::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_no = index + 1;
+            let syntax = |reason: &str| TextMovieError::Syntax {
+                line: line_no,
+                reason: reason.to_owned(),
+            };
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let Some(inputs) = inputs.as_mut() else {
+                if trimmed == INPUTS_MARKER {
+                    // Raw bytes may change the controller flags, so apply them now.
+                    let mut bytes = header.clone().into_bytes();
+                    for (offset, data) in &raw_patches {
+                        bytes[*offset..(*offset + data.len())].copy_from_slice(data);
+                    }
+                    header = M64Header::from_bytes(bytes);
+                    ports = present_ports(header.controller_flags);
+                    inputs = Some(Vec::new());
+                    continue;
+                }
+
+                let (key, value) = trimmed
+                    .split_once('=')
+                    .ok_or_else(|| syntax("expected `key = value`"))?;
+                let (key, value) = (key.trim(), value.trim());
+
+                if let Some(offset) = key.strip_prefix("raw ") {
+                    let offset = parse_int::<usize>(offset.trim())
+                        .ok_or_else(|| syntax("invalid raw offset"))?;
+                    let data = parse_hex(value).ok_or_else(|| syntax("invalid hex data"))?;
+                    if offset + data.len() > mem::size_of::<M64Header>() {
+                        return Err(syntax("raw data extends past the header"));
+                    }
+                    raw_patches.push((offset, data));
+                } else {
+                    apply_field(&mut header, key, value).map_err(syntax)?;
+                }
+                continue;
+            };
+
+            let mut parts = trimmed.split_whitespace();
+            let expected_port = ports
+                .get(inputs.len() % ports.len().max(1))
+                .ok_or_else(|| syntax("movie has no controllers"))?;
+            if parts.next() != Some(&format!("P{}", expected_port + 1)) {
+                return Err(syntax(&format!(
+                    "expected a sample for port {}",
+                    expected_port + 1
+                )));
+            }
+            let x_axis = parts
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| syntax("invalid X axis value"))?;
+            let y_axis = parts
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| syntax("invalid Y axis value"))?;
+            let buttons = parts.next().ok_or_else(|| syntax("missing buttons"))?;
+            if parts.next().is_some() || buttons.chars().count() != BUTTON_CHARS.len() {
+                return Err(syntax("expected 16 button characters"));
+            }
+            let mut bits = 0u16;
+            for (bit, (c, expected)) in buttons.chars().zip(BUTTON_CHARS).enumerate() {
+                match c {
+                    '.' => (),
+                    c if c == expected => bits |= 1 << bit,
+                    _ => return Err(syntax(&format!("unexpected button character {:?}", c))),
+                }
+            }
+
+            inputs.push(Buttons {
+                button_bits: ButtonFlags::from_bits_retain(bits),
+                x_axis,
+                y_axis,
+            });
+        }
+
+        let inputs = inputs.ok_or(TextMovieError::MissingInputs)?;
+        header.length_samples = inputs.len().try_into().unwrap_or(u32::MAX);
+
+        Ok(Self { header, inputs })
+    }
+}
+
+/// Lists the typed header fields, formatted as they appear in the text format.
+fn header_fields(header: &M64Header) -> Vec<(&'static str, String)> {
+    let extended = header.extended_version != 0;
+    let mut fields = vec![
+        ("version", header.version.to_string()),
+        ("uid", header.uid.to_string()),
+        ("length_vis", header.length_vis.to_string()),
+        ("rerecord_count", header.rerecords().to_string()),
+        ("vis_per_second", header.vis_per_second.to_string()),
+        ("num_controllers", header.num_controllers.to_string()),
+        ("start_type", start_type_name(header.start_flags)),
+        (
+            "controller_flags",
+            format!("{:#06x}", header.controller_flags.bits()),
+        ),
+        ("extended_version", header.extended_version.to_string()),
+    ];
+    if extended {
+        fields.extend([
+            (
+                "extended_flags",
+                format!("{:#04x}", header.extended_flags.bits()),
+            ),
+            (
+                "authorship_tag",
+                quote(
+                    &String::from_utf8_lossy(&header.extended_data.authorship_tag)
+                        .trim_end_matches('\0')
+                        .replace('\u{fffd}', ""),
+                ),
+            ),
+            (
+                "bruteforce_extra_data",
+                header.extended_data.bruteforce_extra_data.to_string(),
+            ),
+        ]);
+    }
+    fields.extend([
+        ("rom_name", quote(header.rom_name.try_read().unwrap_or(""))),
+        ("rom_crc", format!("{:#010x}", header.rom_crc)),
+        ("rom_cc", format!("{:#06x}", header.rom_cc)),
+        (
+            "graphics_plugin",
+            quote(header.graphics_plugin.try_read().unwrap_or("")),
+        ),
+        (
+            "audio_plugin",
+            quote(header.audio_plugin.try_read().unwrap_or("")),
+        ),
+        (
+            "input_plugin",
+            quote(header.input_plugin.try_read().unwrap_or("")),
+        ),
+        (
+            "rsp_plugin",
+            quote(header.rsp_plugin.try_read().unwrap_or("")),
+        ),
+        ("author", quote(header.author.try_read().unwrap_or(""))),
+        (
+            "description",
+            quote(header.description.try_read().unwrap_or("")),
+        ),
+    ]);
+    fields
+}
+
+/// Sets a typed header field from its text representation.
+fn apply_field(header: &mut M64Header, key: &str, value: &str) -> Result<(), &'static str> {
+    fn int<T: TryFrom<u64>>(value: &str) -> Result<T, &'static str> {
+        parse_int(value).ok_or("invalid integer")
+    }
+    fn ascii<const N: usize>(field: &mut AsciiField<N>, value: &str) -> Result<(), &'static str> {
+        field
+            .try_write(unquote(value).ok_or("invalid string")?)
+            .map_err(|_| "string is not ASCII or is too long")
+    }
+    fn string<const N: usize>(field: &mut StringField<N>, value: &str) -> Result<(), &'static str> {
+        field
+            .try_write(unquote(value).ok_or("invalid string")?)
+            .map_err(|_| "string is too long")
+    }
+
+    match key {
+        "version" => header.version = int(value)?,
+        "uid" => header.uid = int(value)?,
+        "length_vis" => header.length_vis = int(value)?,
+        "rerecord_count" => {
+            let rerecords: u64 = int(value)?;
+            header.rerecord_count = rerecords as u32;
+            header.extended_data.rerecord_count_high = (rerecords >> 32) as u32;
+        }
+        "vis_per_second" => header.vis_per_second = int(value)?,
+        "num_controllers" => header.num_controllers = int(value)?,
+        "start_type" => {
+            header.start_flags = match value {
+                "snapshot" => StartType::FROM_SNAPSHOT,
+                "reset" => StartType::FROM_RESET,
+                "eeprom" => StartType::FROM_EEPROM,
+                other => StartType(int(other)?),
+            }
+        }
+        "controller_flags" => {
+            header.controller_flags = ControllerFlags::from_bits_retain(int(value)?)
+        }
+        "extended_version" => header.extended_version = int(value)?,
+        "extended_flags" => header.extended_flags = ExtendedFlags::from_bits_retain(int(value)?),
+        "authorship_tag" => {
+            let tag = unquote(value).ok_or("invalid string")?;
+            if tag.len() > 4 {
+                return Err("authorship tag is too long");
+            }
+            header.extended_data.authorship_tag = [0; 4];
+            header.extended_data.authorship_tag[..tag.len()].copy_from_slice(tag.as_bytes());
+        }
+        "bruteforce_extra_data" => header.extended_data.bruteforce_extra_data = int(value)?,
+        "rom_name" => ascii(&mut header.rom_name, value)?,
+        "rom_crc" => header.rom_crc = int(value)?,
+        "rom_cc" => header.rom_cc = int(value)?,
+        "graphics_plugin" => ascii(&mut header.graphics_plugin, value)?,
+        "audio_plugin" => ascii(&mut header.audio_plugin, value)?,
+        "input_plugin" => ascii(&mut header.input_plugin, value)?,
+        "rsp_plugin" => ascii(&mut header.rsp_plugin, value)?,
+        "author" => string(&mut header.author, value)?,
+        "description" => string(&mut header.description, value)?,
+        _ => return Err("unknown header field"),
+    }
+    Ok(())
+}
+
+fn start_type_name(start_type: StartType) -> String {
+    match start_type {
+        StartType::FROM_SNAPSHOT => "snapshot".to_owned(),
+        StartType::FROM_RESET => "reset".to_owned(),
+        StartType::FROM_EEPROM => "eeprom".to_owned(),
+        StartType(other) => format!("{:#06x}", other),
+    }
+}
+
+fn present_ports(flags: ControllerFlags) -> Vec<c_int> {
+    (0..4).filter(|&port| flags.port_present(port)).collect()
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal integer.
+fn parse_int<T: TryFrom<u64>>(value: &str) -> Option<T> {
+    let value = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => value.parse().ok()?,
+    };
+    value.try_into().ok()
+}
+
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Quotes a string, escaping it the same way Rust string literals are.
+fn quote(value: &str) -> String {
+    format!("{:?}", value)
+}
+
+/// Reverses [`quote`].
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        result.push(match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let (hex, rest) = rest.split_once('}')?;
+                let c = char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+                chars = rest.chars();
+                c
+            }
+            _ => return None,
+        });
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use m64prs_sys::{ButtonFlags, Buttons};
+
+    use crate::movie::{ControllerFlags, ExtendedInfo, M64File, M64Header, StartType};
+
+    #[test]
+    fn round_trip() {
+        let mut header = M64Header {
+            uid: 1234,
+            length_vis: 500,
+            start_flags: StartType::FROM_RESET,
+            controller_flags: ControllerFlags::P1_PRESENT | ControllerFlags::P3_PRESENT,
+            ..Default::default()
+        };
+        header.set_extended(Some(ExtendedInfo {
+            authorship_tag: *b"TEST",
+            ..Default::default()
+        }));
+        header.set_rerecords(u32::MAX as u64 + 5);
+        header
+            .author
+            .try_write("Someone \"quoted\"\nand ✓")
+            .unwrap();
+        header.rom_name.try_write("SUPER MARIO 64").unwrap();
+
+        let inputs: Vec<Buttons> = (0..20u16)
+            .map(|i| Buttons {
+                button_bits: ButtonFlags::from_bits_retain(i.wrapping_mul(0x0f0f)),
+                x_axis: (i as i8).wrapping_mul(13),
+                y_axis: -(i as i8),
+            })
+            .collect();
+        header.length_samples = inputs.len() as u32;
+
+        // Junk in a reserved field must survive as well.
+        let mut bytes = header.into_bytes();
+        bytes[0x100] = 0xAB;
+        let movie = M64File {
+            header: M64Header::from_bytes(bytes),
+            inputs,
+        };
+
+        let mut text = Vec::new();
+        movie.write_text(&mut text).unwrap();
+        let parsed = M64File::read_text(&text[..]).unwrap();
+
+        assert_eq!(parsed, movie);
+    }
+}